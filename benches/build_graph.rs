@@ -11,14 +11,75 @@ fn build_graph(c: &mut Criterion) {
     let mut group = c.benchmark_group("build graph");
 
     group.bench_function("fast", |b| {
-        b.iter(|| black_box(graph.build(ANCHORS, BOUNDING_BOXES, false)))
+        b.iter(|| graph.build(black_box(ANCHORS), black_box(BOUNDING_BOXES), false))
     });
 
     group.bench_function("minimal", |b| {
-        b.iter(|| black_box(graph.build(ANCHORS, BOUNDING_BOXES, true)))
+        b.iter(|| graph.build(black_box(ANCHORS), black_box(BOUNDING_BOXES), true))
     });
 }
 
+fn update_graph(c: &mut Criterion) {
+    let mut graph = Graph::default();
+    let mut group = c.benchmark_group("update graph");
+
+    // Moves the first component back and forth.
+    let index = BoundingBoxIndex::from_u32(0).unwrap();
+    let anchors: Vec<_> = ANCHORS
+        .iter()
+        .copied()
+        .filter(|anchor| anchor.bounding_box == index)
+        .collect();
+    let moved_anchors: Vec<_> = anchors
+        .iter()
+        .map(|&anchor| Anchor {
+            position: Point {
+                x: anchor.position.x + 40,
+                y: anchor.position.y - 20,
+            },
+            ..anchor
+        })
+        .collect();
+
+    let bounding_box = BOUNDING_BOXES[0];
+    let moved_bounding_box = BoundingBox {
+        center: Point {
+            x: bounding_box.center.x + 40,
+            y: bounding_box.center.y - 20,
+        },
+        ..bounding_box
+    };
+
+    for (name, minimal) in [("fast", false), ("minimal", true)] {
+        graph.build(ANCHORS, BOUNDING_BOXES, minimal);
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                graph
+                    .update(
+                        &moved_anchors,
+                        &anchors,
+                        &[BoundingBoxChange {
+                            index,
+                            bounding_box: moved_bounding_box,
+                        }],
+                    )
+                    .unwrap();
+                graph
+                    .update(
+                        &anchors,
+                        &moved_anchors,
+                        &[BoundingBoxChange {
+                            index,
+                            bounding_box,
+                        }],
+                    )
+                    .unwrap();
+            })
+        });
+    }
+}
+
 #[derive(Default)]
 struct Net {
     endpoints: Vec<digilogic_routing::Endpoint<'static>>,
@@ -47,7 +108,7 @@ fn create_nets() -> Vec<Net> {
 
     for endpoint in ENDPOINTS {
         if endpoint.net_id >= nets.len() {
-            nets.resize_with(endpoint.net_id + 1, || Net::default());
+            nets.resize_with(endpoint.net_id + 1, Net::default);
         }
 
         nets[endpoint.net_id]
@@ -64,7 +125,7 @@ fn create_nets() -> Vec<Net> {
 
         match &mut nets[waypoint.net_id].endpoints[closest_endpoint].waypoints {
             Cow::Borrowed(_) => unreachable!(),
-            Cow::Owned(waypoints) => waypoints.push(waypoint.position),
+            Cow::Owned(waypoints) => waypoints.push(waypoint.position.into()),
         }
     }
 
@@ -78,18 +139,25 @@ fn route(c: &mut Criterion) {
 
     c.bench_function("route", |b| {
         b.iter(|| {
-            black_box(nets.par_iter().for_each(|net| {
+            nets.par_iter().for_each(|net| {
                 let mut vertices = [MaybeUninit::uninit(); 128];
                 let mut wire_views = [MaybeUninit::uninit(); 32];
                 black_box(
                     graph
-                        .connect_net(&net.endpoints, &mut vertices, &mut wire_views, true)
+                        .connect_net(
+                            &net.endpoints,
+                            &mut vertices,
+                            &mut wire_views,
+                            true,
+                            NetTopology::RootAndBranches,
+                            &CostModel::default(),
+                        )
                         .unwrap(),
                 );
-            }));
+            });
         })
     });
 }
 
-criterion_group!(benches, build_graph, route);
+criterion_group!(benches, build_graph, update_graph, route);
 criterion_main!(benches);
@@ -0,0 +1,81 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use digilogic_routing::*;
+use std::mem::MaybeUninit;
+
+include!("../test_data/graph.rs");
+
+fn find_path(c: &mut Criterion) {
+    let mut graph = Graph::default();
+    graph.build(ANCHORS, BOUNDING_BOXES, false);
+    let mut group = c.benchmark_group("find path");
+
+    // Pairs the first and last endpoints, which lie far apart on the board.
+    let pairs: Vec<_> = ENDPOINTS
+        .iter()
+        .zip(ENDPOINTS.iter().rev())
+        .take(16)
+        .map(|(start, end)| (start.position, end.position))
+        .collect();
+
+    for (name, bidirectional) in [("unidirectional", false), ("bidirectional", true)] {
+        let options = SearchOptions { bidirectional };
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for &(start, end) in &pairs {
+                    black_box(graph.find_path_with_options(
+                        start,
+                        end,
+                        black_box(&CostModel::default()),
+                        &options,
+                    ));
+                }
+            })
+        });
+    }
+
+    // Routing picks the bidirectional search by itself for nets between two endpoints.
+    group.bench_function("connect net", |b| {
+        b.iter(|| {
+            for &(start, end) in &pairs {
+                let endpoints = [start, end].map(|position| digilogic_routing::Endpoint {
+                    position,
+                    waypoints: Default::default(),
+                });
+
+                let mut vertices = [MaybeUninit::uninit(); 128];
+                let mut wire_views = [MaybeUninit::uninit(); 32];
+                black_box(graph.connect_net(
+                    endpoints.as_slice(),
+                    &mut vertices,
+                    &mut wire_views,
+                    false,
+                    NetTopology::RootAndBranches,
+                    black_box(&CostModel::default()),
+                ))
+                .unwrap();
+            }
+        })
+    });
+
+    // Searches for the closest of many ends, which has to keep the heuristic cheap.
+    let ends: Vec<_> = ENDPOINTS
+        .iter()
+        .skip(1)
+        .map(|endpoint| endpoint.position)
+        .collect();
+
+    group.bench_function("many ends", |b| {
+        b.iter(|| {
+            black_box(graph.find_path_multi(
+                ENDPOINTS[0].position,
+                black_box(&ends),
+                false,
+                &CostModel::default(),
+            ))
+        })
+    });
+}
+
+criterion_group!(benches, find_path);
+criterion_main!(benches);
@@ -33,6 +33,115 @@ typedef uint8_t RT_Directions;
 #define RT_WireView_ends_in_junction(wire_view) ((bool)(((wire_view) >> 15) & 0x1))
 #define RT_WireView_is_root(wire_view) ((bool)(((wire_view) >> 14) & 0x1))
 
+/**
+ * The kind of problem a [`Diagnostic`] describes.
+ */
+enum RT_DiagnosticKind {
+    /**
+     * The waypoint at `end` could not be reached from the wire starting at `start`, and was skipped.
+     */
+    RT_DIAGNOSTIC_KIND_UNREACHABLE_WAYPOINT = 0,
+    /**
+     * No path between `start` and `end` was found, so a fallback wire was generated that may cross obstacles.
+     */
+    RT_DIAGNOSTIC_KIND_FALLBACK_WIRE = 1,
+    /**
+     * The endpoint at `start`, or one of its waypoints at `end`, does not lie on a node of the graph.
+     * `start` and `end` are the same if the endpoint itself is invalid. The net is not routed.
+     */
+    RT_DIAGNOSTIC_KIND_INVALID_ENDPOINT = 2,
+};
+typedef uint32_t RT_DiagnosticKind;
+
+/**
+ * Which orientation the wires of a net prefer to run in.
+ */
+enum RT_DirectionPreference {
+    /**
+     * Horizontal and vertical segments are treated the same.
+     */
+    RT_DIRECTION_PREFERENCE_NONE = 0,
+    /**
+     * Vertical segments cost twice as much per unit of length.
+     */
+    RT_DIRECTION_PREFERENCE_HORIZONTAL = 1,
+    /**
+     * Horizontal segments cost twice as much per unit of length.
+     */
+    RT_DIRECTION_PREFERENCE_VERTICAL = 2,
+};
+typedef uint32_t RT_DirectionPreference;
+
+/**
+ * Why an anchor has no way out of its bounding box.
+ */
+enum RT_EscapeBlockedReason {
+    /**
+     * The anchor does not connect in any direction.
+     */
+    RT_ESCAPE_BLOCKED_REASON_NO_CONNECT_DIRECTIONS = 0,
+    /**
+     * In every direction the anchor connects in, another bounding box is too close to fit a wire in between.
+     */
+    RT_ESCAPE_BLOCKED_REASON_OBSTRUCTED = 1,
+};
+typedef uint32_t RT_EscapeBlockedReason;
+
+/**
+ * How strictly wires are kept out of a keep-out zone.
+ */
+enum RT_KeepOutKind {
+    /**
+     * Wires never enter the zone.
+     */
+    RT_KEEP_OUT_KIND_HARD = 0,
+    /**
+     * Wires may enter the zone, but paths through it are more expensive.
+     */
+    RT_KEEP_OUT_KIND_SOFT = 1,
+};
+typedef uint32_t RT_KeepOutKind;
+
+/**
+ * How completely a net was routed.
+ */
+enum RT_NetStatus {
+    /**
+     * All endpoints and waypoints were connected through the graph.
+     */
+    RT_NET_STATUS_ROUTED = 0,
+    /**
+     * All endpoints were connected through the graph, but some waypoints could not be reached and were skipped.
+     */
+    RT_NET_STATUS_PARTIAL = 1,
+    /**
+     * At least one wire could not be routed through the graph and was replaced by a fallback wire.
+     */
+    RT_NET_STATUS_FALLBACK = 2,
+    /**
+     * The net could not be routed at all and has no wires.
+     */
+    RT_NET_STATUS_FAILED = 3,
+};
+typedef uint32_t RT_NetStatus;
+
+/**
+ * The shape of the wire tree connecting the endpoints of a net.
+ */
+enum RT_NetTopology {
+    /**
+     * A root wire between the two most distant endpoints, with all other endpoints branching off of it in order.
+     */
+    RT_NET_TOPOLOGY_ROOT_AND_BRANCHES = 0,
+    /**
+     * A rectilinear Steiner tree.
+     * The wires join at Steiner points found with the iterated 1-Steiner heuristic.
+     * Nets with waypoints or many endpoints instead always connect the endpoint closest to the already routed wires next.
+     */
+    RT_NET_TOPOLOGY_STEINER = 1,
+};
+typedef uint32_t RT_NetTopology;
+
 enum RT_Result {
     RT_RESULT_SUCCESS = 0,
     RT_RESULT_NULL_POINTER_ERROR = 1,
@@ -45,8 +154,36 @@ enum RT_Result {
 };
 typedef uint32_t RT_Result;
 
+/**
+ * The order in which a wire visits the waypoints of its endpoints.
+ */
+enum RT_WaypointOrder {
+    /**
+     * The wire always continues to the waypoint closest to where it currently is.
+     */
+    RT_WAYPOINT_ORDER_NEAREST = 0,
+    /**
+     * The wire visits the waypoints in the order they are listed, starting at the endpoint they belong to.
+     * The waypoints of the endpoint a root wire ends in are visited in reverse.
+     */
+    RT_WAYPOINT_ORDER_GIVEN = 1,
+    /**
+     * The wire visits the waypoints in the order that makes the tour through them the shortest.
+     */
+    RT_WAYPOINT_ORDER_OPTIMIZED = 2,
+};
+typedef uint32_t RT_WaypointOrder;
+
 typedef struct RT_Graph RT_Graph;
 
+/**
+ * Keeps the routing results of a set of nets,
+ * so after an edit only the nets affected by it have to be routed again.
+ *
+ * Nets are identified by their index in the list passed to [`RoutingSession::set_nets`].
+ */
+typedef struct RT_RoutingSession RT_RoutingSession;
+
 typedef struct RT_Point {
     /**
      * The X coordinate of the point.
@@ -100,6 +237,78 @@ typedef struct RT_Slice_BoundingBox {
     size_t len;
 } RT_Slice_BoundingBox;
 
+/**
+ * Controls the tracks wires can run on around bounding boxes.
+ */
+typedef struct RT_TrackSettings {
+    /**
+     * The number of parallel tracks on every side of a bounding box.
+     */
+    uint32_t track_count;
+    /**
+     * The minimum distance between a bounding box and its first track, as well as between two neighboring tracks.
+     */
+    uint32_t wire_spacing;
+    /**
+     * If not 0, tracks are only placed on multiples of this value.
+     */
+    uint32_t grid_pitch;
+    /**
+     * The maximum number of additional tracks in the channel between two bounding boxes facing each other,
+     * placed after the tracks around the left or top one, as long as they stay clear of the tracks around the other one.
+     */
+    uint32_t channel_track_count;
+} RT_TrackSettings;
+
+typedef struct RT_Slice_BoundingBoxIndex {
+    const RT_BoundingBoxIndex *ptr;
+    size_t len;
+} RT_Slice_BoundingBoxIndex;
+
+/**
+ * A region wires should stay out of that is not a component, like a title block or a comment frame.
+ */
+typedef struct RT_KeepOut {
+    /**
+     * The extents of the zone.
+     */
+    struct RT_BoundingBox bounding_box;
+    /**
+     * How strictly wires are kept out of the zone, one of the `RT_KeepOutKind` values.
+     */
+    uint32_t kind;
+    /**
+     * For soft keep-outs, the factor the cost of path segments running through the zone is multiplied with.
+     * The cost of a whole edge of the graph is multiplied, even if only part of the edge lies inside the zone.
+     */
+    uint32_t cost_multiplier;
+    /**
+     * Whether to place anchors at the corners of the tracks around the zone, like around bounding boxes.
+     */
+    bool auto_anchors;
+} RT_KeepOut;
+
+typedef struct RT_Slice_KeepOut {
+    const struct RT_KeepOut *ptr;
+    size_t len;
+} RT_Slice_KeepOut;
+
+typedef struct RT_BoundingBoxChange {
+    /**
+     * The index of the bounding box that changed.
+     */
+    RT_BoundingBoxIndex index;
+    /**
+     * The new extents of the bounding box.
+     */
+    struct RT_BoundingBox bounding_box;
+} RT_BoundingBoxChange;
+
+typedef struct RT_Slice_BoundingBoxChange {
+    const struct RT_BoundingBoxChange *ptr;
+    size_t len;
+} RT_Slice_BoundingBoxChange;
+
 typedef uint32_t RT_NodeIndex;
 
 typedef struct RT_NeighborList {
@@ -146,6 +355,59 @@ typedef struct RT_Slice_Node {
     size_t len;
 } RT_Slice_Node;
 
+/**
+ * An anchor inside its bounding box that has no way out of it.
+ */
+typedef struct RT_BlockedAnchor {
+    /**
+     * The position of the anchor.
+     */
+    struct RT_Point position;
+    /**
+     * Why the anchor has no way out of its bounding box.
+     */
+    RT_EscapeBlockedReason reason;
+} RT_BlockedAnchor;
+
+typedef struct RT_Slice_BlockedAnchor {
+    const struct RT_BlockedAnchor *ptr;
+    size_t len;
+} RT_Slice_BlockedAnchor;
+
+/**
+ * Rules the wires of a single net have to follow on top of the ones every net follows.
+ *
+ * The default does not constrain the net in any way.
+ */
+typedef struct RT_NetConstraints {
+    /**
+     * The maximum number of bends of a wire between two consecutive waypoints or endpoints.
+     *
+     * 0 means that the number of bends is not limited, so wires without any bends cannot be requested.
+     * Paths exceeding the limit are discarded during the search, so a net that cannot satisfy it gets a fallback wire instead.
+     * The search tracks every node separately for every number of bends up to the limit, so its memory grows with the limit.
+     * Limits above [`NetConstraints::MAX_BEND_LIMIT`] are rejected with [`RoutingError::InvalidConstraints`].
+     */
+    uint32_t max_bends;
+    /**
+     * Which orientation the wires of the net prefer to run in.
+     */
+    RT_DirectionPreference direction_preference;
+    /**
+     * If greater than 1, the factor the cost of the paths of the net is multiplied with before congestion costs are added,
+     * so the net avoids the wires of other nets less.
+     */
+    uint32_t cost_multiplier;
+    /**
+     * The order in which the wires of the net visit waypoints.
+     */
+    RT_WaypointOrder waypoint_order;
+} RT_NetConstraints;
+/**
+ * The largest bend limit a net can have.
+ */
+#define RT_NetConstraints_MAX_BEND_LIMIT 16
+
 typedef struct RT_Net {
     /**
      * The offset into the endpoint list at which the endpoints of this net start.
@@ -155,6 +417,14 @@ typedef struct RT_Net {
      * The number of endpoints in the net.
      */
     uint32_t endpoint_count;
+    /**
+     * The rules the wires of the net have to follow.
+     */
+    struct RT_NetConstraints constraints;
+    /**
+     * When routing by priority, nets with a higher priority are routed first.
+     */
+    uint32_t priority;
 } RT_Net;
 
 typedef struct RT_Slice_Net {
@@ -182,10 +452,48 @@ typedef struct RT_Slice_Endpoint {
     size_t len;
 } RT_Slice_Endpoint;
 
-typedef struct RT_Slice_Point {
-    const struct RT_Point *ptr;
+typedef struct RT_Waypoint {
+    /**
+     * The position of the waypoint.
+     */
+    struct RT_Point position;
+    /**
+     * The direction a wire has to travel in while passing through the waypoint, as seen when going away from the endpoint.
+     * If empty, the waypoint can be passed through in any direction.
+     */
+    RT_Directions direction;
+} RT_Waypoint;
+
+typedef struct RT_Slice_Waypoint {
+    const struct RT_Waypoint *ptr;
     size_t len;
-} RT_Slice_Point;
+} RT_Slice_Waypoint;
+
+/**
+ * Weights the path finder uses to decide which path is the shortest.
+ */
+typedef struct RT_CostModel {
+    /**
+     * The cost per unit of length of a path.
+     */
+    uint32_t length_weight;
+    /**
+     * The additional cost per unit of length of a path segment that starts with a bend.
+     */
+    uint32_t bend_length_weight;
+    /**
+     * The additional cost of every bend in a path.
+     */
+    uint32_t bend_penalty;
+    /**
+     * How close a path has to come to a bounding box to be considered near it.
+     */
+    uint32_t bounding_box_clearance;
+    /**
+     * The additional cost per unit of length of a path running near a bounding box.
+     */
+    uint32_t bounding_box_penalty;
+} RT_CostModel;
 
 typedef struct RT_Vertex {
     /**
@@ -230,6 +538,54 @@ typedef struct RT_MutSlice_NetView {
     size_t len;
 } RT_MutSlice_NetView;
 
+typedef struct RT_MutSlice_NetStatus {
+    RT_NetStatus *ptr;
+    size_t len;
+} RT_MutSlice_NetStatus;
+
+/**
+ * A problem that occurred while routing a net.
+ */
+typedef struct RT_Diagnostic {
+    RT_DiagnosticKind kind;
+    struct RT_Point start;
+    struct RT_Point end;
+} RT_Diagnostic;
+
+/**
+ * A problem that occurred while routing one of multiple nets.
+ */
+typedef struct RT_NetDiagnostic {
+    /**
+     * The index of the net the problem occurred in.
+     */
+    uint32_t net;
+    struct RT_Diagnostic diagnostic;
+} RT_NetDiagnostic;
+
+typedef struct RT_MutSlice_NetDiagnostic {
+    struct RT_NetDiagnostic *ptr;
+    size_t len;
+} RT_MutSlice_NetDiagnostic;
+
+/**
+ * Receives the status of every net and the problems that occurred while routing the nets.
+ */
+typedef struct RT_DiagnosticsBuffer {
+    /**
+     * A list to write the status of every net into, in the order of the nets.
+     */
+    struct RT_MutSlice_NetStatus net_statuses;
+    /**
+     * A list to write the problems that occurred into. Problems that do not fit are dropped.
+     */
+    struct RT_MutSlice_NetDiagnostic diagnostics;
+    /**
+     * Receives the number of problems that occurred, including the ones that did not fit into `diagnostics`.
+     */
+    size_t diagnostic_count;
+} RT_DiagnosticsBuffer;
+
 typedef struct RT_Slice_NodeIndex {
     const RT_NodeIndex *ptr;
     size_t len;
@@ -253,6 +609,63 @@ typedef struct RT_ReplayCallbacks {
     void (*routing_end_wire)(void*);
 } RT_ReplayCallbacks;
 
+/**
+ * Costs for running wires close to wires of other nets.
+ */
+typedef struct RT_CongestionCosts {
+    /**
+     * The cost per unit of length of running on top of a wire of another net.
+     */
+    uint32_t overlap_penalty;
+    /**
+     * Whether running on top of a wire of another net is not allowed at all.
+     */
+    bool forbid_overlap;
+    /**
+     * The cost of crossing a wire of another net that passes straight through the crossing.
+     * Paths that only touch a wire of another net, at a bend of either wire or where one of them ends,
+     * are charged `overlap_penalty` once instead.
+     */
+    uint32_t crossing_penalty;
+} RT_CongestionCosts;
+
+/**
+ * Settings for negotiating congestion between nets by ripping up and rerouting them.
+ */
+typedef struct RT_NegotiationSettings {
+    /**
+     * The maximum number of times congested nets are ripped up and routed again.
+     */
+    uint32_t max_iterations;
+    /**
+     * How much the cost per unit of length of running on top of a wire of another net increases every iteration.
+     */
+    uint32_t overlap_penalty_growth;
+    /**
+     * The cost per unit of length added to a channel every iteration it is used by more than one net.
+     */
+    uint32_t history_penalty;
+} RT_NegotiationSettings;
+
+/**
+ * Statistics about a negotiated routing run.
+ */
+typedef struct RT_NegotiationOutcome {
+    /**
+     * The number of rip-up and reroute iterations that were performed.
+     */
+    uint32_t iterations;
+    /**
+     * The number of channels still used by more than one net after the last iteration.
+     */
+    uint32_t overused_channel_count;
+} RT_NegotiationOutcome;
+
+typedef struct RT_Slice_Diagnostic {
+    const struct RT_Diagnostic *ptr;
+    size_t len;
+} RT_Slice_Diagnostic;
+
 #define RT_INVALID_NODE_INDEX UINT32_MAX
 
 #define RT_INVALID_BOUNDING_BOX_INDEX UINT32_MAX
@@ -312,6 +725,102 @@ RT_Result RT_graph_build(struct RT_Graph *graph,
                          struct RT_Slice_BoundingBox bounding_boxes,
                          bool minimal);
 
+/**
+ * Builds a graph with multiple parallel tracks around every bounding box.
+ *
+ * **Parameters**
+ * `graph`: The graph to build.
+ * `anchors`: A list of anchor points to build the graph from.
+ * `bounding_boxes`: A list of bounding boxes to build the graph from.
+ * `minimal`: Whether to spend more processing time to ensure the graph is minimal.
+ * `tracks`: The number, spacing and grid alignment of the tracks around and between bounding boxes.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `anchors.ptr` or `bounding_boxes.ptr` was `NULL`.
+ */
+RT_MUST_USE
+RT_Result RT_graph_build_with_tracks(struct RT_Graph *graph,
+                                     struct RT_Slice_Anchor anchors,
+                                     struct RT_Slice_BoundingBox bounding_boxes,
+                                     bool minimal,
+                                     struct RT_TrackSettings tracks);
+
+/**
+ * Builds a graph out of components that consist of more than one bounding box.
+ *
+ * **Parameters**
+ * `graph`: The graph to build.
+ * `anchors`: A list of anchor points to build the graph from.
+ * `bounding_boxes`: A list of bounding boxes to build the graph from.
+ * `components`: For every bounding box, the index of the bounding box representing the component it is part of,
+ * or `RT_INVALID_BOUNDING_BOX_INDEX` if it is a component on its own. Chains of bounding boxes are resolved
+ * to the bounding box at their end, and indices past the end of `bounding_boxes` are treated as invalid. May be empty.
+ * `minimal`: Whether to spend more processing time to ensure the graph is minimal.
+ * `tracks`: The number, spacing and grid alignment of the tracks around and between bounding boxes.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `anchors.ptr`, `bounding_boxes.ptr` or `components.ptr` was `NULL`.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `components` was neither empty nor the same length as `bounding_boxes`.
+ */
+RT_MUST_USE
+RT_Result RT_graph_build_with_components(struct RT_Graph *graph,
+                                         struct RT_Slice_Anchor anchors,
+                                         struct RT_Slice_BoundingBox bounding_boxes,
+                                         struct RT_Slice_BoundingBoxIndex components,
+                                         bool minimal,
+                                         struct RT_TrackSettings tracks);
+
+/**
+ * Builds a graph with keep-out zones wires should not enter.
+ *
+ * **Parameters**
+ * `graph`: The graph to build.
+ * `anchors`: A list of anchor points to build the graph from.
+ * `bounding_boxes`: A list of bounding boxes to build the graph from.
+ * `components`: For every bounding box, the index of the bounding box representing the component it is part of,
+ * or `RT_INVALID_BOUNDING_BOX_INDEX` if it is a component on its own. Chains of bounding boxes are resolved
+ * to the bounding box at their end, and indices past the end of `bounding_boxes` are treated as invalid. May be empty.
+ * `keep_outs`: A list of hard and soft keep-out zones.
+ * `minimal`: Whether to spend more processing time to ensure the graph is minimal.
+ * `tracks`: The number, spacing and grid alignment of the tracks around and between bounding boxes.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `anchors.ptr`, `bounding_boxes.ptr`, `components.ptr` or `keep_outs.ptr` was `NULL`.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `components` was neither empty nor the same length as `bounding_boxes`,
+ * or the kind of a keep-out was not a valid `RT_KeepOutKind`.
+ */
+RT_MUST_USE
+RT_Result RT_graph_build_with_keep_outs(struct RT_Graph *graph,
+                                        struct RT_Slice_Anchor anchors,
+                                        struct RT_Slice_BoundingBox bounding_boxes,
+                                        struct RT_Slice_BoundingBoxIndex components,
+                                        struct RT_Slice_KeepOut keep_outs,
+                                        bool minimal,
+                                        struct RT_TrackSettings tracks);
+
+/**
+ * Updates a graph after anchors were added or removed, or bounding boxes changed.
+ *
+ * **Parameters**
+ * `graph`: The graph to update.
+ * `added_anchors`: A list of anchor points to add to the graph.
+ * `removed_anchors`: A list of anchor points to remove from the graph.
+ * `changed_bounding_boxes`: A list of bounding boxes to replace in the graph.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `added_anchors.ptr`, `removed_anchors.ptr` or `changed_bounding_boxes.ptr` was `NULL`.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: One of the removed anchors or changed bounding boxes did not exist in the graph.
+ */
+RT_MUST_USE
+RT_Result RT_graph_update(struct RT_Graph *graph,
+                          struct RT_Slice_Anchor added_anchors,
+                          struct RT_Slice_Anchor removed_anchors,
+                          struct RT_Slice_BoundingBoxChange changed_bounding_boxes);
+
 /**
  * Gets the nodes in a graph.
  *
@@ -325,6 +834,23 @@ RT_Result RT_graph_build(struct RT_Graph *graph,
  */
 RT_MUST_USE RT_Result RT_graph_get_nodes(const struct RT_Graph *graph, struct RT_Slice_Node *nodes);
 
+/**
+ * Gets the anchors inside bounding boxes that have no way out of them.
+ *
+ * Every other anchor inside its bounding box has at least one neighbor in a direction it connects in.
+ *
+ * **Parameters**
+ * `graph`: The graph to get the blocked anchors of.
+ * `[out] blocked_anchors`: The list of blocked anchors and why they are blocked.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph` or `blocked_anchors` was `NULL`.
+ */
+RT_MUST_USE
+RT_Result RT_graph_get_blocked_anchors(const struct RT_Graph *graph,
+                                       struct RT_Slice_BlockedAnchor *blocked_anchors);
+
 /**
  * Finds the node at a specific position in the graph.
  *
@@ -394,6 +920,9 @@ RT_MUST_USE RT_Result RT_graph_free(struct RT_Graph *graph);
  * `nets`: The list of nets to serialize.
  * `endpoints`: The list of endpoints to serialize.
  * `waypoints`: The list of waypoints to serialize.
+ * `perform_centering`: Whether to center wires in the alleys they run through.
+ * `topology`: The shape of the wire trees connecting the endpoints of each net.
+ * `cost_model`: The weights used to find the shortest paths.
  * `file_path`: The file to serialize the graph into.
  *
  * **Returns**
@@ -407,13 +936,18 @@ RT_MUST_USE
 RT_Result RT_graph_serialize_connect_nets_query(const struct RT_Graph *graph,
                                                 struct RT_Slice_Net nets,
                                                 struct RT_Slice_Endpoint endpoints,
-                                                struct RT_Slice_Point waypoints,
+                                                struct RT_Slice_Waypoint waypoints,
                                                 bool perform_centering,
+                                                RT_NetTopology topology,
+                                                struct RT_CostModel cost_model,
                                                 const char *file_path);
 
 /**
  * Connects nets in a graph.
  *
+ * The nets are routed in parallel, but their results are written into `vertices` and `wire_views` contiguously,
+ * in the order of `nets`, so the output is identical for identical input regardless of the number of threads.
+ *
  * **Parameters**
  * `graph`: The graph to connect the nets in.
  * `nets`: A list of nets to connect.
@@ -422,25 +956,32 @@ RT_Result RT_graph_serialize_connect_nets_query(const struct RT_Graph *graph,
  * `vertices`: A list to write the found vertices into.
  * `wire_views`: A list to write the found wires into.
  * `net_views`: A list to write the found nets into.
+ * `perform_centering`: Whether to center wires in the alleys they run through.
+ * `topology`: The shape of the wire trees connecting the endpoints of each net.
+ * `cost_model`: The weights used to find the shortest paths.
+ * `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
  *
  * **Returns**
  * `RT_RESULT_SUCCESS`: The operation completed successfully.
- * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr` or `net_views.ptr` was `NULL`.
- * `RT_RESULT_INVALID_OPERATION_ERROR`: One of the paths had an invalid start or end point.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.
  * `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.
  * `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.
  * `RT_RESULT_UNINITIALIZED_ERROR`: The thread pool has not been initialized yet.
- * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or a net contained fewer than 2 endpoints.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+ * a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
  */
 RT_MUST_USE
 RT_Result RT_graph_connect_nets(const struct RT_Graph *graph,
                                 struct RT_Slice_Net nets,
                                 struct RT_Slice_Endpoint endpoints,
-                                struct RT_Slice_Point waypoints,
+                                struct RT_Slice_Waypoint waypoints,
                                 struct RT_MutSlice_Vertex vertices,
                                 struct RT_MutSlice_WireView wire_views,
                                 struct RT_MutSlice_NetView net_views,
-                                bool perform_centering);
+                                bool perform_centering,
+                                RT_NetTopology topology,
+                                struct RT_CostModel cost_model,
+                                struct RT_DiagnosticsBuffer *diagnostics);
 
 /**
  * Connects nets in a graph.
@@ -453,26 +994,369 @@ RT_Result RT_graph_connect_nets(const struct RT_Graph *graph,
  * `vertices`: A list to write the found vertices into.
  * `wire_views`: A list to write the found wires into.
  * `net_views`: A list to write the found nets into.
+ * `perform_centering`: Whether to center wires in the alleys they run through.
+ * `topology`: The shape of the wire trees connecting the endpoints of each net.
+ * `cost_model`: The weights used to find the shortest paths.
  * `replay`: Callbacks for constructing a replay.
+ * `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
  *
  * **Returns**
  * `RT_RESULT_SUCCESS`: The operation completed successfully.
- * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr` or `net_views.ptr` was `NULL`.
- * `RT_RESULT_INVALID_OPERATION_ERROR`: One of the paths had an invalid start or end point.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.
  * `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.
  * `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.
  * `RT_RESULT_UNINITIALIZED_ERROR`: The thread pool has not been initialized yet.
- * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or a net contained fewer than 2 endpoints.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+ * a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
  */
 RT_MUST_USE
 RT_Result RT_graph_connect_nets_replay(const struct RT_Graph *graph,
                                        struct RT_Slice_Net nets,
                                        struct RT_Slice_Endpoint endpoints,
-                                       struct RT_Slice_Point waypoints,
+                                       struct RT_Slice_Waypoint waypoints,
                                        struct RT_MutSlice_Vertex vertices,
                                        struct RT_MutSlice_WireView wire_views,
                                        struct RT_MutSlice_NetView net_views,
                                        bool perform_centering,
-                                       struct RT_ReplayCallbacks replay);
+                                       RT_NetTopology topology,
+                                       struct RT_CostModel cost_model,
+                                       struct RT_ReplayCallbacks replay,
+                                       struct RT_DiagnosticsBuffer *diagnostics);
+
+/**
+ * Connects nets in a graph one after another, making wires avoid running on top of or crossing
+ * the wires of nets that were connected before.
+ *
+ * **Parameters**
+ * `graph`: The graph to connect the nets in.
+ * `nets`: A list of nets to connect.
+ * `endpoints`: A list of endpoints.
+ * `waypoints`: A list of waypoints.
+ * `vertices`: A list to write the found vertices into.
+ * `wire_views`: A list to write the found wires into.
+ * `net_views`: A list to write the found nets into.
+ * `perform_centering`: Whether to center wires in the alleys they run through.
+ * `topology`: The shape of the wire trees connecting the endpoints of each net.
+ * `cost_model`: The weights used to find the shortest paths.
+ * `congestion_costs`: The costs of running on top of or crossing wires of other nets.
+ * `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.
+ * `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.
+ * `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+ * a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+ */
+RT_MUST_USE
+RT_Result RT_graph_connect_nets_congestion_aware(const struct RT_Graph *graph,
+                                                 struct RT_Slice_Net nets,
+                                                 struct RT_Slice_Endpoint endpoints,
+                                                 struct RT_Slice_Waypoint waypoints,
+                                                 struct RT_MutSlice_Vertex vertices,
+                                                 struct RT_MutSlice_WireView wire_views,
+                                                 struct RT_MutSlice_NetView net_views,
+                                                 bool perform_centering,
+                                                 RT_NetTopology topology,
+                                                 struct RT_CostModel cost_model,
+                                                 struct RT_CongestionCosts congestion_costs,
+                                                 struct RT_DiagnosticsBuffer *diagnostics);
+
+/**
+ * Connects nets in a graph, repeatedly ripping up and rerouting the nets running on top of each other
+ * until no channel is used by more than one net or the iteration limit is reached.
+ *
+ * **Parameters**
+ * `graph`: The graph to connect the nets in.
+ * `nets`: A list of nets to connect.
+ * `endpoints`: A list of endpoints.
+ * `waypoints`: A list of waypoints.
+ * `vertices`: A list to write the found vertices into.
+ * `wire_views`: A list to write the found wires into.
+ * `net_views`: A list to write the found nets into.
+ * `perform_centering`: Whether to center wires in the alleys they run through.
+ * `topology`: The shape of the wire trees connecting the endpoints of each net.
+ * `cost_model`: The weights used to find the shortest paths.
+ * `congestion_costs`: The costs of running on top of or crossing wires of other nets.
+ * `settings`: Controls how the congestion is negotiated.
+ * `[out] outcome`: Statistics about the performed negotiation.
+ * `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `outcome`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.
+ * `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.
+ * `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+ * a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+ */
+RT_MUST_USE
+RT_Result RT_graph_connect_nets_negotiated(const struct RT_Graph *graph,
+                                           struct RT_Slice_Net nets,
+                                           struct RT_Slice_Endpoint endpoints,
+                                           struct RT_Slice_Waypoint waypoints,
+                                           struct RT_MutSlice_Vertex vertices,
+                                           struct RT_MutSlice_WireView wire_views,
+                                           struct RT_MutSlice_NetView net_views,
+                                           bool perform_centering,
+                                           RT_NetTopology topology,
+                                           struct RT_CostModel cost_model,
+                                           struct RT_CongestionCosts congestion_costs,
+                                           struct RT_NegotiationSettings settings,
+                                           struct RT_NegotiationOutcome *outcome,
+                                           struct RT_DiagnosticsBuffer *diagnostics);
+
+/**
+ * Connects nets in a graph one after another in order of descending priority, making wires avoid running
+ * on top of or crossing the wires of nets with a higher priority.
+ * Nets with the same priority are connected in the order they appear in `nets`.
+ *
+ * **Parameters**
+ * `graph`: The graph to connect the nets in.
+ * `nets`: A list of nets to connect.
+ * `endpoints`: A list of endpoints.
+ * `waypoints`: A list of waypoints.
+ * `vertices`: A list to write the found vertices into.
+ * `wire_views`: A list to write the found wires into.
+ * `net_views`: A list to write the found nets into, in the order of `nets`.
+ * `perform_centering`: Whether to center wires in the alleys they run through.
+ * `topology`: The shape of the wire trees connecting the endpoints of each net.
+ * `cost_model`: The weights used to find the shortest paths.
+ * `congestion_costs`: The costs of running on top of or crossing wires of other nets.
+ * `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.
+ * `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.
+ * `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+ * a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+ */
+RT_MUST_USE
+RT_Result RT_graph_connect_nets_by_priority(const struct RT_Graph *graph,
+                                            struct RT_Slice_Net nets,
+                                            struct RT_Slice_Endpoint endpoints,
+                                            struct RT_Slice_Waypoint waypoints,
+                                            struct RT_MutSlice_Vertex vertices,
+                                            struct RT_MutSlice_WireView wire_views,
+                                            struct RT_MutSlice_NetView net_views,
+                                            bool perform_centering,
+                                            RT_NetTopology topology,
+                                            struct RT_CostModel cost_model,
+                                            struct RT_CongestionCosts congestion_costs,
+                                            struct RT_DiagnosticsBuffer *diagnostics);
+
+/**
+ * Connects the nets of a bus as a bundle of parallel wires with the same bends.
+ *
+ * Every net has to consist of a start and an end, in that order. The first net is routed through the graph,
+ * and all other nets follow it at the constant distance between their start and the start of the first net.
+ * Nets that cannot follow the bundle are routed on their own instead.
+ *
+ * **Parameters**
+ * `graph`: The graph to connect the nets in.
+ * `nets`: A list of nets to connect.
+ * `endpoints`: A list of endpoints.
+ * `waypoints`: A list of waypoints.
+ * `vertices`: A list to write the found vertices into.
+ * `wire_views`: A list to write the found wires into.
+ * `net_views`: A list to write the found nets into.
+ * `cost_model`: The weights used to find the shortest paths.
+ * `[out] bundled_count`: The number of nets that were routed as part of the bundle.
+ * `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `bundled_count`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.
+ * `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.
+ * `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+ * a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+ */
+RT_MUST_USE
+RT_Result RT_graph_connect_bus(const struct RT_Graph *graph,
+                               struct RT_Slice_Net nets,
+                               struct RT_Slice_Endpoint endpoints,
+                               struct RT_Slice_Waypoint waypoints,
+                               struct RT_MutSlice_Vertex vertices,
+                               struct RT_MutSlice_WireView wire_views,
+                               struct RT_MutSlice_NetView net_views,
+                               struct RT_CostModel cost_model,
+                               uint32_t *bundled_count,
+                               struct RT_DiagnosticsBuffer *diagnostics);
+
+/**
+ * Creates a new routing session.
+ *
+ * **Parameters**
+ * `[out] session`: The created routing session.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `session` was `NULL`.
+ */
+RT_MUST_USE RT_Result RT_session_new(struct RT_RoutingSession **session);
+
+/**
+ * Marks all nets in a routing session whose wires cross one of the given regions as needing to be routed again.
+ *
+ * **Parameters**
+ * `session`: The routing session.
+ * `regions`: A list of regions that changed, for example the old and new extent of moved bounding boxes.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `session` or `regions.ptr` was `NULL`.
+ */
+RT_MUST_USE
+RT_Result RT_session_invalidate_regions(struct RT_RoutingSession *session,
+                                        struct RT_Slice_BoundingBox regions);
+
+/**
+ * Connects nets in a graph, only routing the nets that changed since the last call with the same session.
+ *
+ * **Parameters**
+ * `session`: The routing session holding the previous results.
+ * `graph`: The graph to connect the nets in.
+ * `nets`: A list of nets to connect.
+ * `endpoints`: A list of endpoints.
+ * `waypoints`: A list of waypoints.
+ * `vertices`: A list to write the vertices of all nets into.
+ * `wire_views`: A list to write the wires of all nets into.
+ * `net_views`: A list to write the nets into.
+ * `perform_centering`: Whether to center wires in the alleys they run through.
+ * `topology`: The shape of the wire trees connecting the endpoints of each net.
+ * `cost_model`: The weights used to find the shortest paths.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `session`, `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr` or `net_views.ptr` was `NULL`.
+ * `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.
+ * `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.
+ * `RT_RESULT_UNINITIALIZED_ERROR`: The thread pool has not been initialized yet.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len`, a net contained fewer than 2 endpoints,
+ * a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+ */
+RT_MUST_USE
+RT_Result RT_session_connect_nets(struct RT_RoutingSession *session,
+                                  const struct RT_Graph *graph,
+                                  struct RT_Slice_Net nets,
+                                  struct RT_Slice_Endpoint endpoints,
+                                  struct RT_Slice_Waypoint waypoints,
+                                  struct RT_MutSlice_Vertex vertices,
+                                  struct RT_MutSlice_WireView wire_views,
+                                  struct RT_MutSlice_NetView net_views,
+                                  bool perform_centering,
+                                  RT_NetTopology topology,
+                                  struct RT_CostModel cost_model);
+
+/**
+ * Routes all nets that changed since the last call with the same session, keeping the results inside the session.
+ *
+ * The results can be retrieved with `RT_session_get_result_sizes` and `RT_session_write_results`.
+ *
+ * **Parameters**
+ * `session`: The routing session holding the previous results.
+ * `graph`: The graph to connect the nets in.
+ * `nets`: A list of nets to connect.
+ * `endpoints`: A list of endpoints.
+ * `waypoints`: A list of waypoints.
+ * `perform_centering`: Whether to center wires in the alleys they run through.
+ * `topology`: The shape of the wire trees connecting the endpoints of each net.
+ * `cost_model`: The weights used to find the shortest paths.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `session`, `graph`, `nets.ptr`, `endpoints.ptr` or `waypoints.ptr` was `NULL`.
+ * `RT_RESULT_UNINITIALIZED_ERROR`: The thread pool has not been initialized yet.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: A net contained fewer than 2 endpoints, a waypoint had more than one direction,
+ * or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+ */
+RT_MUST_USE
+RT_Result RT_session_route_nets(struct RT_RoutingSession *session,
+                                const struct RT_Graph *graph,
+                                struct RT_Slice_Net nets,
+                                struct RT_Slice_Endpoint endpoints,
+                                struct RT_Slice_Waypoint waypoints,
+                                bool perform_centering,
+                                RT_NetTopology topology,
+                                struct RT_CostModel cost_model);
+
+/**
+ * Gets the buffer sizes required to hold the results of a routing session.
+ *
+ * **Parameters**
+ * `session`: The routing session.
+ * `[out] vertex_count`: The total number of vertices of all nets.
+ * `[out] wire_view_count`: The total number of wires of all nets.
+ * `[out] net_count`: The number of nets.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `session`, `vertex_count`, `wire_view_count` or `net_count` was `NULL`.
+ */
+RT_MUST_USE
+RT_Result RT_session_get_result_sizes(const struct RT_RoutingSession *session,
+                                      size_t *vertex_count,
+                                      size_t *wire_view_count,
+                                      size_t *net_count);
+
+/**
+ * Gets the status of a net in a routing session and the problems that occurred while routing it.
+ *
+ * The diagnostics remain valid until the session is routed again or freed.
+ *
+ * **Parameters**
+ * `session`: The routing session.
+ * `net`: The index of the net.
+ * `[out] status`: How completely the net was routed.
+ * `[out] diagnostics`: The problems that occurred while routing the net.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `session`, `status` or `diagnostics` was `NULL`.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `net` was not less than the number of nets in the session.
+ */
+RT_MUST_USE
+RT_Result RT_session_get_net_diagnostics(const struct RT_RoutingSession *session,
+                                         size_t net,
+                                         RT_NetStatus *status,
+                                         struct RT_Slice_Diagnostic *diagnostics);
+
+/**
+ * Writes the results of a routing session into contiguous buffers.
+ *
+ * **Parameters**
+ * `session`: The routing session.
+ * `vertices`: A list to write the vertices of all nets into.
+ * `wire_views`: A list to write the wires of all nets into.
+ * `net_views`: A list to write the nets into.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `session`, `vertices.ptr`, `wire_views.ptr` or `net_views.ptr` was `NULL`.
+ * `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.
+ * `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.
+ * `RT_RESULT_INVALID_ARGUMENT_ERROR`: `net_views.len` was not equal to the number of nets in the session.
+ */
+RT_MUST_USE
+RT_Result RT_session_write_results(const struct RT_RoutingSession *session,
+                                   struct RT_MutSlice_Vertex vertices,
+                                   struct RT_MutSlice_WireView wire_views,
+                                   struct RT_MutSlice_NetView net_views);
+
+/**
+ * Frees a routing session.
+ *
+ * **Parameters**
+ * `session`: The routing session to free.
+ *
+ * **Returns**
+ * `RT_RESULT_SUCCESS`: The operation completed successfully.
+ * `RT_RESULT_NULL_POINTER_ERROR`: `session` was `NULL`.
+ */
+RT_MUST_USE RT_Result RT_session_free(struct RT_RoutingSession *session);
 
 #endif /* ROUTING_H */
@@ -0,0 +1,315 @@
+use crate::graph::GraphData;
+use crate::routing::NetDiagnostics;
+use crate::session::{write_results, Scratch};
+use crate::*;
+use std::mem::MaybeUninit;
+
+/// How many times the length weight a unit of length of the leading wire of a bus costs extra
+/// when the bundle would not fit between it and a bounding box.
+const BUNDLE_CLEARANCE_PENALTY: u32 = 4;
+
+/// The unit vector pointing from `a` to `b`, if they are distinct and axis aligned.
+fn segment_direction(a: Point, b: Point) -> Option<(i32, i32)> {
+    match ((b.x - a.x).signum(), (b.y - a.y).signum()) {
+        (0, 0) => None,
+        (dx, 0) => Some((dx, 0)),
+        (0, dy) => Some((0, dy)),
+        _ => None,
+    }
+}
+
+/// The direction 90 degrees counter-clockwise of `dir`.
+#[inline]
+fn left_normal((dx, dy): (i32, i32)) -> (i32, i32) {
+    (-dy, dx)
+}
+
+#[inline]
+fn dot((ax, ay): (i32, i32), (bx, by): (i32, i32)) -> i64 {
+    (ax as i64) * (bx as i64) + (ay as i64) * (by as i64)
+}
+
+#[inline]
+fn offset(a: Point, b: Point) -> (i32, i32) {
+    (b.x - a.x, b.y - a.y)
+}
+
+#[inline]
+fn to_direction((dx, dy): (i32, i32)) -> Direction {
+    match (dx, dy) {
+        (1, 0) => Direction::PosX,
+        (-1, 0) => Direction::NegX,
+        (0, 1) => Direction::PosY,
+        _ => Direction::NegY,
+    }
+}
+
+/// The start, corners and end of the leading wire of a bus, if the net was routed as a single wire.
+fn leader_corners(
+    net_vertices: &[Vertex],
+    net_wire_views: &[WireView],
+    start: Point,
+) -> Option<Vec<Point>> {
+    if net_wire_views.len() != 1 {
+        return None;
+    }
+
+    let mut points: Vec<Point> = net_vertices
+        .iter()
+        .map(|vertex| Point {
+            x: vertex.x.round() as i32,
+            y: vertex.y.round() as i32,
+        })
+        .collect();
+
+    if points.first() != Some(&start) {
+        points.reverse();
+    }
+
+    points.dedup();
+
+    // Only keep the points where the wire changes direction.
+    let mut corners: Vec<Point> = Vec::with_capacity(points.len());
+    for point in points {
+        if let [.., a, b] = corners.as_slice() {
+            if segment_direction(*a, *b) == segment_direction(*b, point) {
+                corners.pop();
+            }
+        }
+
+        corners.push(point);
+    }
+
+    (corners.len() >= 2).then_some(corners)
+}
+
+/// How far `start` lies to the left of the first segment of the leading wire with corners `corners`.
+fn start_shift(corners: &[Point], start: Point) -> Option<i64> {
+    let first_dir = segment_direction(*corners.first()?, *corners.get(1)?)?;
+    Some(dot(left_normal(first_dir), offset(corners[0], start)))
+}
+
+/// Derives the wire from `start` to `end` that runs parallel to the leading wire with corners `corners`.
+///
+/// Every segment is shifted sideways by the same distance the start is shifted from the start of the leading wire,
+/// so the wire has the same bends. Returns the shift and the start, corners and end of the wire.
+/// Returns `None` if the end does not line up with the shifted last segment,
+/// or if the shifted wire folds over itself, passes through a bounding box or leaves a pin in an illegal direction.
+fn parallel_wire(
+    graph: &GraphData,
+    corners: &[Point],
+    start: Point,
+    end: Point,
+) -> Option<(i32, Vec<Point>)> {
+    let directions: Vec<_> = corners
+        .windows(2)
+        .map(|segment| segment_direction(segment[0], segment[1]))
+        .collect::<Option<_>>()?;
+
+    let first_dir = *directions.first()?;
+    let last_dir = *directions.last()?;
+
+    let shift = start_shift(corners, start)?;
+    let end_shift = dot(
+        left_normal(last_dir),
+        offset(corners[corners.len() - 1], end),
+    );
+    if end_shift != shift {
+        return None;
+    }
+
+    let shift: i32 = shift.try_into().ok()?;
+    let mut points = Vec::with_capacity(corners.len());
+    points.push(start);
+    for (i, &corner) in corners[1..(corners.len() - 1)].iter().enumerate() {
+        let (ax, ay) = left_normal(directions[i]);
+        let (bx, by) = left_normal(directions[i + 1]);
+        points.push(Point {
+            x: corner.x + shift * (ax + bx),
+            y: corner.y + shift * (ay + by),
+        });
+    }
+    points.push(end);
+
+    for (segment, &dir) in points.windows(2).zip(&directions) {
+        if (segment_direction(segment[0], segment[1]) != Some(dir))
+            || graph.segment_crosses_bounding_box(segment[0], segment[1])
+        {
+            return None;
+        }
+    }
+
+    let leaves_legally = |point: Point, dir: (i32, i32)| match graph.find_node(point) {
+        Some(index) => graph.nodes[index]
+            .legal_directions
+            .contains(to_direction(dir).into()),
+        None => true,
+    };
+
+    let (last_dx, last_dy) = last_dir;
+    (leaves_legally(start, first_dir) && leaves_legally(end, (-last_dx, -last_dy)))
+        .then_some((shift, points))
+}
+
+/// Determines if a follower with constraints `constraints` can run parallel to a leading wire with `bend_count` bends,
+/// which was routed under `leader_constraints`.
+fn bundle_honours(
+    constraints: &NetConstraints,
+    leader_constraints: &NetConstraints,
+    bend_count: usize,
+) -> bool {
+    ((constraints.max_bends == 0) || (bend_count <= (constraints.max_bends as usize)))
+        && (constraints.direction_preference == leader_constraints.direction_preference)
+}
+
+/// The cost model for the leading wire of a bus, which keeps `bundle_width` away from bounding boxes
+/// so the wires following it on either side fit next to it.
+fn leader_cost_model(cost_model: &CostModel, bundle_width: u32) -> CostModel {
+    if bundle_width == 0 {
+        return *cost_model;
+    }
+
+    CostModel {
+        bounding_box_clearance: cost_model.bounding_box_clearance.max(bundle_width),
+        bounding_box_penalty: cost_model.bounding_box_penalty.max(
+            cost_model
+                .length_weight
+                .saturating_mul(BUNDLE_CLEARANCE_PENALTY),
+        ),
+        ..*cost_model
+    }
+}
+
+pub(crate) fn connect_bus<'a, N: RoutableNet<'a>>(
+    graph: &Graph,
+    nets: &[N],
+    vertices: &mut [MaybeUninit<Vertex>],
+    wire_views: &mut [MaybeUninit<WireView>],
+    net_views: &mut [MaybeUninit<NetView>],
+    cost_model: &CostModel,
+    report: &mut RoutingReport,
+) -> Result<usize, RoutingError> {
+    assert_eq!(nets.len(), net_views.len());
+
+    // Bundled followers are never routed on their own, so their constraints have to be checked here.
+    if !nets.iter().all(|net| net.constraints().is_valid()) {
+        return Err(RoutingError::InvalidConstraints);
+    }
+
+    let mut scratch = Scratch::default();
+    let mut results = Vec::with_capacity(nets.len());
+    let mut diagnostics = Vec::with_capacity(nets.len());
+    let mut bundled_count = 0;
+
+    let route_alone = |scratch: &mut Scratch, net: &N, cost_model: &CostModel| {
+        scratch.route_net(
+            graph,
+            net.endpoints(),
+            false,
+            NetTopology::RootAndBranches,
+            cost_model,
+            &net.constraints(),
+            None,
+        )
+    };
+
+    let Some((leader, followers)) = nets.split_first() else {
+        report.set_nets([]);
+        return Ok(0);
+    };
+
+    let leader_start = match leader.endpoints() {
+        [start, _] => Some(start.position),
+        _ => None,
+    };
+
+    // Only followers that can be bundled need room next to the leading wire.
+    let is_bundle_candidate = |net: &N| match net.endpoints() {
+        [start, end] => start.waypoints.is_empty() && end.waypoints.is_empty(),
+        _ => false,
+    };
+
+    let bundle_width = match leader_start {
+        Some(leader_start) => followers
+            .iter()
+            .filter(|net| is_bundle_candidate(net))
+            .map(|net| leader_start.manhatten_distance_to(net.endpoints()[0].position))
+            .max()
+            .unwrap_or(0),
+        None => 0,
+    };
+
+    let (leader_vertices, leader_wire_views) = route_alone(
+        &mut scratch,
+        leader,
+        &leader_cost_model(cost_model, bundle_width),
+    )?;
+    let corners = match leader_start {
+        Some(start) => leader_corners(&leader_vertices, &leader_wire_views, start),
+        None => None,
+    };
+
+    if corners.is_some() {
+        bundled_count += 1;
+    }
+
+    results.push((leader_vertices, leader_wire_views));
+    diagnostics.push(std::mem::take(&mut scratch.diagnostics));
+
+    // The wires of a bus have to be evenly spaced, with the pitch given by the first follower.
+    let pitch = match (&corners, followers.first().map(|net| net.endpoints())) {
+        (Some(corners), Some([start, _])) => start_shift(corners, start.position),
+        _ => None,
+    };
+
+    let leader_constraints = leader.constraints();
+    for (index, net) in followers.iter().enumerate() {
+        let wire = match (&corners, pitch, net.endpoints()) {
+            (Some(corners), Some(pitch), [start, end])
+                if (pitch != 0)
+                    && is_bundle_candidate(net)
+                    && bundle_honours(
+                        &net.constraints(),
+                        &leader_constraints,
+                        corners.len() - 2,
+                    ) =>
+            {
+                parallel_wire(&graph.data, corners, start.position, end.position)
+                    .filter(|&(shift, _)| {
+                        (shift as i64) == pitch.saturating_mul((index as i64) + 1)
+                    })
+                    .map(|(_, wire)| wire)
+            }
+            _ => None,
+        };
+
+        match wire {
+            Some(wire) => {
+                let wire_view = WireView::new(wire.len(), false, true)
+                    .ok_or(RoutingError::VertexBufferOverflow)?;
+                results.push((
+                    wire.into_iter().map(Vertex::from).collect(),
+                    vec![wire_view],
+                ));
+                diagnostics.push(NetDiagnostics::default());
+                bundled_count += 1;
+            }
+            None => {
+                results.push(route_alone(&mut scratch, net, cost_model)?);
+                diagnostics.push(std::mem::take(&mut scratch.diagnostics));
+            }
+        }
+    }
+
+    write_results(
+        results
+            .iter()
+            .map(|(vertices, wire_views)| (vertices.as_slice(), wire_views.as_slice())),
+        vertices,
+        wire_views,
+        net_views,
+    )?;
+
+    report.set_nets(diagnostics);
+    Ok(bundled_count)
+}
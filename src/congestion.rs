@@ -0,0 +1,333 @@
+use crate::graph::{GraphData, NodeIndex, INVALID_NODE_INDEX};
+use crate::path_finding::Path;
+use crate::*;
+
+/// Costs for running wires close to wires of other nets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct CongestionCosts {
+    /// The cost per unit of length of running on top of a wire of another net.
+    pub overlap_penalty: u32,
+    /// Whether running on top of a wire of another net is not allowed at all.
+    pub forbid_overlap: bool,
+    /// The cost of crossing a wire of another net that passes straight through the crossing.
+    /// Paths that only touch a wire of another net, at a bend of either wire or where one of them ends,
+    /// are charged `overlap_penalty` once instead.
+    pub crossing_penalty: u32,
+}
+
+impl Default for CongestionCosts {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            overlap_penalty: 10,
+            forbid_overlap: false,
+            crossing_penalty: 20,
+        }
+    }
+}
+
+/// An edge of the graph, identified by the node at its negative end and its axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct EdgeKey {
+    node: NodeIndex,
+    vertical: bool,
+}
+
+impl EdgeKey {
+    #[inline]
+    fn new(node: NodeIndex, dir: Direction, neighbor: NodeIndex) -> Self {
+        match dir {
+            Direction::PosX => Self {
+                node,
+                vertical: false,
+            },
+            Direction::NegX => Self {
+                node: neighbor,
+                vertical: false,
+            },
+            Direction::PosY => Self {
+                node,
+                vertical: true,
+            },
+            Direction::NegY => Self {
+                node: neighbor,
+                vertical: true,
+            },
+        }
+    }
+}
+
+/// Keeps track of the nets using each edge of the graph.
+#[derive(Default)]
+pub(crate) struct Occupancy {
+    edges: HashMap<EdgeKey, Vec<u32>>,
+    net_edges: HashMap<u32, Vec<EdgeKey>>,
+    history: HashMap<EdgeKey, u32>,
+}
+
+impl Occupancy {
+    /// Marks all edges used by `net` as free again.
+    pub(crate) fn remove_net(&mut self, net: u32) {
+        let Some(keys) = self.net_edges.remove(&net) else {
+            return;
+        };
+
+        for key in keys {
+            if let Some(users) = self.edges.get_mut(&key) {
+                users.retain(|&user| user != net);
+                if users.is_empty() {
+                    self.edges.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Raises the history cost of every edge used by more than one net by `penalty`.
+    ///
+    /// Returns the number of such edges.
+    pub(crate) fn penalize_overuse(&mut self, penalty: u32) -> u32 {
+        let mut overused_count = 0;
+        for (&key, users) in &self.edges {
+            if users.len() > 1 {
+                let history = self.history.entry(key).or_default();
+                *history = history.saturating_add(penalty);
+                overused_count += 1;
+            }
+        }
+
+        overused_count
+    }
+
+    /// All nets using an edge that is also used by another net, in ascending order.
+    pub(crate) fn congested_nets(&self) -> Vec<u32> {
+        let mut nets: Vec<_> = self
+            .edges
+            .values()
+            .filter(|users| users.len() > 1)
+            .flatten()
+            .copied()
+            .collect();
+        nets.sort_unstable();
+        nets.dedup();
+        nets
+    }
+
+    fn insert_edge(&mut self, key: EdgeKey, net: u32) {
+        let users = self.edges.entry(key).or_default();
+        if !users.contains(&net) {
+            users.push(net);
+            self.net_edges.entry(net).or_default().push(key);
+        }
+    }
+
+    /// Marks all edges along `path` as used by `net`.
+    pub(crate) fn insert_path(&mut self, graph: &GraphData, path: &Path, net: u32) {
+        for pair in path.nodes().windows(2) {
+            let [a, b] = pair else { unreachable!() };
+
+            let Some(dir) = a.bend_direction else {
+                continue;
+            };
+
+            let a_index = graph.find_node(a.position).expect("invalid path node");
+            let b_index = graph.find_node(b.position).expect("invalid path node");
+
+            self.insert_edge(EdgeKey::new(a_index, dir, b_index), net);
+        }
+    }
+
+    /// Marks all edges the axis aligned segments between consecutive `points` run along as used by `net`.
+    ///
+    /// Unlike the ones of paths, the segments do not have to start or end at nodes, like the ones of fallback wires.
+    pub(crate) fn insert_segments(&mut self, graph: &GraphData, points: &[Point], net: u32) {
+        for pair in points.windows(2) {
+            let [a, b] = *pair else { unreachable!() };
+
+            let (coords, dir, min, max) = if a.y == b.y {
+                (
+                    graph.x_coords(),
+                    Direction::PosX,
+                    a.x.min(b.x),
+                    a.x.max(b.x),
+                )
+            } else {
+                (
+                    graph.y_coords(),
+                    Direction::PosY,
+                    a.y.min(b.y),
+                    a.y.max(b.y),
+                )
+            };
+
+            let start = coords.partition_point(|&coord| coord < min);
+            let end = coords.partition_point(|&coord| coord <= max);
+
+            // Walk the nodes on the segment in ascending order, every edge between two of them is covered by it.
+            let mut prev_index = None;
+            for &coord in &coords[start..end] {
+                let position = match dir {
+                    Direction::PosX => Point { x: coord, y: a.y },
+                    _ => Point { x: a.x, y: coord },
+                };
+
+                let Some(index) = graph.find_node(position) else {
+                    continue;
+                };
+
+                if let Some(prev_index) = prev_index {
+                    if graph.nodes[prev_index].neighbors[dir] == index {
+                        self.insert_edge(EdgeKey::new(prev_index, dir, index), net);
+                    }
+                }
+
+                prev_index = Some(index);
+            }
+        }
+    }
+
+    /// Marks all edges the wires of a routed net run along as used by `net`.
+    ///
+    /// Segments that do not run along lines of the graph, like the ones moved by centering, are skipped.
+    pub(crate) fn insert_wires(
+        &mut self,
+        graph: &GraphData,
+        vertices: &[Vertex],
+        wire_views: &[WireView],
+        net: u32,
+    ) {
+        let mut vertices = vertices;
+        for wire_view in wire_views {
+            let (wire_vertices, tail) = vertices.split_at(wire_view.vertex_count());
+            vertices = tail;
+
+            for pair in wire_vertices.windows(2) {
+                let [a, b] = pair else { unreachable!() };
+
+                let on_grid = [a.x, a.y, b.x, b.y]
+                    .iter()
+                    .all(|coord| coord.fract() == 0.0);
+                if on_grid {
+                    let a = Point {
+                        x: a.x as i32,
+                        y: a.y as i32,
+                    };
+                    let b = Point {
+                        x: b.x as i32,
+                        y: b.y as i32,
+                    };
+                    self.insert_segments(graph, &[a, b], net);
+                }
+            }
+        }
+    }
+
+    /// The nets using the edge from `node` in direction `dir`.
+    fn users(&self, graph: &GraphData, node: NodeIndex, dir: Direction) -> &[u32] {
+        let neighbor = graph.nodes[node].neighbors[dir];
+        if neighbor == INVALID_NODE_INDEX {
+            return &[];
+        }
+
+        self.edges
+            .get(&EdgeKey::new(node, dir, neighbor))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The number of nets other than `net` using the edge from `node` in direction `dir`.
+    fn other_users(&self, graph: &GraphData, node: NodeIndex, dir: Direction, net: u32) -> u32 {
+        self.users(graph, node, dir)
+            .iter()
+            .filter(|&&user| user != net)
+            .count() as u32
+    }
+
+    /// Determines if a net other than `net` passes straight through `node` along the axis of `dir`.
+    fn passes_through(&self, graph: &GraphData, node: NodeIndex, dir: Direction, net: u32) -> bool {
+        let opposite_users = self.users(graph, node, dir.opposite());
+        self.users(graph, node, dir)
+            .iter()
+            .any(|&user| (user != net) && opposite_users.contains(&user))
+    }
+
+    /// The history cost per unit of length of the edge from `node` in direction `dir`.
+    fn history_cost(&self, graph: &GraphData, node: NodeIndex, dir: Direction) -> u32 {
+        let neighbor = graph.nodes[node].neighbors[dir];
+        self.history
+            .get(&EdgeKey::new(node, dir, neighbor))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// The congestion state the path finder has to take into account while routing a net.
+pub(crate) struct Congestion<'a> {
+    pub(crate) occupancy: &'a mut Occupancy,
+    pub(crate) net: u32,
+    pub(crate) costs: CongestionCosts,
+}
+
+impl Congestion<'_> {
+    /// The additional cost of moving from `node` to its neighbor in direction `dir`,
+    /// or `None` if the move is not allowed.
+    ///
+    /// `straight_dir` is the direction the path arrived at `node` in, if it did not start there.
+    pub(crate) fn edge_cost(
+        &self,
+        graph: &GraphData,
+        node: NodeIndex,
+        dir: Direction,
+        straight_dir: Option<Direction>,
+    ) -> Option<u32> {
+        let neighbor = graph.nodes[node].neighbors[dir];
+        let length = graph.nodes[node]
+            .position
+            .manhatten_distance_to(graph.nodes[neighbor].position);
+
+        let mut cost = self
+            .occupancy
+            .history_cost(graph, node, dir)
+            .saturating_mul(length);
+
+        let overlap_count = self.occupancy.other_users(graph, node, dir, self.net);
+        if overlap_count > 0 {
+            if self.costs.forbid_overlap {
+                return None;
+            }
+
+            cost = cost.saturating_add(
+                overlap_count
+                    .saturating_mul(length)
+                    .saturating_mul(self.costs.overlap_penalty),
+            );
+        }
+
+        // Passing through `node`, the path either crosses the wires of other nets there or touches them.
+        // Wires running along the same edges as the path are already paid for as overlap.
+        let Some(straight_dir) = straight_dir else {
+            return Some(cost);
+        };
+
+        let perpendicular_dir = match dir {
+            Direction::PosX | Direction::NegX => Direction::PosY,
+            Direction::PosY | Direction::NegY => Direction::PosX,
+        };
+
+        let crosses = (dir == straight_dir)
+            && self
+                .occupancy
+                .passes_through(graph, node, perpendicular_dir, self.net);
+        let touches = Direction::ALL
+            .into_iter()
+            .filter(|&other_dir| (other_dir != dir) && (other_dir != straight_dir.opposite()))
+            .any(|other_dir| self.occupancy.other_users(graph, node, other_dir, self.net) > 0);
+
+        if crosses {
+            cost = cost.saturating_add(self.costs.crossing_penalty);
+        } else if touches {
+            cost = cost.saturating_add(self.costs.overlap_penalty);
+        }
+
+        Some(cost)
+    }
+}
@@ -0,0 +1,372 @@
+use crate::routing::{JunctionMap, NetDiagnostics};
+use crate::session::Scratch;
+use crate::*;
+use rayon::prelude::*;
+use std::borrow::Cow;
+
+/// A net to connect.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NetSpec<'a> {
+    /// The endpoints of the net.
+    pub endpoints: Cow<'a, [Endpoint<'a>]>,
+    /// The rules the wires of the net have to follow.
+    pub constraints: NetConstraints,
+    /// When routing by priority, nets with a higher priority are routed first.
+    pub priority: u32,
+}
+
+impl<'a> NetSpec<'a> {
+    #[inline]
+    pub fn with_constraints(self, constraints: NetConstraints) -> Self {
+        Self {
+            constraints,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_priority(self, priority: u32) -> Self {
+        Self { priority, ..self }
+    }
+}
+
+impl<'a> From<Vec<Endpoint<'a>>> for NetSpec<'a> {
+    #[inline]
+    fn from(endpoints: Vec<Endpoint<'a>>) -> Self {
+        Self {
+            endpoints: Cow::Owned(endpoints),
+            constraints: NetConstraints::default(),
+            priority: 0,
+        }
+    }
+}
+
+impl<'a> From<&'a [Endpoint<'a>]> for NetSpec<'a> {
+    #[inline]
+    fn from(endpoints: &'a [Endpoint<'a>]) -> Self {
+        Self {
+            endpoints: Cow::Borrowed(endpoints),
+            constraints: NetConstraints::default(),
+            priority: 0,
+        }
+    }
+}
+
+impl<'a> AsRef<[Endpoint<'a>]> for NetSpec<'a> {
+    #[inline]
+    fn as_ref(&self) -> &[Endpoint<'a>] {
+        &self.endpoints
+    }
+}
+
+/// A net that can be routed: its endpoints and the rules its wires have to follow.
+///
+/// Plain lists of endpoints are nets without constraints.
+pub trait RoutableNet<'a> {
+    /// The endpoints of the net.
+    fn endpoints(&self) -> &[Endpoint<'a>];
+
+    /// The rules the wires of the net have to follow.
+    #[inline]
+    fn constraints(&self) -> NetConstraints {
+        NetConstraints::default()
+    }
+
+    /// When routing by priority, nets with a higher priority are routed first.
+    #[inline]
+    fn priority(&self) -> u32 {
+        0
+    }
+}
+
+impl<'a> RoutableNet<'a> for [Endpoint<'a>] {
+    #[inline]
+    fn endpoints(&self) -> &[Endpoint<'a>] {
+        self
+    }
+}
+
+impl<'a, const N: usize> RoutableNet<'a> for [Endpoint<'a>; N] {
+    #[inline]
+    fn endpoints(&self) -> &[Endpoint<'a>] {
+        self
+    }
+}
+
+impl<'a> RoutableNet<'a> for Vec<Endpoint<'a>> {
+    #[inline]
+    fn endpoints(&self) -> &[Endpoint<'a>] {
+        self
+    }
+}
+
+impl<'a> RoutableNet<'a> for NetSpec<'a> {
+    #[inline]
+    fn endpoints(&self) -> &[Endpoint<'a>] {
+        &self.endpoints
+    }
+
+    #[inline]
+    fn constraints(&self) -> NetConstraints {
+        self.constraints
+    }
+
+    #[inline]
+    fn priority(&self) -> u32 {
+        self.priority
+    }
+}
+
+impl<'a, T: RoutableNet<'a> + ?Sized> RoutableNet<'a> for &T {
+    #[inline]
+    fn endpoints(&self) -> &[Endpoint<'a>] {
+        (**self).endpoints()
+    }
+
+    #[inline]
+    fn constraints(&self) -> NetConstraints {
+        (**self).constraints()
+    }
+
+    #[inline]
+    fn priority(&self) -> u32 {
+        (**self).priority()
+    }
+}
+
+/// Where a wire starts or ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireAttachment {
+    /// The endpoint at this index in the net.
+    Endpoint(usize),
+    /// The junction at this index in [`RoutedNet::junctions`].
+    Junction(usize),
+}
+
+/// A point where a wire joins other wires of the same net.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Junction {
+    /// The position of the junction.
+    pub position: Vertex,
+    /// The number of wire segments meeting at the junction.
+    pub degree: u32,
+}
+
+/// A wire connecting an endpoint to another endpoint or a junction.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RoutedWire {
+    /// The corners of the wire, including its start and end.
+    pub vertices: Vec<Vertex>,
+    /// Where the wire starts, or `None` if it does not start at an endpoint.
+    pub start: Option<WireAttachment>,
+    /// Where the wire ends, or `None` if it ends neither at an endpoint nor in a junction.
+    pub end: Option<WireAttachment>,
+    /// Whether this is the root wire of the net.
+    pub is_root: bool,
+}
+
+impl RoutedWire {
+    /// Iterates over the straight segments of the wire.
+    pub fn segments(&self) -> impl Iterator<Item = [Vertex; 2]> + '_ {
+        self.vertices.windows(2).map(|pair| [pair[0], pair[1]])
+    }
+}
+
+/// The wires of a routed net.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RoutedNet {
+    /// The vertices of all wires of the net, one wire after another.
+    pub vertices: Vec<Vertex>,
+    /// The wires of the net, in the same layout as [`Graph::connect_net`].
+    pub wire_views: Vec<WireView>,
+    /// The wires of the net, with the wire segments leading through waypoints joined together.
+    pub wires: Vec<RoutedWire>,
+    /// The junctions of the net.
+    pub junctions: Vec<Junction>,
+    /// How completely the net was routed.
+    pub status: NetStatus,
+    /// The problems that occurred while routing the net.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl RoutedNet {
+    /// Collects the wires and junctions of a net from the wire views and junctions recorded while routing it.
+    ///
+    /// Fails if two consecutive wire views that make up the same wire do not meet.
+    pub(crate) fn new(
+        endpoints: &[Endpoint],
+        vertices: Vec<Vertex>,
+        wire_views: Vec<WireView>,
+        junctions: &JunctionMap,
+        diagnostics: NetDiagnostics,
+    ) -> Result<Self, RoutingError> {
+        let mut net = Self {
+            vertices,
+            wire_views,
+            wires: Vec::new(),
+            junctions: Vec::new(),
+            status: diagnostics.status,
+            diagnostics: diagnostics.diagnostics,
+        };
+
+        // Number the junctions in the order of the first wire ending in them.
+        let mut junction_list: Vec<_> = junctions
+            .iter()
+            .filter_map(|(&position, kind)| {
+                let first_end = kind.wire_ends().iter().copied().min()?;
+                Some((first_end, position, kind.wire_ends()))
+            })
+            .collect();
+        junction_list.sort_unstable_by_key(|&(first_end, ..)| first_end);
+
+        let mut junction_at_vertex = HashMap::default();
+        for (index, &(first_end, position, wire_ends)) in junction_list.iter().enumerate() {
+            // Besides the wires ending in it, a junction splits the wire it lies on into two segments,
+            // unless it lies on the end of that wire at an endpoint.
+            let on_endpoint = endpoints
+                .iter()
+                .any(|endpoint| endpoint.position == position);
+            let wire_degree = if on_endpoint { 1 } else { 2 };
+
+            net.junctions.push(Junction {
+                position: net.vertices[first_end],
+                degree: (wire_ends.len() as u32) + wire_degree,
+            });
+
+            for &wire_end in wire_ends {
+                junction_at_vertex.insert(wire_end, index);
+            }
+        }
+
+        let endpoint_at = |position: Vertex| {
+            endpoints
+                .iter()
+                .position(|endpoint| Vertex::from(endpoint.position) == position)
+                .map(WireAttachment::Endpoint)
+        };
+
+        // Wires leading through waypoints consist of multiple wire views, join them back together.
+        let mut vertex_start = 0;
+        let mut continues_wire = false;
+        for &wire_view in &net.wire_views {
+            let vertex_end = vertex_start + wire_view.vertex_count();
+            let wire_vertices = &net.vertices[vertex_start..vertex_end];
+
+            match net.wires.last_mut() {
+                Some(wire) if continues_wire && (wire.is_root == wire_view.is_root()) => {
+                    if wire.vertices.last() != wire_vertices.first() {
+                        return Err(RoutingError::DisconnectedWire);
+                    }
+
+                    wire.vertices.extend_from_slice(&wire_vertices[1..]);
+                }
+                _ => net.wires.push(RoutedWire {
+                    vertices: wire_vertices.to_vec(),
+                    start: wire_vertices.first().and_then(|&start| endpoint_at(start)),
+                    end: None,
+                    is_root: wire_view.is_root(),
+                }),
+            }
+
+            let wire = net.wires.last_mut().unwrap();
+            if wire_view.ends_in_junction() {
+                let junction = junction_at_vertex
+                    .get(&(vertex_end - 1))
+                    .ok_or(RoutingError::DisconnectedWire)?;
+                wire.end = Some(WireAttachment::Junction(*junction));
+            } else {
+                wire.end = wire.vertices.last().and_then(|&end| endpoint_at(end));
+            }
+
+            continues_wire = !wire_view.ends_in_junction();
+            vertex_start = vertex_end;
+        }
+
+        Ok(net)
+    }
+
+    /// Iterates over the wires of the net in the same layout as [`Graph::connect_net`], together with their vertices.
+    pub fn wires(&self) -> impl Iterator<Item = (WireView, &[Vertex])> + '_ {
+        let mut vertices = self.vertices.as_slice();
+        self.wire_views.iter().map(move |&wire_view| {
+            let (wire_vertices, tail) = vertices.split_at(wire_view.vertex_count());
+            vertices = tail;
+            (wire_view, wire_vertices)
+        })
+    }
+}
+
+/// The result of connecting multiple nets.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RoutedDesign {
+    /// The routed nets, in the same order they were passed in.
+    pub nets: Vec<RoutedNet>,
+}
+
+impl RoutedDesign {
+    /// The total number of vertices in all nets.
+    pub fn vertex_count(&self) -> usize {
+        self.nets.iter().map(|net| net.vertices.len()).sum()
+    }
+
+    /// The total number of wires in all nets.
+    pub fn wire_view_count(&self) -> usize {
+        self.nets.iter().map(|net| net.wire_views.len()).sum()
+    }
+}
+
+pub(crate) fn connect_nets(
+    graph: &Graph,
+    nets: &[NetSpec],
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: &CostModel,
+) -> Result<RoutedDesign, RoutingError> {
+    let mut results: Vec<_> = nets
+        .par_iter()
+        .map_init(Scratch::default, |scratch, net| {
+            let (vertices, wire_views) = scratch.route_net(
+                graph,
+                &net.endpoints,
+                perform_centering,
+                topology,
+                cost_model,
+                &net.constraints,
+                None,
+            )?;
+
+            let junctions = std::mem::take(&mut scratch.junctions);
+            let diagnostics = std::mem::take(&mut scratch.diagnostics);
+            Ok((vertices, wire_views, junctions, diagnostics))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let rerouted = session::assign_distinct_tracks(
+        graph,
+        nets,
+        |index| (results[index].0.as_slice(), results[index].1.as_slice()),
+        perform_centering,
+        topology,
+        cost_model,
+    )?;
+
+    for net in rerouted {
+        results[net.index] = (net.vertices, net.wire_views, net.junctions, net.diagnostics);
+    }
+
+    let nets = nets
+        .iter()
+        .zip(results)
+        .map(|(net, (vertices, wire_views, junctions, diagnostics))| {
+            RoutedNet::new(
+                &net.endpoints,
+                vertices,
+                wire_views,
+                &junctions,
+                diagnostics,
+            )
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(RoutedDesign { nets })
+}
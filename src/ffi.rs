@@ -2,17 +2,16 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 
 use crate::graph::{NodeIndex, INVALID_NODE_INDEX};
-use crate::routing::{Array, CenteringCandidate, JunctionMap};
+use crate::routing::{JunctionMap, NetDiagnostics};
 use crate::*;
-use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::borrow::Cow;
 use std::ffi::{c_char, c_void, CStr};
 use std::fs::File;
 use std::mem::MaybeUninit;
 use std::ops::Range;
 use std::sync::atomic::{AtomicU16, Ordering};
-use thread_local::ThreadLocal;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
@@ -30,10 +29,14 @@ pub enum Result {
 impl From<RoutingError> for Result {
     fn from(err: RoutingError) -> Self {
         match err {
-            RoutingError::NotEnoughEndpoints => Result::InvalidArgumentError,
+            RoutingError::NotEnoughEndpoints | RoutingError::InvalidConstraints => {
+                Result::InvalidArgumentError
+            }
             RoutingError::VertexBufferOverflow => Result::VertexBufferOverflowError,
             RoutingError::WireViewBufferOverflow => Result::WireViewBufferOverflowError,
-            RoutingError::InvalidPoint => Result::InvalidOperationError,
+            RoutingError::InvalidPoint | RoutingError::DisconnectedWire => {
+                Result::InvalidOperationError
+            }
         }
     }
 }
@@ -102,7 +105,7 @@ unsafe impl<T> Sync for MutSlice<T> {}
 #[allow(dead_code)]
 impl<T> MutSlice<T> {
     #[inline]
-    fn is_null(self) -> bool {
+    fn is_null(&self) -> bool {
         self.ptr.is_null() && (self.len != 0)
     }
 
@@ -274,6 +277,221 @@ pub unsafe extern "C" fn RT_graph_build(
     Result::Success
 }
 
+/// Builds a graph with multiple parallel tracks around every bounding box.
+///
+/// **Parameters**  
+/// `graph`: The graph to build.  
+/// `anchors`: A list of anchor points to build the graph from.  
+/// `bounding_boxes`: A list of bounding boxes to build the graph from.  
+/// `minimal`: Whether to spend more processing time to ensure the graph is minimal.  
+/// `tracks`: The number, spacing and grid alignment of the tracks around and between bounding boxes.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `anchors.ptr` or `bounding_boxes.ptr` was `NULL`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_graph_build_with_tracks(
+    graph: *mut Graph,
+    anchors: Slice<Anchor>,
+    bounding_boxes: Slice<BoundingBox>,
+    minimal: bool,
+    tracks: TrackSettings,
+) -> Result {
+    if graph.is_null() || anchors.is_null() || bounding_boxes.is_null() {
+        return Result::NullPointerError;
+    }
+
+    let graph = unsafe { &mut *graph };
+    let anchors = unsafe { anchors.as_ref() };
+    let bounding_boxes = unsafe { bounding_boxes.as_ref() };
+    graph.build_with_tracks(anchors, bounding_boxes, minimal, tracks);
+
+    Result::Success
+}
+
+/// Builds a graph out of components that consist of more than one bounding box.
+///
+/// **Parameters**  
+/// `graph`: The graph to build.  
+/// `anchors`: A list of anchor points to build the graph from.  
+/// `bounding_boxes`: A list of bounding boxes to build the graph from.  
+/// `components`: For every bounding box, the index of the bounding box representing the component it is part of,
+/// or `RT_INVALID_BOUNDING_BOX_INDEX` if it is a component on its own. Chains of bounding boxes are resolved
+/// to the bounding box at their end, and indices past the end of `bounding_boxes` are treated as invalid. May be empty.  
+/// `minimal`: Whether to spend more processing time to ensure the graph is minimal.  
+/// `tracks`: The number, spacing and grid alignment of the tracks around and between bounding boxes.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `anchors.ptr`, `bounding_boxes.ptr` or `components.ptr` was `NULL`.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `components` was neither empty nor the same length as `bounding_boxes`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_graph_build_with_components(
+    graph: *mut Graph,
+    anchors: Slice<Anchor>,
+    bounding_boxes: Slice<BoundingBox>,
+    components: Slice<BoundingBoxIndex>,
+    minimal: bool,
+    tracks: TrackSettings,
+) -> Result {
+    if graph.is_null() || anchors.is_null() || bounding_boxes.is_null() || components.is_null() {
+        return Result::NullPointerError;
+    }
+
+    let graph = unsafe { &mut *graph };
+    let anchors = unsafe { anchors.as_ref() };
+    let bounding_boxes = unsafe { bounding_boxes.as_ref() };
+    let components = unsafe { components.as_ref() };
+    if !components.is_empty() && (components.len() != bounding_boxes.len()) {
+        return Result::InvalidArgumentError;
+    }
+
+    graph.build_with_components(anchors, bounding_boxes, components, minimal, tracks);
+
+    Result::Success
+}
+
+/// A region wires should stay out of that is not a component, like a title block or a comment frame.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct KeepOut {
+    /// The extents of the zone.
+    pub bounding_box: BoundingBox,
+    /// How strictly wires are kept out of the zone, one of the `RT_KeepOutKind` values.
+    pub kind: u32,
+    /// For soft keep-outs, the factor the cost of path segments running through the zone is multiplied with.
+    /// The cost of a whole edge of the graph is multiplied, even if only part of the edge lies inside the zone.
+    pub cost_multiplier: u32,
+    /// Whether to place anchors at the corners of the tracks around the zone, like around bounding boxes.
+    pub auto_anchors: bool,
+}
+
+impl KeepOut {
+    /// Converts the keep-out, or returns `None` if `kind` is not a valid `RT_KeepOutKind`.
+    fn to_keep_out(self) -> Option<crate::KeepOut> {
+        let kind = match self.kind {
+            kind if kind == (KeepOutKind::Hard as u32) => KeepOutKind::Hard,
+            kind if kind == (KeepOutKind::Soft as u32) => KeepOutKind::Soft,
+            _ => return None,
+        };
+
+        Some(crate::KeepOut {
+            bounding_box: self.bounding_box,
+            kind,
+            cost_multiplier: self.cost_multiplier,
+            auto_anchors: self.auto_anchors,
+        })
+    }
+}
+
+/// Builds a graph with keep-out zones wires should not enter.
+///
+/// **Parameters**  
+/// `graph`: The graph to build.  
+/// `anchors`: A list of anchor points to build the graph from.  
+/// `bounding_boxes`: A list of bounding boxes to build the graph from.  
+/// `components`: For every bounding box, the index of the bounding box representing the component it is part of,
+/// or `RT_INVALID_BOUNDING_BOX_INDEX` if it is a component on its own. Chains of bounding boxes are resolved
+/// to the bounding box at their end, and indices past the end of `bounding_boxes` are treated as invalid. May be empty.  
+/// `keep_outs`: A list of hard and soft keep-out zones.  
+/// `minimal`: Whether to spend more processing time to ensure the graph is minimal.  
+/// `tracks`: The number, spacing and grid alignment of the tracks around and between bounding boxes.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `anchors.ptr`, `bounding_boxes.ptr`, `components.ptr` or `keep_outs.ptr` was `NULL`.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `components` was neither empty nor the same length as `bounding_boxes`,
+/// or the kind of a keep-out was not a valid `RT_KeepOutKind`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_graph_build_with_keep_outs(
+    graph: *mut Graph,
+    anchors: Slice<Anchor>,
+    bounding_boxes: Slice<BoundingBox>,
+    components: Slice<BoundingBoxIndex>,
+    keep_outs: Slice<KeepOut>,
+    minimal: bool,
+    tracks: TrackSettings,
+) -> Result {
+    if graph.is_null()
+        || anchors.is_null()
+        || bounding_boxes.is_null()
+        || components.is_null()
+        || keep_outs.is_null()
+    {
+        return Result::NullPointerError;
+    }
+
+    let graph = unsafe { &mut *graph };
+    let anchors = unsafe { anchors.as_ref() };
+    let bounding_boxes = unsafe { bounding_boxes.as_ref() };
+    let components = unsafe { components.as_ref() };
+    let keep_outs = unsafe { keep_outs.as_ref() };
+    if !components.is_empty() && (components.len() != bounding_boxes.len()) {
+        return Result::InvalidArgumentError;
+    }
+
+    let Some(keep_outs) = keep_outs
+        .iter()
+        .map(|keep_out| keep_out.to_keep_out())
+        .collect::<Option<Vec<_>>>()
+    else {
+        return Result::InvalidArgumentError;
+    };
+
+    graph.build_with_keep_outs(
+        anchors,
+        bounding_boxes,
+        components,
+        &keep_outs,
+        minimal,
+        tracks,
+    );
+
+    Result::Success
+}
+
+/// Updates a graph after anchors were added or removed, or bounding boxes changed.
+///
+/// **Parameters**  
+/// `graph`: The graph to update.  
+/// `added_anchors`: A list of anchor points to add to the graph.  
+/// `removed_anchors`: A list of anchor points to remove from the graph.  
+/// `changed_bounding_boxes`: A list of bounding boxes to replace in the graph.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `added_anchors.ptr`, `removed_anchors.ptr` or `changed_bounding_boxes.ptr` was `NULL`.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: One of the removed anchors or changed bounding boxes did not exist in the graph.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_graph_update(
+    graph: *mut Graph,
+    added_anchors: Slice<Anchor>,
+    removed_anchors: Slice<Anchor>,
+    changed_bounding_boxes: Slice<BoundingBoxChange>,
+) -> Result {
+    if graph.is_null()
+        || added_anchors.is_null()
+        || removed_anchors.is_null()
+        || changed_bounding_boxes.is_null()
+    {
+        return Result::NullPointerError;
+    }
+
+    let graph = unsafe { &mut *graph };
+    let added_anchors = unsafe { added_anchors.as_ref() };
+    let removed_anchors = unsafe { removed_anchors.as_ref() };
+    let changed_bounding_boxes = unsafe { changed_bounding_boxes.as_ref() };
+
+    match graph.update(added_anchors, removed_anchors, changed_bounding_boxes) {
+        Ok(()) => Result::Success,
+        Err(_) => Result::InvalidArgumentError,
+    }
+}
+
 /// Gets the nodes in a graph.
 ///
 /// **Parameters**  
@@ -304,6 +522,35 @@ pub unsafe extern "C" fn RT_graph_get_nodes(
     Result::Success
 }
 
+/// Gets the anchors inside bounding boxes that have no way out of them.
+///
+/// Every other anchor inside its bounding box has at least one neighbor in a direction it connects in.
+///
+/// **Parameters**  
+/// `graph`: The graph to get the blocked anchors of.  
+/// `[out] blocked_anchors`: The list of blocked anchors and why they are blocked.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph` or `blocked_anchors` was `NULL`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_graph_get_blocked_anchors(
+    graph: *const Graph,
+    blocked_anchors: *mut Slice<BlockedAnchor>,
+) -> Result {
+    if graph.is_null() || blocked_anchors.is_null() {
+        return Result::NullPointerError;
+    }
+
+    let graph = unsafe { &*graph };
+    unsafe {
+        blocked_anchors.write(graph.blocked_anchors().into());
+    }
+
+    Result::Success
+}
+
 /// Finds the node at a specific position in the graph.
 ///
 /// **Parameters**  
@@ -450,6 +697,88 @@ pub struct Endpoint {
     pub waypoint_count: u32,
 }
 
+#[derive(Debug, Clone, Copy, Serialize)]
+#[repr(C)]
+pub struct Waypoint {
+    /// The position of the waypoint.
+    pub position: Point,
+    /// The direction a wire has to travel in while passing through the waypoint, as seen when going away from the endpoint.
+    /// If empty, the waypoint can be passed through in any direction.
+    pub direction: Directions,
+}
+
+impl<'de> Deserialize<'de> for Waypoint {
+    /// Deserializes a waypoint from a query.
+    /// Queries serialized before waypoints had a direction store only the position, which is accepted as well.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        /// The first element of a serialized waypoint, or of a bare position.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Head {
+            Position(Point),
+            X(i32),
+        }
+
+        struct WaypointVisitor;
+
+        impl<'de> Visitor<'de> for WaypointVisitor {
+            type Value = Waypoint;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a waypoint or a position")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> std::result::Result<Waypoint, A::Error> {
+                let head = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let waypoint = match head {
+                    Head::Position(position) => Waypoint {
+                        position,
+                        direction: seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(1, &self))?,
+                    },
+                    Head::X(x) => Waypoint {
+                        position: Point {
+                            x,
+                            y: seq
+                                .next_element()?
+                                .ok_or_else(|| de::Error::invalid_length(1, &self))?,
+                        },
+                        direction: Directions::empty(),
+                    },
+                };
+
+                Ok(waypoint)
+            }
+        }
+
+        deserializer.deserialize_seq(WaypointVisitor)
+    }
+}
+
+impl Waypoint {
+    /// Converts the waypoint, or returns `None` if more than one direction is set.
+    fn to_waypoint(self) -> Option<routing::Waypoint> {
+        let mut directions = Direction::ALL
+            .into_iter()
+            .filter(|&dir| self.direction.contains(dir.into()));
+        let direction = directions.next();
+        if directions.next().is_some() {
+            return None;
+        }
+
+        Some(routing::Waypoint {
+            position: self.position,
+            direction,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[repr(C)]
 pub struct Net {
@@ -457,15 +786,27 @@ pub struct Net {
     pub endpoint_offset: u32,
     /// The number of endpoints in the net.
     pub endpoint_count: u32,
+    /// The rules the wires of the net have to follow.
+    #[serde(default)]
+    pub constraints: NetConstraints,
+    /// When routing by priority, nets with a higher priority are routed first.
+    #[serde(default)]
+    pub priority: u32,
 }
 
+/// A query to connect nets, as written by `RT_graph_serialize_connect_nets_query`.
+/// Fields added since the first version default if missing, so older queries can still be read.
 #[derive(Serialize, Deserialize)]
-struct GraphConnectNetsQuery {
-    graph: GraphData,
-    nets: Vec<Net>,
-    endpoints: Vec<Endpoint>,
-    waypoints: Vec<Point>,
-    perform_centering: bool,
+pub(crate) struct GraphConnectNetsQuery {
+    pub(crate) graph: GraphData,
+    pub(crate) nets: Vec<Net>,
+    pub(crate) endpoints: Vec<Endpoint>,
+    pub(crate) waypoints: Vec<Waypoint>,
+    pub(crate) perform_centering: bool,
+    #[serde(default)]
+    pub(crate) topology: NetTopology,
+    #[serde(default)]
+    pub(crate) cost_model: CostModel,
 }
 
 /// Serializes a query to connect nets in a graph.
@@ -475,6 +816,9 @@ struct GraphConnectNetsQuery {
 /// `nets`: The list of nets to serialize.  
 /// `endpoints`: The list of endpoints to serialize.  
 /// `waypoints`: The list of waypoints to serialize.  
+/// `perform_centering`: Whether to center wires in the alleys they run through.  
+/// `topology`: The shape of the wire trees connecting the endpoints of each net.  
+/// `cost_model`: The weights used to find the shortest paths.  
 /// `file_path`: The file to serialize the graph into.
 ///
 /// **Returns**  
@@ -489,8 +833,10 @@ pub unsafe extern "C" fn RT_graph_serialize_connect_nets_query(
     graph: *const Graph,
     nets: Slice<Net>,
     endpoints: Slice<Endpoint>,
-    waypoints: Slice<Point>,
+    waypoints: Slice<Waypoint>,
     perform_centering: bool,
+    topology: NetTopology,
+    cost_model: CostModel,
     file_path: *const c_char,
 ) -> Result {
     if graph.is_null()
@@ -513,6 +859,8 @@ pub unsafe extern "C" fn RT_graph_serialize_connect_nets_query(
         endpoints: endpoints.to_vec(),
         waypoints: waypoints.to_vec(),
         perform_centering,
+        topology,
+        cost_model,
     };
 
     let file_path = unsafe { CStr::from_ptr(file_path) };
@@ -531,8 +879,65 @@ pub unsafe extern "C" fn RT_graph_serialize_connect_nets_query(
     }
 }
 
+/// A problem that occurred while routing one of multiple nets.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct NetDiagnostic {
+    /// The index of the net the problem occurred in.
+    pub net: u32,
+    pub diagnostic: Diagnostic,
+}
+
+/// Receives the status of every net and the problems that occurred while routing the nets.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct DiagnosticsBuffer {
+    /// A list to write the status of every net into, in the order of the nets.
+    pub net_statuses: MutSlice<NetStatus>,
+    /// A list to write the problems that occurred into. Problems that do not fit are dropped.
+    pub diagnostics: MutSlice<NetDiagnostic>,
+    /// Receives the number of problems that occurred, including the ones that did not fit into `diagnostics`.
+    pub diagnostic_count: usize,
+}
+
+impl DiagnosticsBuffer {
+    #[inline]
+    fn is_null(&self) -> bool {
+        self.net_statuses.is_null() || self.diagnostics.is_null()
+    }
+
+    /// Writes the status and diagnostics of every net in `report`.
+    /// `net_statuses` must be exactly as long as the number of nets in `report`.
+    unsafe fn write(&mut self, report: &RoutingReport) {
+        let net_statuses = unsafe { self.net_statuses.as_uninit_mut() };
+        let diagnostics = unsafe { self.diagnostics.as_uninit_mut() };
+        assert_eq!(net_statuses.len(), report.net_count());
+
+        let mut diagnostic_count = 0;
+        for (net, net_status) in net_statuses.iter_mut().enumerate() {
+            net_status.write(report.status(net));
+
+            for &diagnostic in report.diagnostics(net) {
+                if let Some(slot) = diagnostics.get_mut(diagnostic_count) {
+                    slot.write(NetDiagnostic {
+                        net: net.try_into().expect("too many nets"),
+                        diagnostic,
+                    });
+                }
+
+                diagnostic_count += 1;
+            }
+        }
+
+        self.diagnostic_count = diagnostic_count;
+    }
+}
+
 /// Connects nets in a graph.
 ///
+/// The nets are routed in parallel, but their results are written into `vertices` and `wire_views` contiguously,
+/// in the order of `nets`, so the output is identical for identical input regardless of the number of threads.
+///
 /// **Parameters**  
 /// `graph`: The graph to connect the nets in.  
 /// `nets`: A list of nets to connect.  
@@ -540,33 +945,38 @@ pub unsafe extern "C" fn RT_graph_serialize_connect_nets_query(
 /// `waypoints`: A list of waypoints.  
 /// `vertices`: A list to write the found vertices into.  
 /// `wire_views`: A list to write the found wires into.  
-/// `net_views`: A list to write the found nets into.
+/// `net_views`: A list to write the found nets into.  
+/// `perform_centering`: Whether to center wires in the alleys they run through.  
+/// `topology`: The shape of the wire trees connecting the endpoints of each net.  
+/// `cost_model`: The weights used to find the shortest paths.  
+/// `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
 ///
 /// **Returns**  
 /// `RT_RESULT_SUCCESS`: The operation completed successfully.  
-/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr` or `net_views.ptr` was `NULL`.  
-/// `RT_RESULT_INVALID_OPERATION_ERROR`: One of the paths had an invalid start or end point.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.  
 /// `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.  
 /// `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.  
 /// `RT_RESULT_UNINITIALIZED_ERROR`: The thread pool has not been initialized yet.  
-/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or a net contained fewer than 2 endpoints.
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+/// a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
 #[no_mangle]
 #[must_use]
 pub unsafe extern "C" fn RT_graph_connect_nets(
     graph: *const Graph,
     nets: Slice<Net>,
     endpoints: Slice<Endpoint>,
-    waypoints: Slice<Point>,
-    vertices: MutSlice<Vertex>,
-    wire_views: MutSlice<WireView>,
+    waypoints: Slice<Waypoint>,
+    mut vertices: MutSlice<Vertex>,
+    mut wire_views: MutSlice<WireView>,
     mut net_views: MutSlice<NetView>,
     perform_centering: bool,
+    topology: NetTopology,
+    cost_model: CostModel,
+    diagnostics: *mut DiagnosticsBuffer,
 ) -> Result {
-    let num_cpus = NUM_CPUS.load(Ordering::Acquire);
-    if num_cpus == 0 {
+    if NUM_CPUS.load(Ordering::Acquire) == 0 {
         return Result::UninitializedError;
     }
-    assert_eq!(num_cpus as usize, rayon::current_num_threads());
 
     if graph.is_null()
         || nets.is_null()
@@ -575,11 +985,17 @@ pub unsafe extern "C" fn RT_graph_connect_nets(
         || vertices.is_null()
         || wire_views.is_null()
         || net_views.is_null()
+        || diagnostics.is_null()
     {
         return Result::NullPointerError;
     }
 
-    if nets.len != net_views.len {
+    let diagnostics = unsafe { &mut *diagnostics };
+    if diagnostics.is_null() {
+        return Result::NullPointerError;
+    }
+
+    if (nets.len != net_views.len) || (nets.len != diagnostics.net_statuses.len) {
         return Result::InvalidArgumentError;
     }
 
@@ -587,110 +1003,32 @@ pub unsafe extern "C" fn RT_graph_connect_nets(
     let nets = unsafe { nets.as_ref() };
     let endpoints = unsafe { endpoints.as_ref() };
     let waypoints = unsafe { waypoints.as_ref() };
+    let vertices = unsafe { vertices.as_uninit_mut() };
+    let wire_views = unsafe { wire_views.as_uninit_mut() };
     let net_views = unsafe { net_views.as_uninit_mut() };
 
-    let vertices_per_thread = vertices.len / (num_cpus as usize);
-    let wire_views_per_thread = wire_views.len / (num_cpus as usize);
-
-    struct MutableThreadlocalData {
-        vertices: Array<'static, Vertex>,
-        wire_views: Array<'static, WireView>,
-        ends: Vec<Point>,
-        centering_candidates: Vec<CenteringCandidate>,
-        junctions: JunctionMap,
-    }
-
-    struct ThreadlocalData {
-        mutable: RefCell<MutableThreadlocalData>,
-        vertex_base_offset: usize,
-        wire_base_offset: usize,
-    }
-
-    let next_thread_index: AtomicU16 = AtomicU16::new(0);
-    let threadlocal_data = ThreadLocal::new();
-
-    let result = nets
-        .par_iter()
-        .zip(net_views.par_iter_mut())
-        .try_for_each(|(net, net_view)| {
-            let threadlocal_data = threadlocal_data.get_or(|| {
-                let thread_index = next_thread_index.fetch_add(1, Ordering::AcqRel);
-                assert!(thread_index < num_cpus);
-                let thread_index = thread_index as usize;
-
-                let mut vertices = vertices;
-                let vertices_start = thread_index * vertices_per_thread;
-                let vertices_end = vertices_start + vertices_per_thread;
-                let mut vertices = unsafe { vertices.subslice_mut(vertices_start..vertices_end) };
-                let vertices = unsafe { vertices.as_uninit_mut().into() };
-
-                let mut wire_views = wire_views;
-                let wire_views_start = thread_index * wire_views_per_thread;
-                let wire_views_end = wire_views_start + wire_views_per_thread;
-                let mut wire_views =
-                    unsafe { wire_views.subslice_mut(wire_views_start..wire_views_end) };
-                let wire_views = unsafe { wire_views.as_uninit_mut().into() };
-
-                ThreadlocalData {
-                    mutable: RefCell::new(MutableThreadlocalData {
-                        vertices,
-                        wire_views,
-                        ends: Vec::new(),
-                        centering_candidates: Vec::new(),
-                        junctions: JunctionMap::default(),
-                    }),
-                    vertex_base_offset: vertices_start,
-                    wire_base_offset: wire_views_start,
-                }
-            });
-
-            let ThreadlocalData {
-                vertex_base_offset,
-                wire_base_offset,
-                ..
-            } = *threadlocal_data;
-
-            let MutableThreadlocalData {
-                vertices,
-                wire_views,
-                ends,
-                centering_candidates,
-                junctions,
-            } = &mut *threadlocal_data.mutable.borrow_mut();
-
-            let endpoint_start = net.endpoint_offset as usize;
-            let endpoint_end = endpoint_start + (net.endpoint_count as usize);
-            let endpoints = &endpoints[endpoint_start..endpoint_end];
-
-            let endpoints = endpoints.iter().map(|endpoint| {
-                let waypoint_start = endpoint.waypoint_offset as usize;
-                let waypoint_end = waypoint_start + (endpoint.waypoint_count as usize);
-                let waypoints = &waypoints[waypoint_start..waypoint_end];
+    let Some(nets) = collect_nets(nets, endpoints, waypoints) else {
+        return Result::InvalidArgumentError;
+    };
+    let mut report = RoutingReport::new();
 
-                routing::Endpoint {
-                    position: endpoint.position,
-                    waypoints: Cow::Borrowed(waypoints),
-                }
-            });
-
-            routing::connect_net(
-                graph,
-                endpoints,
-                vertex_base_offset,
-                wire_base_offset,
-                vertices,
-                wire_views,
-                net_view,
-                ends,
-                centering_candidates,
-                junctions,
-                perform_centering,
-                &mut NoReplay,
-            )
-        });
+    match graph.connect_nets_deterministic(
+        &nets,
+        vertices,
+        wire_views,
+        net_views,
+        perform_centering,
+        topology,
+        &cost_model,
+        &mut report,
+    ) {
+        Ok(()) => {
+            unsafe {
+                diagnostics.write(&report);
+            }
 
-    match result {
-        Ok(_) => Result::Success,
+            Result::Success
+        }
         Err(err) => err.into(),
     }
 }
@@ -804,28 +1142,35 @@ impl ReplayCapture for ReplayCallbacks {
 /// `vertices`: A list to write the found vertices into.  
 /// `wire_views`: A list to write the found wires into.  
 /// `net_views`: A list to write the found nets into.  
-/// `replay`: Callbacks for constructing a replay.
+/// `perform_centering`: Whether to center wires in the alleys they run through.  
+/// `topology`: The shape of the wire trees connecting the endpoints of each net.  
+/// `cost_model`: The weights used to find the shortest paths.  
+/// `replay`: Callbacks for constructing a replay.  
+/// `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
 ///
 /// **Returns**  
 /// `RT_RESULT_SUCCESS`: The operation completed successfully.  
-/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr` or `net_views.ptr` was `NULL`.  
-/// `RT_RESULT_INVALID_OPERATION_ERROR`: One of the paths had an invalid start or end point.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.  
 /// `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.  
 /// `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.  
 /// `RT_RESULT_UNINITIALIZED_ERROR`: The thread pool has not been initialized yet.  
-/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or a net contained fewer than 2 endpoints.
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+/// a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
 #[no_mangle]
 #[must_use]
 pub unsafe extern "C" fn RT_graph_connect_nets_replay(
     graph: *const Graph,
     nets: Slice<Net>,
     endpoints: Slice<Endpoint>,
-    waypoints: Slice<Point>,
+    waypoints: Slice<Waypoint>,
     mut vertices: MutSlice<Vertex>,
     mut wire_views: MutSlice<WireView>,
     mut net_views: MutSlice<NetView>,
     perform_centering: bool,
+    topology: NetTopology,
+    cost_model: CostModel,
     mut replay: ReplayCallbacks,
+    diagnostics: *mut DiagnosticsBuffer,
 ) -> Result {
     let num_cpus = NUM_CPUS.load(Ordering::Acquire);
     if num_cpus == 0 {
@@ -840,11 +1185,17 @@ pub unsafe extern "C" fn RT_graph_connect_nets_replay(
         || vertices.is_null()
         || wire_views.is_null()
         || net_views.is_null()
+        || diagnostics.is_null()
     {
         return Result::NullPointerError;
     }
 
-    if nets.len != net_views.len {
+    let diagnostics = unsafe { &mut *diagnostics };
+    if diagnostics.is_null() {
+        return Result::NullPointerError;
+    }
+
+    if (nets.len != net_views.len) || (nets.len != diagnostics.net_statuses.len) {
         return Result::InvalidArgumentError;
     }
 
@@ -852,6 +1203,9 @@ pub unsafe extern "C" fn RT_graph_connect_nets_replay(
     let nets = unsafe { nets.as_ref() };
     let endpoints = unsafe { endpoints.as_ref() };
     let waypoints = unsafe { waypoints.as_ref() };
+    let Some(nets) = collect_nets(nets, endpoints, waypoints) else {
+        return Result::InvalidArgumentError;
+    };
     let net_views = unsafe { net_views.as_uninit_mut() };
 
     let mut vertices = unsafe { vertices.as_uninit_mut().into() };
@@ -859,26 +1213,13 @@ pub unsafe extern "C" fn RT_graph_connect_nets_replay(
     let mut ends = Vec::new();
     let mut centering_candidates = Vec::new();
     let mut junctions = JunctionMap::default();
+    let mut net_diagnostics = NetDiagnostics::default();
+    let mut nets_diagnostics = Vec::with_capacity(nets.len());
 
     for (net, net_view) in nets.iter().zip(net_views.iter_mut()) {
-        let endpoint_start = net.endpoint_offset as usize;
-        let endpoint_end = endpoint_start + (net.endpoint_count as usize);
-        let endpoints = &endpoints[endpoint_start..endpoint_end];
-
-        let endpoints = endpoints.iter().map(|endpoint| {
-            let waypoint_start = endpoint.waypoint_offset as usize;
-            let waypoint_end = waypoint_start + (endpoint.waypoint_count as usize);
-            let waypoints = &waypoints[waypoint_start..waypoint_end];
-
-            routing::Endpoint {
-                position: endpoint.position,
-                waypoints: Cow::Borrowed(waypoints),
-            }
-        });
-
         let result = routing::connect_net(
             graph,
-            endpoints,
+            net.endpoints.iter(),
             0,
             0,
             &mut vertices,
@@ -887,15 +1228,802 @@ pub unsafe extern "C" fn RT_graph_connect_nets_replay(
             &mut ends,
             &mut centering_candidates,
             &mut junctions,
+            &mut net_diagnostics,
             perform_centering,
+            topology,
+            &cost_model,
+            &net.constraints,
+            None,
             &mut replay,
         );
 
         match result {
-            Ok(_) => (),
+            Ok(_) => nets_diagnostics.push(net_diagnostics.clone()),
             Err(err) => return err.into(),
         }
     }
 
+    let mut report = RoutingReport::new();
+    report.set_nets(nets_diagnostics);
+    unsafe {
+        diagnostics.write(&report);
+    }
+
+    Result::Success
+}
+
+/// Converts an endpoint and its waypoints, or returns `None` if a waypoint is invalid.
+fn collect_endpoint(
+    endpoint: &Endpoint,
+    waypoints: &[Waypoint],
+) -> Option<routing::Endpoint<'static>> {
+    let waypoint_start = endpoint.waypoint_offset as usize;
+    let waypoint_end = waypoint_start + (endpoint.waypoint_count as usize);
+    let waypoints = waypoints[waypoint_start..waypoint_end]
+        .iter()
+        .map(|waypoint| waypoint.to_waypoint())
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(routing::Endpoint {
+        position: endpoint.position,
+        waypoints: Cow::Owned(waypoints),
+    })
+}
+
+/// Gathers the endpoints, waypoints and constraints of every net, or returns `None` if a waypoint is invalid.
+fn collect_nets(
+    nets: &[Net],
+    endpoints: &[Endpoint],
+    waypoints: &[Waypoint],
+) -> Option<Vec<NetSpec<'static>>> {
+    nets.iter()
+        .map(|net| {
+            let endpoint_start = net.endpoint_offset as usize;
+            let endpoint_end = endpoint_start + (net.endpoint_count as usize);
+            let endpoints = endpoints[endpoint_start..endpoint_end]
+                .iter()
+                .map(|endpoint| collect_endpoint(endpoint, waypoints))
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(
+                NetSpec::from(endpoints)
+                    .with_constraints(net.constraints)
+                    .with_priority(net.priority),
+            )
+        })
+        .collect()
+}
+
+/// Sets the endpoints and constraints of every net in a session.
+fn set_session_nets(session: &mut RoutingSession, nets: &[NetSpec]) {
+    session.set_nets(nets.iter().map(|net| net.endpoints.iter()));
+
+    for (index, net) in nets.iter().enumerate() {
+        session.set_constraints(index, net.constraints);
+    }
+}
+
+/// Connects nets in a graph one after another, making wires avoid running on top of or crossing
+/// the wires of nets that were connected before.
+///
+/// **Parameters**  
+/// `graph`: The graph to connect the nets in.  
+/// `nets`: A list of nets to connect.  
+/// `endpoints`: A list of endpoints.  
+/// `waypoints`: A list of waypoints.  
+/// `vertices`: A list to write the found vertices into.  
+/// `wire_views`: A list to write the found wires into.  
+/// `net_views`: A list to write the found nets into.  
+/// `perform_centering`: Whether to center wires in the alleys they run through.  
+/// `topology`: The shape of the wire trees connecting the endpoints of each net.  
+/// `cost_model`: The weights used to find the shortest paths.  
+/// `congestion_costs`: The costs of running on top of or crossing wires of other nets.  
+/// `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.  
+/// `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.  
+/// `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+/// a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_graph_connect_nets_congestion_aware(
+    graph: *const Graph,
+    nets: Slice<Net>,
+    endpoints: Slice<Endpoint>,
+    waypoints: Slice<Waypoint>,
+    mut vertices: MutSlice<Vertex>,
+    mut wire_views: MutSlice<WireView>,
+    mut net_views: MutSlice<NetView>,
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: CostModel,
+    congestion_costs: CongestionCosts,
+    diagnostics: *mut DiagnosticsBuffer,
+) -> Result {
+    if graph.is_null()
+        || nets.is_null()
+        || endpoints.is_null()
+        || waypoints.is_null()
+        || vertices.is_null()
+        || wire_views.is_null()
+        || net_views.is_null()
+        || diagnostics.is_null()
+    {
+        return Result::NullPointerError;
+    }
+
+    let diagnostics = unsafe { &mut *diagnostics };
+    if diagnostics.is_null() {
+        return Result::NullPointerError;
+    }
+
+    if (nets.len != net_views.len) || (nets.len != diagnostics.net_statuses.len) {
+        return Result::InvalidArgumentError;
+    }
+
+    let graph = unsafe { &*graph };
+    let nets = unsafe { nets.as_ref() };
+    let endpoints = unsafe { endpoints.as_ref() };
+    let waypoints = unsafe { waypoints.as_ref() };
+    let vertices = unsafe { vertices.as_uninit_mut() };
+    let wire_views = unsafe { wire_views.as_uninit_mut() };
+    let net_views = unsafe { net_views.as_uninit_mut() };
+
+    let Some(nets) = collect_nets(nets, endpoints, waypoints) else {
+        return Result::InvalidArgumentError;
+    };
+    let mut report = RoutingReport::new();
+
+    match graph.connect_nets_congestion_aware(
+        &nets,
+        vertices,
+        wire_views,
+        net_views,
+        perform_centering,
+        topology,
+        &cost_model,
+        congestion_costs,
+        &mut report,
+    ) {
+        Ok(()) => {
+            unsafe {
+                diagnostics.write(&report);
+            }
+
+            Result::Success
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// Connects nets in a graph, repeatedly ripping up and rerouting the nets running on top of each other
+/// until no channel is used by more than one net or the iteration limit is reached.
+///
+/// **Parameters**  
+/// `graph`: The graph to connect the nets in.  
+/// `nets`: A list of nets to connect.  
+/// `endpoints`: A list of endpoints.  
+/// `waypoints`: A list of waypoints.  
+/// `vertices`: A list to write the found vertices into.  
+/// `wire_views`: A list to write the found wires into.  
+/// `net_views`: A list to write the found nets into.  
+/// `perform_centering`: Whether to center wires in the alleys they run through.  
+/// `topology`: The shape of the wire trees connecting the endpoints of each net.  
+/// `cost_model`: The weights used to find the shortest paths.  
+/// `congestion_costs`: The costs of running on top of or crossing wires of other nets.  
+/// `settings`: Controls how the congestion is negotiated.  
+/// `[out] outcome`: Statistics about the performed negotiation.  
+/// `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `outcome`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.  
+/// `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.  
+/// `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+/// a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_graph_connect_nets_negotiated(
+    graph: *const Graph,
+    nets: Slice<Net>,
+    endpoints: Slice<Endpoint>,
+    waypoints: Slice<Waypoint>,
+    mut vertices: MutSlice<Vertex>,
+    mut wire_views: MutSlice<WireView>,
+    mut net_views: MutSlice<NetView>,
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: CostModel,
+    congestion_costs: CongestionCosts,
+    settings: NegotiationSettings,
+    outcome: *mut NegotiationOutcome,
+    diagnostics: *mut DiagnosticsBuffer,
+) -> Result {
+    if graph.is_null()
+        || nets.is_null()
+        || endpoints.is_null()
+        || waypoints.is_null()
+        || vertices.is_null()
+        || wire_views.is_null()
+        || net_views.is_null()
+        || outcome.is_null()
+        || diagnostics.is_null()
+    {
+        return Result::NullPointerError;
+    }
+
+    let diagnostics = unsafe { &mut *diagnostics };
+    if diagnostics.is_null() {
+        return Result::NullPointerError;
+    }
+
+    if (nets.len != net_views.len) || (nets.len != diagnostics.net_statuses.len) {
+        return Result::InvalidArgumentError;
+    }
+
+    let graph = unsafe { &*graph };
+    let nets = unsafe { nets.as_ref() };
+    let endpoints = unsafe { endpoints.as_ref() };
+    let waypoints = unsafe { waypoints.as_ref() };
+    let vertices = unsafe { vertices.as_uninit_mut() };
+    let wire_views = unsafe { wire_views.as_uninit_mut() };
+    let net_views = unsafe { net_views.as_uninit_mut() };
+
+    let Some(nets) = collect_nets(nets, endpoints, waypoints) else {
+        return Result::InvalidArgumentError;
+    };
+    let mut report = RoutingReport::new();
+
+    match graph.connect_nets_negotiated(
+        &nets,
+        vertices,
+        wire_views,
+        net_views,
+        perform_centering,
+        topology,
+        &cost_model,
+        congestion_costs,
+        settings,
+        &mut report,
+    ) {
+        Ok(result) => {
+            unsafe {
+                outcome.write(result);
+                diagnostics.write(&report);
+            }
+
+            Result::Success
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// Connects nets in a graph one after another in order of descending priority, making wires avoid running
+/// on top of or crossing the wires of nets with a higher priority.
+/// Nets with the same priority are connected in the order they appear in `nets`.
+///
+/// **Parameters**  
+/// `graph`: The graph to connect the nets in.  
+/// `nets`: A list of nets to connect.  
+/// `endpoints`: A list of endpoints.  
+/// `waypoints`: A list of waypoints.  
+/// `vertices`: A list to write the found vertices into.  
+/// `wire_views`: A list to write the found wires into.  
+/// `net_views`: A list to write the found nets into, in the order of `nets`.  
+/// `perform_centering`: Whether to center wires in the alleys they run through.  
+/// `topology`: The shape of the wire trees connecting the endpoints of each net.  
+/// `cost_model`: The weights used to find the shortest paths.  
+/// `congestion_costs`: The costs of running on top of or crossing wires of other nets.  
+/// `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.  
+/// `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.  
+/// `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+/// a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_graph_connect_nets_by_priority(
+    graph: *const Graph,
+    nets: Slice<Net>,
+    endpoints: Slice<Endpoint>,
+    waypoints: Slice<Waypoint>,
+    mut vertices: MutSlice<Vertex>,
+    mut wire_views: MutSlice<WireView>,
+    mut net_views: MutSlice<NetView>,
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: CostModel,
+    congestion_costs: CongestionCosts,
+    diagnostics: *mut DiagnosticsBuffer,
+) -> Result {
+    if graph.is_null()
+        || nets.is_null()
+        || endpoints.is_null()
+        || waypoints.is_null()
+        || vertices.is_null()
+        || wire_views.is_null()
+        || net_views.is_null()
+        || diagnostics.is_null()
+    {
+        return Result::NullPointerError;
+    }
+
+    let diagnostics = unsafe { &mut *diagnostics };
+    if diagnostics.is_null() {
+        return Result::NullPointerError;
+    }
+
+    if (nets.len != net_views.len) || (nets.len != diagnostics.net_statuses.len) {
+        return Result::InvalidArgumentError;
+    }
+
+    let graph = unsafe { &*graph };
+    let nets = unsafe { nets.as_ref() };
+    let endpoints = unsafe { endpoints.as_ref() };
+    let waypoints = unsafe { waypoints.as_ref() };
+    let vertices = unsafe { vertices.as_uninit_mut() };
+    let wire_views = unsafe { wire_views.as_uninit_mut() };
+    let net_views = unsafe { net_views.as_uninit_mut() };
+
+    let Some(nets) = collect_nets(nets, endpoints, waypoints) else {
+        return Result::InvalidArgumentError;
+    };
+    let mut report = RoutingReport::new();
+
+    match graph.connect_nets_by_priority(
+        &nets,
+        vertices,
+        wire_views,
+        net_views,
+        perform_centering,
+        topology,
+        &cost_model,
+        congestion_costs,
+        &mut report,
+    ) {
+        Ok(()) => {
+            unsafe {
+                diagnostics.write(&report);
+            }
+
+            Result::Success
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// Connects the nets of a bus as a bundle of parallel wires with the same bends.
+///
+/// Every net has to consist of a start and an end, in that order. The first net is routed through the graph,
+/// and all other nets follow it at the constant distance between their start and the start of the first net.
+/// Nets that cannot follow the bundle are routed on their own instead.
+///
+/// **Parameters**  
+/// `graph`: The graph to connect the nets in.  
+/// `nets`: A list of nets to connect.  
+/// `endpoints`: A list of endpoints.  
+/// `waypoints`: A list of waypoints.  
+/// `vertices`: A list to write the found vertices into.  
+/// `wire_views`: A list to write the found wires into.  
+/// `net_views`: A list to write the found nets into.  
+/// `cost_model`: The weights used to find the shortest paths.  
+/// `[out] bundled_count`: The number of nets that were routed as part of the bundle.  
+/// `[out] diagnostics`: Receives the status of every net and the problems that occurred while routing the nets.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr`, `net_views.ptr`, `bundled_count`, `diagnostics`, `diagnostics->net_statuses.ptr` or `diagnostics->diagnostics.ptr` was `NULL`.  
+/// `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.  
+/// `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len` or `diagnostics->net_statuses.len`, a net contained fewer than 2 endpoints,
+/// a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_graph_connect_bus(
+    graph: *const Graph,
+    nets: Slice<Net>,
+    endpoints: Slice<Endpoint>,
+    waypoints: Slice<Waypoint>,
+    mut vertices: MutSlice<Vertex>,
+    mut wire_views: MutSlice<WireView>,
+    mut net_views: MutSlice<NetView>,
+    cost_model: CostModel,
+    bundled_count: *mut u32,
+    diagnostics: *mut DiagnosticsBuffer,
+) -> Result {
+    if graph.is_null()
+        || nets.is_null()
+        || endpoints.is_null()
+        || waypoints.is_null()
+        || vertices.is_null()
+        || wire_views.is_null()
+        || net_views.is_null()
+        || bundled_count.is_null()
+        || diagnostics.is_null()
+    {
+        return Result::NullPointerError;
+    }
+
+    let diagnostics = unsafe { &mut *diagnostics };
+    if diagnostics.is_null() {
+        return Result::NullPointerError;
+    }
+
+    if (nets.len != net_views.len) || (nets.len != diagnostics.net_statuses.len) {
+        return Result::InvalidArgumentError;
+    }
+
+    let graph = unsafe { &*graph };
+    let nets = unsafe { nets.as_ref() };
+    let endpoints = unsafe { endpoints.as_ref() };
+    let waypoints = unsafe { waypoints.as_ref() };
+    let vertices = unsafe { vertices.as_uninit_mut() };
+    let wire_views = unsafe { wire_views.as_uninit_mut() };
+    let net_views = unsafe { net_views.as_uninit_mut() };
+
+    let Some(nets) = collect_nets(nets, endpoints, waypoints) else {
+        return Result::InvalidArgumentError;
+    };
+    let mut report = RoutingReport::new();
+
+    match graph.connect_bus(
+        &nets,
+        vertices,
+        wire_views,
+        net_views,
+        &cost_model,
+        &mut report,
+    ) {
+        Ok(count) => {
+            unsafe {
+                bundled_count.write(count.try_into().expect("too many nets"));
+                diagnostics.write(&report);
+            }
+
+            Result::Success
+        }
+        Err(err) => err.into(),
+    }
+}
+
+/// Creates a new routing session.
+///
+/// **Parameters**  
+/// `[out] session`: The created routing session.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `session` was `NULL`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_session_new(session: *mut *mut RoutingSession) -> Result {
+    if session.is_null() {
+        return Result::NullPointerError;
+    }
+
+    let ptr = Box::into_raw(Box::new(RoutingSession::default()));
+    unsafe {
+        session.write(ptr);
+    }
+
+    Result::Success
+}
+
+/// Marks all nets in a routing session whose wires cross one of the given regions as needing to be routed again.
+///
+/// **Parameters**  
+/// `session`: The routing session.  
+/// `regions`: A list of regions that changed, for example the old and new extent of moved bounding boxes.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `session` or `regions.ptr` was `NULL`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_session_invalidate_regions(
+    session: *mut RoutingSession,
+    regions: Slice<BoundingBox>,
+) -> Result {
+    if session.is_null() || regions.is_null() {
+        return Result::NullPointerError;
+    }
+
+    let session = unsafe { &mut *session };
+    let regions = unsafe { regions.as_ref() };
+    for &region in regions {
+        session.invalidate_region(region);
+    }
+
+    Result::Success
+}
+
+/// Connects nets in a graph, only routing the nets that changed since the last call with the same session.
+///
+/// **Parameters**  
+/// `session`: The routing session holding the previous results.  
+/// `graph`: The graph to connect the nets in.  
+/// `nets`: A list of nets to connect.  
+/// `endpoints`: A list of endpoints.  
+/// `waypoints`: A list of waypoints.  
+/// `vertices`: A list to write the vertices of all nets into.  
+/// `wire_views`: A list to write the wires of all nets into.  
+/// `net_views`: A list to write the nets into.  
+/// `perform_centering`: Whether to center wires in the alleys they run through.  
+/// `topology`: The shape of the wire trees connecting the endpoints of each net.  
+/// `cost_model`: The weights used to find the shortest paths.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `session`, `graph`, `nets.ptr`, `endpoints.ptr`, `waypoints.ptr`, `vertices.ptr`, `wire_views.ptr` or `net_views.ptr` was `NULL`.  
+/// `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.  
+/// `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.  
+/// `RT_RESULT_UNINITIALIZED_ERROR`: The thread pool has not been initialized yet.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `nets.len` was not equal to `net_views.len`, a net contained fewer than 2 endpoints,
+/// a waypoint had more than one direction, or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_session_connect_nets(
+    session: *mut RoutingSession,
+    graph: *const Graph,
+    nets: Slice<Net>,
+    endpoints: Slice<Endpoint>,
+    waypoints: Slice<Waypoint>,
+    mut vertices: MutSlice<Vertex>,
+    mut wire_views: MutSlice<WireView>,
+    mut net_views: MutSlice<NetView>,
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: CostModel,
+) -> Result {
+    let num_cpus = NUM_CPUS.load(Ordering::Acquire);
+    if num_cpus == 0 {
+        return Result::UninitializedError;
+    }
+    assert_eq!(num_cpus as usize, rayon::current_num_threads());
+
+    if session.is_null()
+        || graph.is_null()
+        || nets.is_null()
+        || endpoints.is_null()
+        || waypoints.is_null()
+        || vertices.is_null()
+        || wire_views.is_null()
+        || net_views.is_null()
+    {
+        return Result::NullPointerError;
+    }
+
+    if nets.len != net_views.len {
+        return Result::InvalidArgumentError;
+    }
+
+    let session = unsafe { &mut *session };
+    let graph = unsafe { &*graph };
+    let nets = unsafe { nets.as_ref() };
+    let endpoints = unsafe { endpoints.as_ref() };
+    let waypoints = unsafe { waypoints.as_ref() };
+    let vertices = unsafe { vertices.as_uninit_mut() };
+    let wire_views = unsafe { wire_views.as_uninit_mut() };
+    let net_views = unsafe { net_views.as_uninit_mut() };
+
+    let Some(nets) = collect_nets(nets, endpoints, waypoints) else {
+        return Result::InvalidArgumentError;
+    };
+    set_session_nets(session, &nets);
+
+    if let Err(err) = session.route(graph, perform_centering, topology, &cost_model) {
+        return err.into();
+    }
+
+    match session.write_results(vertices, wire_views, net_views) {
+        Ok(()) => Result::Success,
+        Err(err) => err.into(),
+    }
+}
+
+/// Routes all nets that changed since the last call with the same session, keeping the results inside the session.
+///
+/// The results can be retrieved with `RT_session_get_result_sizes` and `RT_session_write_results`.
+///
+/// **Parameters**  
+/// `session`: The routing session holding the previous results.  
+/// `graph`: The graph to connect the nets in.  
+/// `nets`: A list of nets to connect.  
+/// `endpoints`: A list of endpoints.  
+/// `waypoints`: A list of waypoints.  
+/// `perform_centering`: Whether to center wires in the alleys they run through.  
+/// `topology`: The shape of the wire trees connecting the endpoints of each net.  
+/// `cost_model`: The weights used to find the shortest paths.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `session`, `graph`, `nets.ptr`, `endpoints.ptr` or `waypoints.ptr` was `NULL`.  
+/// `RT_RESULT_UNINITIALIZED_ERROR`: The thread pool has not been initialized yet.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: A net contained fewer than 2 endpoints, a waypoint had more than one direction,
+/// or the bend limit of a net was above `RT_NetConstraints_MAX_BEND_LIMIT`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_session_route_nets(
+    session: *mut RoutingSession,
+    graph: *const Graph,
+    nets: Slice<Net>,
+    endpoints: Slice<Endpoint>,
+    waypoints: Slice<Waypoint>,
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: CostModel,
+) -> Result {
+    if NUM_CPUS.load(Ordering::Acquire) == 0 {
+        return Result::UninitializedError;
+    }
+
+    if session.is_null()
+        || graph.is_null()
+        || nets.is_null()
+        || endpoints.is_null()
+        || waypoints.is_null()
+    {
+        return Result::NullPointerError;
+    }
+
+    let session = unsafe { &mut *session };
+    let graph = unsafe { &*graph };
+    let nets = unsafe { nets.as_ref() };
+    let endpoints = unsafe { endpoints.as_ref() };
+    let waypoints = unsafe { waypoints.as_ref() };
+
+    let Some(nets) = collect_nets(nets, endpoints, waypoints) else {
+        return Result::InvalidArgumentError;
+    };
+    set_session_nets(session, &nets);
+
+    match session.route(graph, perform_centering, topology, &cost_model) {
+        Ok(_) => Result::Success,
+        Err(err) => err.into(),
+    }
+}
+
+/// Gets the buffer sizes required to hold the results of a routing session.
+///
+/// **Parameters**  
+/// `session`: The routing session.  
+/// `[out] vertex_count`: The total number of vertices of all nets.  
+/// `[out] wire_view_count`: The total number of wires of all nets.  
+/// `[out] net_count`: The number of nets.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `session`, `vertex_count`, `wire_view_count` or `net_count` was `NULL`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_session_get_result_sizes(
+    session: *const RoutingSession,
+    vertex_count: *mut usize,
+    wire_view_count: *mut usize,
+    net_count: *mut usize,
+) -> Result {
+    if session.is_null()
+        || vertex_count.is_null()
+        || wire_view_count.is_null()
+        || net_count.is_null()
+    {
+        return Result::NullPointerError;
+    }
+
+    let session = unsafe { &*session };
+    unsafe {
+        vertex_count.write(session.vertex_count());
+        wire_view_count.write(session.wire_view_count());
+        net_count.write(session.net_count());
+    }
+
+    Result::Success
+}
+
+/// Gets the status of a net in a routing session and the problems that occurred while routing it.
+///
+/// The diagnostics remain valid until the session is routed again or freed.
+///
+/// **Parameters**  
+/// `session`: The routing session.  
+/// `net`: The index of the net.  
+/// `[out] status`: How completely the net was routed.  
+/// `[out] diagnostics`: The problems that occurred while routing the net.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `session`, `status` or `diagnostics` was `NULL`.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `net` was not less than the number of nets in the session.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_session_get_net_diagnostics(
+    session: *const RoutingSession,
+    net: usize,
+    status: *mut NetStatus,
+    diagnostics: *mut Slice<Diagnostic>,
+) -> Result {
+    if session.is_null() || status.is_null() || diagnostics.is_null() {
+        return Result::NullPointerError;
+    }
+
+    let session = unsafe { &*session };
+    if net >= session.net_count() {
+        return Result::InvalidArgumentError;
+    }
+
+    unsafe {
+        status.write(session.status(net));
+        diagnostics.write(session.diagnostics(net).into());
+    }
+
+    Result::Success
+}
+
+/// Writes the results of a routing session into contiguous buffers.
+///
+/// **Parameters**  
+/// `session`: The routing session.  
+/// `vertices`: A list to write the vertices of all nets into.  
+/// `wire_views`: A list to write the wires of all nets into.  
+/// `net_views`: A list to write the nets into.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `session`, `vertices.ptr`, `wire_views.ptr` or `net_views.ptr` was `NULL`.  
+/// `RT_RESULT_VERTEX_BUFFER_OVERFLOW_ERROR`: The capacity of `vertices` was too small to hold all vertices.  
+/// `RT_RESULT_WIRE_VIEW_BUFFER_OVERFLOW_ERROR`: The capacity of `wire_views` was too small to hold all wire views.  
+/// `RT_RESULT_INVALID_ARGUMENT_ERROR`: `net_views.len` was not equal to the number of nets in the session.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_session_write_results(
+    session: *const RoutingSession,
+    mut vertices: MutSlice<Vertex>,
+    mut wire_views: MutSlice<WireView>,
+    mut net_views: MutSlice<NetView>,
+) -> Result {
+    if session.is_null() || vertices.is_null() || wire_views.is_null() || net_views.is_null() {
+        return Result::NullPointerError;
+    }
+
+    let session = unsafe { &*session };
+    if net_views.len != session.net_count() {
+        return Result::InvalidArgumentError;
+    }
+
+    let vertices = unsafe { vertices.as_uninit_mut() };
+    let wire_views = unsafe { wire_views.as_uninit_mut() };
+    let net_views = unsafe { net_views.as_uninit_mut() };
+
+    match session.write_results(vertices, wire_views, net_views) {
+        Ok(()) => Result::Success,
+        Err(err) => err.into(),
+    }
+}
+
+/// Frees a routing session.
+///
+/// **Parameters**  
+/// `session`: The routing session to free.
+///
+/// **Returns**  
+/// `RT_RESULT_SUCCESS`: The operation completed successfully.  
+/// `RT_RESULT_NULL_POINTER_ERROR`: `session` was `NULL`.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn RT_session_free(session: *mut RoutingSession) -> Result {
+    if session.is_null() {
+        return Result::NullPointerError;
+    }
+
+    let session = unsafe { Box::from_raw(session) };
+    std::mem::drop(session);
+
     Result::Success
 }
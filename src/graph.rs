@@ -1,5 +1,5 @@
 use crate::segment_tree::*;
-use crate::HashMap;
+use crate::{HashMap, HashSet};
 use bitflags::bitflags;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -100,7 +100,7 @@ impl TryFrom<u32> for BoundingBoxIndex {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum Direction {
     PosX = 0,
@@ -184,7 +184,7 @@ impl Default for Point {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(C)]
 pub struct Anchor {
     /// The position of the anchor.
@@ -222,7 +222,7 @@ impl Anchor {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(C)]
 pub struct BoundingBox {
     /// The center of the bounding box.
@@ -287,6 +287,168 @@ impl BoundingBox {
     }
 }
 
+/// Controls the tracks wires can run on around bounding boxes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(C)]
+pub struct TrackSettings {
+    /// The number of parallel tracks on every side of a bounding box.
+    pub track_count: u32,
+    /// The minimum distance between a bounding box and its first track, as well as between two neighboring tracks.
+    pub wire_spacing: u32,
+    /// If not 0, tracks are only placed on multiples of this value.
+    pub grid_pitch: u32,
+    /// The maximum number of additional tracks in the channel between two bounding boxes facing each other,
+    /// placed after the tracks around the left or top one, as long as they stay clear of the tracks around the other one.
+    pub channel_track_count: u32,
+}
+
+impl Default for TrackSettings {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            track_count: 1,
+            wire_spacing: 1,
+            grid_pitch: 0,
+            channel_track_count: 0,
+        }
+    }
+}
+
+impl TrackSettings {
+    /// The position of the next track after `coord`, moving away from it in the direction of `sign`.
+    ///
+    /// Tracks that would lie outside the range of `i32` are clamped to it.
+    fn next_track(self, coord: i32, sign: i32) -> i32 {
+        let spacing = i64::from(self.wire_spacing.max(1));
+        let coord = i64::from(coord) + i64::from(sign.signum()) * spacing;
+
+        let track = match i64::from(self.grid_pitch) {
+            0 => coord,
+            pitch if sign < 0 => coord.div_euclid(pitch) * pitch,
+            pitch => -(-coord).div_euclid(pitch) * pitch,
+        };
+
+        track.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32
+    }
+
+    /// The position of the track `count` tracks after `coord`, moving away from it in the direction of `sign`.
+    fn nth_track(self, coord: i32, sign: i32, count: u32) -> i32 {
+        (0..count).fold(coord, |coord, _| self.next_track(coord, sign))
+    }
+
+    /// Determines if wires of different nets can run next to each other on distinct tracks.
+    #[inline]
+    pub(crate) fn has_parallel_tracks(self) -> bool {
+        (self.track_count > 1) || (self.channel_track_count > 0)
+    }
+
+    /// How far from the edges of a bounding box the tracks around it, the tracks in the channels it faces
+    /// and the escape stubs of its anchors can depend on other bounding boxes.
+    fn reach(self) -> i32 {
+        let step = i64::from(self.wire_spacing.max(1)) + i64::from(self.grid_pitch);
+        let track_count = match self.channel_track_count {
+            0 => 1,
+            channel_track_count => {
+                2 * i64::from(self.track_count) + i64::from(channel_track_count) + 1
+            }
+        };
+        step.saturating_mul(track_count).min(i64::from(i32::MAX)) as i32
+    }
+}
+
+/// How strictly wires are kept out of a keep-out zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u32)]
+pub enum KeepOutKind {
+    /// Wires never enter the zone.
+    Hard = 0,
+    /// Wires may enter the zone, but paths through it are more expensive.
+    Soft = 1,
+}
+
+/// A region wires should stay out of that is not a component, like a title block or a comment frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeepOut {
+    /// The extents of the zone.
+    pub bounding_box: BoundingBox,
+    /// How strictly wires are kept out of the zone.
+    pub kind: KeepOutKind,
+    /// For soft keep-outs, the factor the cost of path segments running through the zone is multiplied with.
+    /// The cost of a whole edge of the graph is multiplied, even if only part of the edge lies inside the zone.
+    pub cost_multiplier: u32,
+    /// Whether to place anchors at the corners of the tracks around the zone, like around bounding boxes.
+    pub auto_anchors: bool,
+}
+
+impl KeepOut {
+    /// A zone wires never enter.
+    #[inline]
+    pub const fn hard(bounding_box: BoundingBox) -> Self {
+        Self {
+            bounding_box,
+            kind: KeepOutKind::Hard,
+            cost_multiplier: 1,
+            auto_anchors: false,
+        }
+    }
+
+    /// A zone that multiplies the cost of path segments running through it with `cost_multiplier`.
+    #[inline]
+    pub const fn soft(bounding_box: BoundingBox, cost_multiplier: u32) -> Self {
+        Self {
+            bounding_box,
+            kind: KeepOutKind::Soft,
+            cost_multiplier,
+            auto_anchors: false,
+        }
+    }
+
+    #[inline]
+    pub const fn with_auto_anchors(self) -> Self {
+        Self {
+            auto_anchors: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct BoundingBoxChange {
+    /// The index of the bounding box that changed.
+    pub index: BoundingBoxIndex,
+    /// The new extents of the bounding box.
+    pub bounding_box: BoundingBox,
+}
+
+/// Why an anchor has no way out of its bounding box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u32)]
+pub enum EscapeBlockedReason {
+    /// The anchor does not connect in any direction.
+    NoConnectDirections = 0,
+    /// In every direction the anchor connects in, another bounding box is too close to fit a wire in between.
+    Obstructed = 1,
+}
+
+/// An anchor inside its bounding box that has no way out of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(C)]
+pub struct BlockedAnchor {
+    /// The position of the anchor.
+    pub position: Point,
+    /// Why the anchor has no way out of its bounding box.
+    pub reason: EscapeBlockedReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphUpdateError {
+    /// One of the removed anchors was not part of the graph.
+    AnchorNotFound,
+    /// One of the changed bounding boxes did not exist.
+    InvalidBoundingBoxIndex,
+}
+
 /// cbindgen:field-names=[pos_x, neg_x, pos_y, neg_y]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[repr(C)]
@@ -325,13 +487,7 @@ impl NeighborList {
             return None;
         }
 
-        for dir in Direction::ALL {
-            if self[dir] == node {
-                return Some(dir);
-            }
-        }
-
-        None
+        Direction::ALL.into_iter().find(|&dir| self[dir] == node)
     }
 }
 
@@ -406,6 +562,11 @@ impl Node {
 pub(crate) struct NodeList(Vec<Node>);
 
 impl NodeList {
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+
     #[inline]
     fn clear(&mut self) {
         self.0.clear();
@@ -446,6 +607,8 @@ impl IndexMut<NodeIndex> for NodeList {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct HorizontalBoundingBox {
     index: BoundingBoxIndex,
+    #[serde(default)]
+    component: BoundingBoxIndex,
     min_x: i32,
     max_x: i32,
 }
@@ -453,50 +616,353 @@ struct HorizontalBoundingBox {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct VerticalBoundingBox {
     index: BoundingBoxIndex,
+    #[serde(default)]
+    component: BoundingBoxIndex,
     min_y: i32,
     max_y: i32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SoftKeepOut {
+    bounding_box: BoundingBox,
+    cost_multiplier: u32,
+}
+
 #[derive(Default, Clone, Serialize, Deserialize)]
 pub(crate) struct BoundingBoxList {
+    bounding_boxes: Vec<BoundingBox>,
+    /// The component every bounding box is part of, identified by the index of the bounding box representing it.
+    #[serde(default)]
+    components: Vec<BoundingBoxIndex>,
+    /// Hard keep-outs are part of the bounding box trees with indices following the ones of the bounding boxes.
+    #[serde(default)]
+    keep_outs: Vec<KeepOut>,
     horizontal_bounding_boxes: SegmentTree<HorizontalBoundingBox>,
     vertical_bounding_boxes: SegmentTree<VerticalBoundingBox>,
+    #[serde(default)]
+    horizontal_soft_keep_outs: SegmentTree<SoftKeepOut>,
+    #[serde(default)]
+    vertical_soft_keep_outs: SegmentTree<SoftKeepOut>,
 }
 
 impl BoundingBoxList {
-    fn build(&mut self, bounding_boxes: &[BoundingBox]) {
+    fn build(
+        &mut self,
+        bounding_boxes: &[BoundingBox],
+        components: &[BoundingBoxIndex],
+        keep_outs: &[KeepOut],
+    ) {
+        self.bounding_boxes.clear();
+        self.bounding_boxes.extend_from_slice(bounding_boxes);
+
+        // Bounding boxes that are not part of a larger component form a component of their own.
+        // A bounding box pointing at one that is itself part of another component joins that component as well,
+        // so every chain of bounding boxes is resolved to the bounding box at its end.
+        let mut parents: Vec<usize> = (0..bounding_boxes.len()).collect();
+        fn root(parents: &mut [usize], mut index: usize) -> usize {
+            while parents[index] != index {
+                parents[index] = parents[parents[index]];
+                index = parents[index];
+            }
+
+            index
+        }
+
+        for (i, component) in components.iter().enumerate() {
+            let Some(component) = component
+                .to_usize()
+                .filter(|&component| component < bounding_boxes.len())
+            else {
+                continue;
+            };
+
+            let root_i = root(&mut parents, i);
+            let root_component = root(&mut parents, component);
+            if root_i != root_component {
+                parents[root_i] = root_component;
+            }
+        }
+
+        self.components.clear();
+        for i in 0..bounding_boxes.len() {
+            let component = root(&mut parents, i);
+            self.components
+                .push(BoundingBoxIndex::from_usize(component).expect("too many bounding boxes"));
+        }
+
+        self.keep_outs.clear();
+        self.keep_outs.extend_from_slice(keep_outs);
+
+        let soft_keep_outs = keep_outs
+            .iter()
+            .filter(|keep_out| keep_out.kind == KeepOutKind::Soft)
+            .map(|keep_out| SoftKeepOut {
+                bounding_box: keep_out.bounding_box,
+                cost_multiplier: keep_out.cost_multiplier,
+            });
+
+        self.horizontal_soft_keep_outs
+            .build(soft_keep_outs.clone().map(|keep_out| Segment {
+                start_inclusive: keep_out.bounding_box.min_y(),
+                end_inclusive: keep_out.bounding_box.max_y(),
+                value: keep_out,
+            }));
+
+        self.vertical_soft_keep_outs
+            .build(soft_keep_outs.map(|keep_out| Segment {
+                start_inclusive: keep_out.bounding_box.min_x(),
+                end_inclusive: keep_out.bounding_box.max_x(),
+                value: keep_out,
+            }));
+
+        self.build_trees();
+    }
+
+    /// All bounding boxes that block sightlines with their index and the component they are part of,
+    /// that is the bounding boxes themselves followed by the hard keep-outs.
+    fn obstacles<'a>(
+        bounding_boxes: &'a [BoundingBox],
+        components: &'a [BoundingBoxIndex],
+        keep_outs: &'a [KeepOut],
+    ) -> impl Iterator<Item = (BoundingBoxIndex, BoundingBox, BoundingBoxIndex)> + 'a {
+        let boxes = bounding_boxes.iter().enumerate().map(|(i, &bb)| {
+            let index = BoundingBoxIndex::from_usize(i).expect("too many bounding boxes");
+            (index, bb, components[i])
+        });
+
+        // Every keep-out is a component of its own that no anchor belongs to.
+        let keep_outs = keep_outs
+            .iter()
+            .enumerate()
+            .filter(|(_, keep_out)| keep_out.kind == KeepOutKind::Hard)
+            .map(|(i, keep_out)| {
+                let index = BoundingBoxIndex::from_usize(bounding_boxes.len() + i)
+                    .expect("too many bounding boxes");
+                (index, keep_out.bounding_box, index)
+            });
+
+        boxes.chain(keep_outs)
+    }
+
+    fn build_trees(&mut self) {
+        let obstacles = || Self::obstacles(&self.bounding_boxes, &self.components, &self.keep_outs);
+
         self.horizontal_bounding_boxes
-            .build(bounding_boxes.iter().enumerate().map(|(i, bb)| Segment {
+            .build(obstacles().map(|(index, bb, component)| Segment {
                 start_inclusive: bb.min_y(),
                 end_inclusive: bb.max_y(),
                 value: HorizontalBoundingBox {
-                    index: BoundingBoxIndex::from_usize(i).expect("too many bounding boxes"),
+                    index,
+                    component,
                     min_x: bb.min_x(),
                     max_x: bb.max_x(),
                 },
             }));
 
         self.vertical_bounding_boxes
-            .build(bounding_boxes.iter().enumerate().map(|(i, bb)| Segment {
+            .build(obstacles().map(|(index, bb, component)| Segment {
                 start_inclusive: bb.min_x(),
                 end_inclusive: bb.max_x(),
                 value: VerticalBoundingBox {
-                    index: BoundingBoxIndex::from_usize(i).expect("too many bounding boxes"),
+                    index,
+                    component,
                     min_y: bb.min_y(),
                     max_y: bb.max_y(),
                 },
             }));
     }
 
+    /// The component the bounding box at `index` is part of, or `BoundingBoxIndex::INVALID` if there is no such bounding box.
+    #[inline]
+    fn component(&self, index: BoundingBoxIndex) -> BoundingBoxIndex {
+        index
+            .to_usize()
+            .and_then(|index| self.components.get(index))
+            .copied()
+            .unwrap_or(BoundingBoxIndex::INVALID)
+    }
+
+    #[inline]
+    fn get(&self, index: BoundingBoxIndex) -> Option<BoundingBox> {
+        index
+            .to_usize()
+            .and_then(|index| self.bounding_boxes.get(index))
+            .copied()
+    }
+
+    /// Like [`BoundingBoxList::get`], but also finds keep-outs by the indices they have in the bounding box trees.
+    #[inline]
+    fn get_obstacle(&self, index: BoundingBoxIndex) -> Option<BoundingBox> {
+        let index = index.to_usize()?;
+        match index.checked_sub(self.bounding_boxes.len()) {
+            None => self.bounding_boxes.get(index).copied(),
+            Some(index) => self
+                .keep_outs
+                .get(index)
+                .map(|keep_out| keep_out.bounding_box),
+        }
+    }
+
+    /// Changes the extents of the bounding box at `index`, patching the trees instead of rebuilding them.
+    fn set(&mut self, index: usize, bounding_box: BoundingBox) {
+        let old_bounding_box = std::mem::replace(&mut self.bounding_boxes[index], bounding_box);
+        let component = self.components[index];
+        let index = BoundingBoxIndex::from_usize(index).expect("too many bounding boxes");
+
+        self.horizontal_bounding_boxes
+            .remove(old_bounding_box.min_y(), |bb| bb.index == index)
+            .expect("bounding box not in tree");
+        self.horizontal_bounding_boxes.insert(Segment {
+            start_inclusive: bounding_box.min_y(),
+            end_inclusive: bounding_box.max_y(),
+            value: HorizontalBoundingBox {
+                index,
+                component,
+                min_x: bounding_box.min_x(),
+                max_x: bounding_box.max_x(),
+            },
+        });
+
+        self.vertical_bounding_boxes
+            .remove(old_bounding_box.min_x(), |bb| bb.index == index)
+            .expect("bounding box not in tree");
+        self.vertical_bounding_boxes.insert(Segment {
+            start_inclusive: bounding_box.min_x(),
+            end_inclusive: bounding_box.max_x(),
+            value: VerticalBoundingBox {
+                index,
+                component,
+                min_y: bounding_box.min_y(),
+                max_y: bounding_box.max_y(),
+            },
+        });
+    }
+
+    /// The indices of all bounding boxes that overlap the area from `min` to `max` (inclusive), excluding keep-outs.
+    fn indices_in_area(&self, min: Point, max: Point) -> impl Iterator<Item = usize> + '_ {
+        self.horizontal_bounding_boxes
+            .iter_overlapping(min.y, max.y)
+            .filter(move |bb| (bb.min_x <= max.x) && (bb.max_x >= min.x))
+            .filter_map(|bb| bb.index.to_usize())
+            .filter(|&index| index < self.bounding_boxes.len())
+    }
+
+    /// The factor the cost of the axis aligned segment from `a` to `b` is multiplied with
+    /// for passing through the inside of soft keep-outs, no matter how much of the segment lies inside them.
+    fn keep_out_cost_multiplier(&self, a: Point, b: Point) -> u32 {
+        let keep_outs = if a.y == b.y {
+            let min_x = a.x.min(b.x);
+            let max_x = a.x.max(b.x);
+
+            self.horizontal_soft_keep_outs
+                .iter_containing(a.y)
+                .filter(|keep_out| {
+                    let bb = keep_out.bounding_box;
+                    (bb.min_y() < a.y)
+                        && (bb.max_y() > a.y)
+                        && (bb.min_x() < max_x)
+                        && (bb.max_x() > min_x)
+                })
+                .map(|keep_out| keep_out.cost_multiplier)
+                .max()
+        } else {
+            debug_assert_eq!(a.x, b.x);
+
+            let min_y = a.y.min(b.y);
+            let max_y = a.y.max(b.y);
+
+            self.vertical_soft_keep_outs
+                .iter_containing(a.x)
+                .filter(|keep_out| {
+                    let bb = keep_out.bounding_box;
+                    (bb.min_x() < a.x)
+                        && (bb.max_x() > a.x)
+                        && (bb.min_y() < max_y)
+                        && (bb.max_y() > min_y)
+                })
+                .map(|keep_out| keep_out.cost_multiplier)
+                .max()
+        };
+
+        keep_outs.unwrap_or(1).max(1)
+    }
+
     #[inline]
-    fn iter_containing_horizontal(&self, y: i32) -> ContainingSegmentIter<HorizontalBoundingBox> {
+    fn iter_containing_horizontal(
+        &self,
+        y: i32,
+    ) -> ContainingSegmentIter<'_, HorizontalBoundingBox> {
         self.horizontal_bounding_boxes.iter_containing(y)
     }
 
     #[inline]
-    fn iter_containing_vertical(&self, x: i32) -> ContainingSegmentIter<VerticalBoundingBox> {
+    fn iter_containing_vertical(&self, x: i32) -> ContainingSegmentIter<'_, VerticalBoundingBox> {
         self.vertical_bounding_boxes.iter_containing(x)
     }
+
+    /// Determines if the axis aligned segment from `a` to `b` comes within `clearance` of any bounding box.
+    fn any_near_segment(&self, a: Point, b: Point, clearance: u32) -> bool {
+        let clearance = clearance.min(i32::MAX as u32) as i32;
+
+        if a.y == b.y {
+            let min_x = a.x.min(b.x);
+            let max_x = a.x.max(b.x);
+
+            self.horizontal_bounding_boxes
+                .iter_overlapping(a.y.saturating_sub(clearance), a.y.saturating_add(clearance))
+                .any(|bb| {
+                    (bb.min_x.saturating_sub(clearance) <= max_x)
+                        && (bb.max_x.saturating_add(clearance) >= min_x)
+                })
+        } else {
+            debug_assert_eq!(a.x, b.x);
+
+            let min_y = a.y.min(b.y);
+            let max_y = a.y.max(b.y);
+
+            self.vertical_bounding_boxes
+                .iter_overlapping(a.x.saturating_sub(clearance), a.x.saturating_add(clearance))
+                .any(|bb| {
+                    (bb.min_y.saturating_sub(clearance) <= max_y)
+                        && (bb.max_y.saturating_add(clearance) >= min_y)
+                })
+        }
+    }
+
+    /// Determines if the axis aligned segment from `a` to `b` passes through the inside of any bounding box or hard keep-out.
+    ///
+    /// Segments running along the edge of a bounding box do not count as passing through it.
+    fn any_crossed_by_segment(&self, a: Point, b: Point) -> bool {
+        if a.y == b.y {
+            let min_x = a.x.min(b.x);
+            let max_x = a.x.max(b.x);
+
+            self.horizontal_bounding_boxes
+                .iter_overlapping(a.y, a.y)
+                .filter_map(|bb| self.get_obstacle(bb.index))
+                .any(|bb| {
+                    (bb.min_y() < a.y)
+                        && (bb.max_y() > a.y)
+                        && (bb.min_x() < max_x)
+                        && (bb.max_x() > min_x)
+                })
+        } else {
+            debug_assert_eq!(a.x, b.x);
+
+            let min_y = a.y.min(b.y);
+            let max_y = a.y.max(b.y);
+
+            self.vertical_bounding_boxes
+                .iter_overlapping(a.x, a.x)
+                .filter_map(|bb| self.get_obstacle(bb.index))
+                .any(|bb| {
+                    (bb.min_x() < a.x)
+                        && (bb.max_x() > a.x)
+                        && (bb.min_y() < max_y)
+                        && (bb.max_y() > min_y)
+                })
+        }
+    }
 }
 
 /// Determines if two horizontally aligned points have a sightline to each other.
@@ -504,12 +970,12 @@ fn points_have_horizontal_sightline(
     bounding_boxes: ContainingSegmentIter<HorizontalBoundingBox>,
     x1: i32,
     x2: i32,
-    ignore_box: BoundingBoxIndex,
+    ignore_component: BoundingBoxIndex,
 ) -> bool {
     assert!(x1 < x2);
 
     for bb in bounding_boxes {
-        if bb.index == ignore_box {
+        if bb.component == ignore_component {
             continue;
         }
 
@@ -528,12 +994,12 @@ fn points_have_vertical_sightline(
     bounding_boxes: ContainingSegmentIter<VerticalBoundingBox>,
     y1: i32,
     y2: i32,
-    ignore_box: BoundingBoxIndex,
+    ignore_component: BoundingBoxIndex,
 ) -> bool {
     assert!(y1 < y2);
 
     for bb in bounding_boxes {
-        if bb.index == ignore_box {
+        if bb.component == ignore_component {
             continue;
         }
 
@@ -554,24 +1020,30 @@ fn find_neg_x_cutoff(
     x1_coords: &[i32],
     x2: i32,
     offset: usize,
-    ignore_box: BoundingBoxIndex,
+    ignore_component: BoundingBoxIndex,
 ) -> usize {
-    if x1_coords.len() == 0 {
+    if x1_coords.is_empty() {
         return offset;
     }
 
     let center = x1_coords.len() / 2;
     let x1 = x1_coords[center];
 
-    if points_have_horizontal_sightline(bounding_boxes.clone(), x1, x2, ignore_box) {
-        find_neg_x_cutoff(bounding_boxes, &x1_coords[..center], x2, offset, ignore_box)
+    if points_have_horizontal_sightline(bounding_boxes.clone(), x1, x2, ignore_component) {
+        find_neg_x_cutoff(
+            bounding_boxes,
+            &x1_coords[..center],
+            x2,
+            offset,
+            ignore_component,
+        )
     } else {
         find_neg_x_cutoff(
             bounding_boxes,
             &x1_coords[(center + 1)..],
             x2,
             offset + center + 1,
-            ignore_box,
+            ignore_component,
         )
     }
 }
@@ -583,25 +1055,31 @@ fn find_pos_x_cutoff(
     x1: i32,
     x2_coords: &[i32],
     offset: usize,
-    ignore_box: BoundingBoxIndex,
+    ignore_component: BoundingBoxIndex,
 ) -> usize {
-    if x2_coords.len() == 0 {
+    if x2_coords.is_empty() {
         return offset;
     }
 
     let center = x2_coords.len() / 2;
     let x2 = x2_coords[center];
 
-    if points_have_horizontal_sightline(bounding_boxes.clone(), x1, x2, ignore_box) {
+    if points_have_horizontal_sightline(bounding_boxes.clone(), x1, x2, ignore_component) {
         find_pos_x_cutoff(
             bounding_boxes,
             x1,
             &x2_coords[(center + 1)..],
             offset + center + 1,
-            ignore_box,
+            ignore_component,
         )
     } else {
-        find_pos_x_cutoff(bounding_boxes, x1, &x2_coords[..center], offset, ignore_box)
+        find_pos_x_cutoff(
+            bounding_boxes,
+            x1,
+            &x2_coords[..center],
+            offset,
+            ignore_component,
+        )
     }
 }
 
@@ -612,24 +1090,30 @@ fn find_neg_y_cutoff(
     y1_coords: &[i32],
     y2: i32,
     offset: usize,
-    ignore_box: BoundingBoxIndex,
+    ignore_component: BoundingBoxIndex,
 ) -> usize {
-    if y1_coords.len() == 0 {
+    if y1_coords.is_empty() {
         return offset;
     }
 
     let center = y1_coords.len() / 2;
     let y1 = y1_coords[center];
 
-    if points_have_vertical_sightline(bounding_boxes.clone(), y1, y2, ignore_box) {
-        find_neg_y_cutoff(bounding_boxes, &y1_coords[..center], y2, offset, ignore_box)
+    if points_have_vertical_sightline(bounding_boxes.clone(), y1, y2, ignore_component) {
+        find_neg_y_cutoff(
+            bounding_boxes,
+            &y1_coords[..center],
+            y2,
+            offset,
+            ignore_component,
+        )
     } else {
         find_neg_y_cutoff(
             bounding_boxes,
             &y1_coords[(center + 1)..],
             y2,
             offset + center + 1,
-            ignore_box,
+            ignore_component,
         )
     }
 }
@@ -641,25 +1125,31 @@ fn find_pos_y_cutoff(
     y1: i32,
     y2_coords: &[i32],
     offset: usize,
-    ignore_box: BoundingBoxIndex,
+    ignore_component: BoundingBoxIndex,
 ) -> usize {
-    if y2_coords.len() == 0 {
+    if y2_coords.is_empty() {
         return offset;
     }
 
     let center = y2_coords.len() / 2;
     let y2 = y2_coords[center];
 
-    if points_have_vertical_sightline(bounding_boxes.clone(), y1, y2, ignore_box) {
+    if points_have_vertical_sightline(bounding_boxes.clone(), y1, y2, ignore_component) {
         find_pos_y_cutoff(
             bounding_boxes,
             y1,
             &y2_coords[(center + 1)..],
             offset + center + 1,
-            ignore_box,
+            ignore_component,
         )
     } else {
-        find_pos_y_cutoff(bounding_boxes, y1, &y2_coords[..center], offset, ignore_box)
+        find_pos_y_cutoff(
+            bounding_boxes,
+            y1,
+            &y2_coords[..center],
+            offset,
+            ignore_component,
+        )
     }
 }
 
@@ -690,6 +1180,7 @@ struct ScanXData<'a> {
     x_coords: &'a [i32],
     x_index: usize,
     bounding_boxes: ContainingSegmentIter<'a, HorizontalBoundingBox>,
+    ignore_component: BoundingBoxIndex,
     anchor: Anchor,
     anchor_index: u32,
 }
@@ -701,6 +1192,7 @@ fn scan_neg_x(
         x_coords,
         x_index,
         bounding_boxes,
+        ignore_component,
         anchor,
         anchor_index,
     }: ScanXData,
@@ -711,7 +1203,7 @@ fn scan_neg_x(
         &x_coords[..x_index],
         anchor.position.x,
         0,
-        anchor.bounding_box,
+        ignore_component,
     );
 
     // Create edges for all nodes between `neg_x_cutoff` and `x_index`.
@@ -742,6 +1234,7 @@ fn scan_pos_x(
         x_coords,
         x_index,
         bounding_boxes,
+        ignore_component,
         anchor,
         anchor_index,
     }: ScanXData,
@@ -752,7 +1245,7 @@ fn scan_pos_x(
         anchor.position.x,
         &x_coords[(x_index + 1)..],
         x_index + 1,
-        anchor.bounding_box,
+        ignore_component,
     );
 
     // Create edges for all nodes between `x_index` and `pos_x_cutoff`.
@@ -782,6 +1275,7 @@ struct ScanYData<'a> {
     y_coords: &'a [i32],
     y_index: usize,
     bounding_boxes: ContainingSegmentIter<'a, VerticalBoundingBox>,
+    ignore_component: BoundingBoxIndex,
     anchor: Anchor,
     anchor_index: u32,
 }
@@ -793,6 +1287,7 @@ fn scan_neg_y(
         y_coords,
         y_index,
         bounding_boxes,
+        ignore_component,
         anchor,
         anchor_index,
     }: ScanYData,
@@ -803,7 +1298,7 @@ fn scan_neg_y(
         &y_coords[..y_index],
         anchor.position.y,
         0,
-        anchor.bounding_box,
+        ignore_component,
     );
 
     // Create edges for all nodes between `neg_y_cutoff` and `y_index`.
@@ -834,6 +1329,7 @@ fn scan_pos_y(
         y_coords,
         y_index,
         bounding_boxes,
+        ignore_component,
         anchor,
         anchor_index,
     }: ScanYData,
@@ -844,7 +1340,7 @@ fn scan_pos_y(
         anchor.position.y,
         &y_coords[(y_index + 1)..],
         y_index + 1,
-        anchor.bounding_box,
+        ignore_component,
     );
 
     // Create edges for all nodes between `y_index` and `pos_y_cutoff`.
@@ -868,112 +1364,687 @@ fn scan_pos_y(
     }
 }
 
-#[derive(Default, Clone, Serialize, Deserialize)]
-pub(crate) struct GraphData {
-    pub(crate) bounding_boxes: BoundingBoxList,
-    x_coords: Vec<i32>,
-    y_coords: Vec<i32>,
-    node_map: HashMap<Point, NodeIndex>,
-    pub(crate) nodes: NodeList,
+/// The anchors that are automatically placed around the corners of bounding boxes.
+///
+/// Every track around a bounding box gets its own ring of anchors, so the graph contains a line for each of them.
+fn auto_anchors(
+    bounding_boxes: &[BoundingBox],
+    tracks: TrackSettings,
+) -> impl Clone + Iterator<Item = Anchor> + '_ {
+    bounding_boxes.iter().flat_map(move |&bb| {
+        let first_ring = [
+            tracks.next_track(bb.min_x(), -1),
+            tracks.next_track(bb.min_y(), -1),
+            tracks.next_track(bb.max_x(), 1),
+            tracks.next_track(bb.max_y(), 1),
+        ];
+
+        std::iter::successors(Some(first_ring), move |&[min_x, min_y, max_x, max_y]| {
+            Some([
+                tracks.next_track(min_x, -1),
+                tracks.next_track(min_y, -1),
+                tracks.next_track(max_x, 1),
+                tracks.next_track(max_y, 1),
+            ])
+        })
+        .take(tracks.track_count.max(1) as usize)
+        .flat_map(|[min_x, min_y, max_x, max_y]| {
+            [
+                Anchor::new(min_x, min_y),
+                Anchor::new(min_x, max_y),
+                Anchor::new(max_x, min_y),
+                Anchor::new(max_x, max_y),
+            ]
+        })
+    })
 }
 
-impl GraphData {
-    fn scan(&mut self, anchor: Anchor, anchor_index: u32) {
-        if anchor.connect_directions.intersects(Directions::X) {
-            let x_index = self
-                .x_coords
-                .binary_search(&anchor.position.x)
-                .expect("invalid anchor point");
+/// Appends the anchors of the tracks in the channels between `bb` and the nearest bounding boxes or hard keep-outs
+/// facing its positive X and Y sides to `anchors`.
+///
+/// The tracks continue the ones around `bb` into the channel, one every `wire_spacing`, and end one `wire_spacing`
+/// before the tracks around the facing side, so the two sides together leave no gaps in channels that are not too wide.
+fn channel_anchors(
+    bounding_boxes: &BoundingBoxList,
+    tracks: TrackSettings,
+    bb: BoundingBox,
+    anchors: &mut Vec<Anchor>,
+) {
+    if tracks.channel_track_count == 0 {
+        return;
+    }
 
-            let bounding_boxes = self
-                .bounding_boxes
-                .iter_containing_horizontal(anchor.position.y);
+    for vertical in [false, true] {
+        // The near edge of the facing obstacle and the range in which the two face each other.
+        let facing = if vertical {
+            bounding_boxes
+                .vertical_bounding_boxes
+                .iter_overlapping(bb.min_x(), bb.max_x())
+                .filter(|other| other.min_y > bb.max_y())
+                .filter_map(|other| {
+                    let other_bb = bounding_boxes.get_obstacle(other.index)?;
+                    let min = other_bb.min_x().max(bb.min_x());
+                    let max = other_bb.max_x().min(bb.max_x());
+                    Some((other.min_y, min, max))
+                })
+                .min_by_key(|&(edge, ..)| edge)
+        } else {
+            bounding_boxes
+                .horizontal_bounding_boxes
+                .iter_overlapping(bb.min_y(), bb.max_y())
+                .filter(|other| other.min_x > bb.max_x())
+                .filter_map(|other| {
+                    let other_bb = bounding_boxes.get_obstacle(other.index)?;
+                    let min = other_bb.min_y().max(bb.min_y());
+                    let max = other_bb.max_y().min(bb.max_y());
+                    Some((other.min_x, min, max))
+                })
+                .min_by_key(|&(edge, ..)| edge)
+        };
 
-            if anchor.connect_directions.contains(Directions::NEG_X) {
-                scan_neg_x(ScanXData {
-                    node_map: &mut self.node_map,
-                    nodes: &mut self.nodes,
-                    x_coords: &self.x_coords,
-                    x_index,
-                    bounding_boxes: bounding_boxes.clone(),
-                    anchor,
-                    anchor_index,
-                });
-            }
+        let Some((edge, min, max)) = facing else {
+            continue;
+        };
 
-            if anchor.connect_directions.contains(Directions::POS_X) {
-                scan_pos_x(ScanXData {
-                    node_map: &mut self.node_map,
-                    nodes: &mut self.nodes,
-                    x_coords: &self.x_coords,
-                    x_index,
-                    bounding_boxes,
-                    anchor,
-                    anchor_index,
-                });
-            }
+        if min >= max {
+            continue;
         }
 
-        if anchor.connect_directions.intersects(Directions::Y) {
-            let y_index = self
-                .y_coords
-                .binary_search(&anchor.position.y)
-                .expect("invalid anchor point");
+        let side = if vertical { bb.max_y() } else { bb.max_x() };
+        let mut track = tracks.nth_track(side, 1, tracks.track_count.saturating_add(1));
+        let last_track = tracks.nth_track(edge, -1, tracks.track_count.saturating_add(1));
 
-            let bounding_boxes = self
-                .bounding_boxes
-                .iter_containing_vertical(anchor.position.x);
+        // Anchors at both ends of the channel, on the first tracks around the sides that bound it.
+        let min = tracks.next_track(min, -1);
+        let max = tracks.next_track(max, 1);
 
-            if anchor.connect_directions.contains(Directions::NEG_Y) {
-                scan_neg_y(ScanYData {
-                    node_map: &mut self.node_map,
-                    nodes: &mut self.nodes,
-                    y_coords: &self.y_coords,
-                    y_index,
-                    bounding_boxes: bounding_boxes.clone(),
-                    anchor,
-                    anchor_index,
-                });
+        for _ in 0..tracks.channel_track_count {
+            if track > last_track {
+                break;
             }
 
-            if anchor.connect_directions.contains(Directions::POS_Y) {
-                scan_pos_y(ScanYData {
-                    node_map: &mut self.node_map,
-                    nodes: &mut self.nodes,
-                    y_coords: &self.y_coords,
-                    y_index,
-                    bounding_boxes,
-                    anchor,
-                    anchor_index,
-                });
+            if vertical {
+                anchors.extend([Anchor::new(min, track), Anchor::new(max, track)]);
+            } else {
+                anchors.extend([Anchor::new(track, min), Anchor::new(track, max)]);
+            }
+
+            let next_track = tracks.next_track(track, 1);
+            if next_track <= track {
+                break;
             }
+
+            track = next_track;
         }
     }
+}
 
-    fn remove_redundant_nodes(&mut self) {
-        struct HeadTail<'a> {
-            node_index: usize,
-            head: &'a mut [Node],
-            tail: &'a mut [Node],
-        }
+/// Finds the edge of the bounding box closest to `coord` in the direction of `sign`,
+/// out of the bounding boxes (given as their component and extent along the line) crossing the line between `min` and `max`.
+fn nearest_obstacle(
+    bounding_boxes: impl Iterator<Item = (BoundingBoxIndex, i32, i32)>,
+    min: i32,
+    max: i32,
+    sign: i32,
+    ignore_component: BoundingBoxIndex,
+) -> Option<i32> {
+    bounding_boxes
+        .filter(|&(component, bb_min, bb_max)| {
+            (component != ignore_component) && (bb_min <= max) && (bb_max >= min)
+        })
+        .map(|(_, bb_min, bb_max)| if sign < 0 { bb_max } else { bb_min })
+        .min_by_key(|&edge| sign * edge)
+}
 
-        impl<'a> HeadTail<'a> {
-            #[inline]
-            fn new(nodes: &'a mut [Node], node_index: usize) -> (&'a mut Node, Self) {
-                let (head, tail) = nodes.split_at_mut(node_index);
-                let (node, tail) = tail.split_first_mut().unwrap();
+/// Finds the stub leading `anchor` out of its bounding box `own_box` in direction `dir`.
+///
+/// Returns `Ok(None)` if the anchor has a sightline to the first track around its bounding box, so it needs no stub.
+/// If another bounding box is in the way, the stub is placed in the middle of the gap between the two.
+/// Returns `Err(())` if the gap is too narrow to fit a wire.
+fn find_escape_stub(
+    bounding_boxes: &BoundingBoxList,
+    tracks: TrackSettings,
+    anchor: Anchor,
+    own_box: BoundingBox,
+    dir: Direction,
+) -> Result<Option<Point>, ()> {
+    let Point { x, y } = anchor.position;
+    let (sign, edge) = match dir {
+        Direction::PosX => (1, own_box.max_x()),
+        Direction::NegX => (-1, own_box.min_x()),
+        Direction::PosY => (1, own_box.max_y()),
+        Direction::NegY => (-1, own_box.min_y()),
+    };
 
-                (
-                    node,
-                    Self {
-                        node_index,
-                        head,
-                        tail,
-                    },
-                )
-            }
+    let first_track = tracks.next_track(edge, sign);
+    let ignore_component = bounding_boxes.component(anchor.bounding_box);
+    let obstacle = match dir {
+        Direction::PosX | Direction::NegX => nearest_obstacle(
+            bounding_boxes
+                .iter_containing_horizontal(y)
+                .map(|bb| (bb.component, bb.min_x, bb.max_x)),
+            x.min(first_track),
+            x.max(first_track),
+            sign,
+            ignore_component,
+        ),
+        Direction::PosY | Direction::NegY => nearest_obstacle(
+            bounding_boxes
+                .iter_containing_vertical(x)
+                .map(|bb| (bb.component, bb.min_y, bb.max_y)),
+            y.min(first_track),
+            y.max(first_track),
+            sign,
+            ignore_component,
+        ),
+    };
 
-            #[inline]
+    let Some(obstacle) = obstacle else {
+        return Ok(None);
+    };
+
+    // There has to be at least one coordinate strictly between the two bounding boxes.
+    let gap = ((obstacle as i64) - (edge as i64)) * (sign as i64);
+    if gap < 2 {
+        return Err(());
+    }
+
+    let stub = edge + sign * ((gap / 2) as i32);
+    Ok(Some(match dir {
+        Direction::PosX | Direction::NegX => Point { x: stub, y },
+        Direction::PosY | Direction::NegY => Point { x, y: stub },
+    }))
+}
+
+/// Finds the stubs leading anchors out of their bounding boxes where other bounding boxes are in the way,
+/// as well as the anchors that have no way out at all, and appends them to `stubs` and `blocked_anchors`.
+///
+/// Only anchors that lie inside or on the edge of their own bounding box are considered.
+fn escape_anchors(
+    anchors: &[Anchor],
+    bounding_boxes: &BoundingBoxList,
+    tracks: TrackSettings,
+    stubs: &mut Vec<Anchor>,
+    blocked_anchors: &mut Vec<BlockedAnchor>,
+) {
+    for &anchor in anchors {
+        let Some(own_box) = bounding_boxes.get(anchor.bounding_box) else {
+            continue;
+        };
+
+        if !own_box.contains(anchor.position) {
+            continue;
+        }
+
+        if anchor.connect_directions.is_empty() {
+            blocked_anchors.push(BlockedAnchor {
+                position: anchor.position,
+                reason: EscapeBlockedReason::NoConnectDirections,
+            });
+
+            continue;
+        }
+
+        let mut escaped = false;
+        for dir in Direction::ALL {
+            if !anchor.connect_directions.contains(dir.into()) {
+                continue;
+            }
+
+            match find_escape_stub(bounding_boxes, tracks, anchor, own_box, dir) {
+                Ok(None) => escaped = true,
+                Ok(Some(stub)) => {
+                    stubs.push(Anchor::new(stub.x, stub.y));
+                    escaped = true;
+                }
+                Err(()) => (),
+            }
+        }
+
+        if !escaped {
+            blocked_anchors.push(BlockedAnchor {
+                position: anchor.position,
+                reason: EscapeBlockedReason::Obstructed,
+            });
+        }
+    }
+}
+
+/// Rows or columns of the graph that have to be rescanned during an update.
+#[derive(Default)]
+struct DirtyLines {
+    lines: HashSet<i32>,
+    ranges: Vec<(i32, i32)>,
+}
+
+impl DirtyLines {
+    #[inline]
+    fn insert(&mut self, line: i32) {
+        self.lines.insert(line);
+    }
+
+    #[inline]
+    fn insert_range(&mut self, min_inclusive: i32, max_inclusive: i32) {
+        self.ranges.push((min_inclusive, max_inclusive));
+    }
+
+    #[inline]
+    fn contains(&self, line: i32) -> bool {
+        self.lines.contains(&line)
+            || self
+                .ranges
+                .iter()
+                .any(|&(min, max)| (line >= min) && (line <= max))
+    }
+
+    /// The dirty lines out of the sorted list of coordinates `coords`, sorted and deduplicated.
+    fn collect(&self, coords: &[i32]) -> Vec<i32> {
+        let mut lines: Vec<_> = self
+            .lines
+            .iter()
+            .copied()
+            .filter(|line| coords.binary_search(line).is_ok())
+            .collect();
+
+        for &(min, max) in &self.ranges {
+            let start = coords.partition_point(|&coord| coord < min);
+            let end = coords.partition_point(|&coord| coord <= max);
+            lines.extend_from_slice(&coords[start..end]);
+        }
+
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+}
+
+/// Finds the lines out of `lines` that gained their first anchor or lost their last one,
+/// given the sorted coordinates `coords` of all lines before the change.
+fn changed_lines(
+    coords: &[i32],
+    lines: &HashSet<i32>,
+    line_anchors: &HashMap<i32, Vec<(i32, Anchor)>>,
+) -> (Vec<i32>, Vec<i32>) {
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    for &line in lines {
+        match (
+            coords.binary_search(&line).is_ok(),
+            line_anchors.contains_key(&line),
+        ) {
+            (false, true) => added.push(line),
+            (true, false) => removed.push(line),
+            _ => (),
+        }
+    }
+
+    added.sort_unstable();
+    removed.sort_unstable();
+    (added, removed)
+}
+
+/// Inserts `added` into and removes `removed` from the sorted list of coordinates `coords`.
+fn patch_coords(coords: &mut Vec<i32>, added: &[i32], removed: &[i32]) {
+    for &coord in removed {
+        let index = coords
+            .binary_search(&coord)
+            .expect("removed coordinate not in list");
+        coords.remove(index);
+    }
+
+    for &coord in added {
+        let index = coords
+            .binary_search(&coord)
+            .expect_err("added coordinate already in list");
+        coords.insert(index, coord);
+    }
+}
+
+/// Finds the positions that occur more often in `old` than in `new` and the other way around.
+fn diff_positions(mut old: Vec<Point>, mut new: Vec<Point>) -> (Vec<Point>, Vec<Point>) {
+    use std::cmp::Ordering;
+
+    old.sort_unstable_by_key(|position| (position.x, position.y));
+    new.sort_unstable_by_key(|position| (position.x, position.y));
+
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let mut old = old.into_iter().peekable();
+    let mut new = new.into_iter().peekable();
+    loop {
+        match (old.peek(), new.peek()) {
+            (Some(a), Some(b)) => match (a.x, a.y).cmp(&(b.x, b.y)) {
+                Ordering::Less => removed.extend(old.next()),
+                Ordering::Greater => added.extend(new.next()),
+                Ordering::Equal => {
+                    old.next();
+                    new.next();
+                }
+            },
+            (Some(_), None) => removed.extend(old.next()),
+            (None, Some(_)) => added.extend(new.next()),
+            (None, None) => break,
+        }
+    }
+
+    (removed, added)
+}
+
+/// The lines removed by an update in progress.
+/// Until the update is done, nodes can still lie on them in addition to the current lines.
+#[derive(Default)]
+struct RemovedLines {
+    x: Vec<i32>,
+    y: Vec<i32>,
+}
+
+/// Iterates the coordinates in the sorted and disjoint lists `a` and `b` that lie beyond `coord`,
+/// in ascending order if `ascending` is set and in descending order otherwise.
+fn coords_beyond<'a>(
+    a: &'a [i32],
+    b: &'a [i32],
+    coord: i32,
+    ascending: bool,
+) -> impl Iterator<Item = i32> + 'a {
+    let beyond = move |coords: &'a [i32]| -> Box<dyn Iterator<Item = i32> + 'a> {
+        if ascending {
+            let start = coords.partition_point(|&other| other <= coord);
+            Box::new(coords[start..].iter().copied())
+        } else {
+            let end = coords.partition_point(|&other| other < coord);
+            Box::new(coords[..end].iter().rev().copied())
+        }
+    };
+
+    let mut a = beyond(a).peekable();
+    let mut b = beyond(b).peekable();
+    std::iter::from_fn(move || match (a.peek(), b.peek()) {
+        (Some(&a_coord), Some(&b_coord)) => {
+            if (a_coord < b_coord) == ascending {
+                a.next()
+            } else {
+                b.next()
+            }
+        }
+        (Some(_), None) => a.next(),
+        (None, _) => b.next(),
+    })
+}
+
+/// Finds the nearest anchor in `anchors` (sorted by coordinate) before `coord` that connects in
+/// direction `before_dir`, and the nearest anchor after `coord` that connects in direction `after_dir`.
+///
+/// Only anchors for which `has_sightline(min, max, bounding_box)` holds are considered,
+/// where `bounding_box` is the bounding box the anchor belongs to.
+fn nearest_visible_anchors(
+    anchors: &[(i32, Anchor)],
+    coord: i32,
+    before_dir: Directions,
+    after_dir: Directions,
+    mut has_sightline: impl FnMut(i32, i32, BoundingBoxIndex) -> bool,
+) -> [Option<Anchor>; 2] {
+    let before_end = anchors.partition_point(|&(anchor_coord, _)| anchor_coord < coord);
+    let after_start = anchors.partition_point(|&(anchor_coord, _)| anchor_coord <= coord);
+
+    let before = anchors[..before_end]
+        .iter()
+        .rev()
+        .filter(|(_, anchor)| anchor.connect_directions.contains(before_dir))
+        .find(|&&(anchor_coord, anchor)| has_sightline(anchor_coord, coord, anchor.bounding_box))
+        .map(|&(_, anchor)| anchor);
+    let after = anchors[after_start..]
+        .iter()
+        .filter(|(_, anchor)| anchor.connect_directions.contains(after_dir))
+        .find(|&&(anchor_coord, anchor)| has_sightline(coord, anchor_coord, anchor.bounding_box))
+        .map(|&(_, anchor)| anchor);
+
+    [before, after]
+}
+
+/// The anchors placed automatically for a single bounding box or keep-out.
+#[derive(Default, Clone)]
+struct DerivedAnchors {
+    /// The anchors at the corners of the tracks around it, followed by the ones of the tracks in the channels
+    /// it faces and the escape stubs of its anchors.
+    anchors: Vec<Anchor>,
+    /// Its anchors that have no way out of it.
+    blocked: Vec<BlockedAnchor>,
+}
+
+/// Lookup tables that let updates find the anchors and lines they affect without visiting the whole graph.
+///
+/// They are not serialized, and apart from the automatically placed anchors only built by the first update.
+#[derive(Default, Clone)]
+struct AnchorIndex {
+    built: bool,
+    /// The indices into `GraphData::anchors` of the anchors at every position.
+    positions: HashMap<Point, Vec<u32>>,
+    /// The anchors belonging to every bounding box.
+    bounding_box_anchors: Vec<Vec<Anchor>>,
+    /// The automatically placed anchors of every bounding box, followed by the ones of every keep-out.
+    derived: Vec<DerivedAnchors>,
+    /// The number of automatically placed anchors at every position.
+    auto_positions: HashMap<Point, u32>,
+    /// All anchors on every row, sorted by X coordinate.
+    rows: HashMap<i32, Vec<(i32, Anchor)>>,
+    /// All anchors on every column, sorted by Y coordinate.
+    columns: HashMap<i32, Vec<(i32, Anchor)>>,
+}
+
+impl AnchorIndex {
+    /// Adds an anchor to the row and column it lies on.
+    fn insert_line_anchor(&mut self, anchor: Anchor) {
+        fn insert(
+            lines: &mut HashMap<i32, Vec<(i32, Anchor)>>,
+            line: i32,
+            coord: i32,
+            anchor: Anchor,
+        ) {
+            let line_anchors = lines.entry(line).or_default();
+            let index = line_anchors.partition_point(|&(other_coord, _)| other_coord <= coord);
+            line_anchors.insert(index, (coord, anchor));
+        }
+
+        let Point { x, y } = anchor.position;
+        insert(&mut self.rows, y, x, anchor);
+        insert(&mut self.columns, x, y, anchor);
+    }
+
+    /// Removes an anchor from the row and column it lies on, dropping lines that are left without anchors.
+    fn remove_line_anchor(&mut self, anchor: Anchor) {
+        use std::collections::hash_map::Entry;
+
+        fn remove(
+            lines: &mut HashMap<i32, Vec<(i32, Anchor)>>,
+            line: i32,
+            coord: i32,
+            anchor: Anchor,
+        ) {
+            let Entry::Occupied(mut entry) = lines.entry(line) else {
+                panic!("anchor not in line");
+            };
+
+            let line_anchors = entry.get_mut();
+            let start = line_anchors.partition_point(|&(other_coord, _)| other_coord < coord);
+            let offset = line_anchors[start..]
+                .iter()
+                .position(|&(_, other)| other == anchor)
+                .expect("anchor not in line");
+            line_anchors.remove(start + offset);
+
+            if line_anchors.is_empty() {
+                entry.remove();
+            }
+        }
+
+        let Point { x, y } = anchor.position;
+        remove(&mut self.rows, y, x, anchor);
+        remove(&mut self.columns, x, y, anchor);
+    }
+
+    fn insert_auto_anchor(&mut self, anchor: Anchor) {
+        *self.auto_positions.entry(anchor.position).or_insert(0) += 1;
+        self.insert_line_anchor(anchor);
+    }
+
+    fn remove_auto_anchor(&mut self, anchor: Anchor) {
+        use std::collections::hash_map::Entry;
+
+        let Entry::Occupied(mut entry) = self.auto_positions.entry(anchor.position) else {
+            panic!("auto anchor not in index");
+        };
+
+        *entry.get_mut() -= 1;
+        if *entry.get() == 0 {
+            entry.remove();
+        }
+
+        self.remove_line_anchor(anchor);
+    }
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub(crate) struct GraphData {
+    pub(crate) bounding_boxes: BoundingBoxList,
+    anchors: Vec<Anchor>,
+    minimal: bool,
+    #[serde(default)]
+    tracks: TrackSettings,
+    #[serde(default)]
+    blocked_anchors: Vec<BlockedAnchor>,
+    /// The directions the anchors at each position connect in.
+    /// Unlike the legal directions of their nodes, these are not widened by sightlines passing through.
+    #[serde(default)]
+    anchor_directions: HashMap<Point, Directions>,
+    x_coords: Vec<i32>,
+    y_coords: Vec<i32>,
+    node_map: HashMap<Point, NodeIndex>,
+    pub(crate) nodes: NodeList,
+    #[serde(skip)]
+    index: AnchorIndex,
+}
+
+impl GraphData {
+    /// The sides a wire may arrive at the node `index` from.
+    /// At anchors these are the directions the anchors connect in, even if other sightlines pass through them.
+    #[inline]
+    pub(crate) fn arrival_directions(&self, index: NodeIndex) -> Directions {
+        let position = self.nodes[index].position;
+        self.anchor_directions
+            .get(&position)
+            .copied()
+            .unwrap_or(Directions::ALL)
+    }
+
+    fn scan(&mut self, anchor: Anchor, anchor_index: u32) {
+        self.scan_x(anchor, anchor_index);
+        self.scan_y(anchor, anchor_index);
+    }
+
+    fn scan_x(&mut self, anchor: Anchor, anchor_index: u32) {
+        if anchor.connect_directions.intersects(Directions::X) {
+            let x_index = self
+                .x_coords
+                .binary_search(&anchor.position.x)
+                .expect("invalid anchor point");
+
+            let bounding_boxes = self
+                .bounding_boxes
+                .iter_containing_horizontal(anchor.position.y);
+            let ignore_component = self.bounding_boxes.component(anchor.bounding_box);
+
+            if anchor.connect_directions.contains(Directions::NEG_X) {
+                scan_neg_x(ScanXData {
+                    node_map: &mut self.node_map,
+                    nodes: &mut self.nodes,
+                    x_coords: &self.x_coords,
+                    x_index,
+                    bounding_boxes: bounding_boxes.clone(),
+                    ignore_component,
+                    anchor,
+                    anchor_index,
+                });
+            }
+
+            if anchor.connect_directions.contains(Directions::POS_X) {
+                scan_pos_x(ScanXData {
+                    node_map: &mut self.node_map,
+                    nodes: &mut self.nodes,
+                    x_coords: &self.x_coords,
+                    x_index,
+                    bounding_boxes,
+                    ignore_component,
+                    anchor,
+                    anchor_index,
+                });
+            }
+        }
+    }
+
+    fn scan_y(&mut self, anchor: Anchor, anchor_index: u32) {
+        if anchor.connect_directions.intersects(Directions::Y) {
+            let y_index = self
+                .y_coords
+                .binary_search(&anchor.position.y)
+                .expect("invalid anchor point");
+
+            let bounding_boxes = self
+                .bounding_boxes
+                .iter_containing_vertical(anchor.position.x);
+            let ignore_component = self.bounding_boxes.component(anchor.bounding_box);
+
+            if anchor.connect_directions.contains(Directions::NEG_Y) {
+                scan_neg_y(ScanYData {
+                    node_map: &mut self.node_map,
+                    nodes: &mut self.nodes,
+                    y_coords: &self.y_coords,
+                    y_index,
+                    bounding_boxes: bounding_boxes.clone(),
+                    ignore_component,
+                    anchor,
+                    anchor_index,
+                });
+            }
+
+            if anchor.connect_directions.contains(Directions::POS_Y) {
+                scan_pos_y(ScanYData {
+                    node_map: &mut self.node_map,
+                    nodes: &mut self.nodes,
+                    y_coords: &self.y_coords,
+                    y_index,
+                    bounding_boxes,
+                    ignore_component,
+                    anchor,
+                    anchor_index,
+                });
+            }
+        }
+    }
+
+    fn remove_redundant_nodes(&mut self) {
+        struct HeadTail<'a> {
+            node_index: usize,
+            head: &'a mut [Node],
+            tail: &'a mut [Node],
+        }
+
+        impl<'a> HeadTail<'a> {
+            #[inline]
+            fn new(nodes: &'a mut [Node], node_index: usize) -> (&'a mut Node, Self) {
+                let (head, tail) = nodes.split_at_mut(node_index);
+                let (node, tail) = tail.split_first_mut().unwrap();
+
+                (
+                    node,
+                    Self {
+                        node_index,
+                        head,
+                        tail,
+                    },
+                )
+            }
+
+            #[inline]
             fn get_neighbor(&mut self, neighbor_index: NodeIndex) -> &mut Node {
                 use std::cmp::Ordering;
 
@@ -1093,43 +2164,362 @@ impl GraphData {
     #[cfg(not(debug_assertions))]
     fn assert_graph_is_valid(&self) {}
 
-    /// Builds the graph.
-    ///
-    /// If the graph had previously been built, this will reset it and reuse the resources.
-    pub(crate) fn build(
-        &mut self,
-        anchors: &[Anchor],
-        bounding_boxes: &[BoundingBox],
-        minimal: bool,
-    ) {
-        use std::collections::hash_map::Entry;
+    /// The automatically placed anchors of the bounding box or keep-out at `index` in the bounding box trees.
+    fn derive_anchors(&self, index: usize) -> DerivedAnchors {
+        let mut derived = DerivedAnchors::default();
+
+        match index.checked_sub(self.bounding_boxes.bounding_boxes.len()) {
+            None => {
+                let bb = self.bounding_boxes.bounding_boxes[index];
+                derived
+                    .anchors
+                    .extend(auto_anchors(std::slice::from_ref(&bb), self.tracks));
+                channel_anchors(&self.bounding_boxes, self.tracks, bb, &mut derived.anchors);
+
+                escape_anchors(
+                    &self.index.bounding_box_anchors[index],
+                    &self.bounding_boxes,
+                    self.tracks,
+                    &mut derived.anchors,
+                    &mut derived.blocked,
+                );
+            }
+            Some(index) => {
+                let keep_out = &self.bounding_boxes.keep_outs[index];
+                if keep_out.auto_anchors {
+                    derived.anchors.extend(auto_anchors(
+                        std::slice::from_ref(&keep_out.bounding_box),
+                        self.tracks,
+                    ));
+                }
+            }
+        }
 
-        self.bounding_boxes.build(bounding_boxes);
+        derived
+    }
 
-        let auto_anchors = bounding_boxes.iter().flat_map(|&bb| {
-            [
-                Anchor::new(bb.min_x() - 1, bb.min_y() - 1),
-                Anchor::new(bb.min_x() - 1, bb.max_y() + 1),
-                Anchor::new(bb.max_x() + 1, bb.min_y() - 1),
-                Anchor::new(bb.max_x() + 1, bb.max_y() + 1),
-            ]
-        });
+    /// Places the anchors of all bounding boxes and keep-outs automatically and collects the coordinates of all lines.
+    ///
+    /// The remaining lookup tables are only needed by updates, so building them is left to [`GraphData::rebuild_index`].
+    fn derive_all_anchors(&mut self) {
+        let bounding_box_count = self.bounding_boxes.bounding_boxes.len();
+        let obstacle_count = bounding_box_count + self.bounding_boxes.keep_outs.len();
+
+        self.index = AnchorIndex::default();
+        self.index
+            .bounding_box_anchors
+            .resize_with(bounding_box_count, Vec::new);
+        for &anchor in &self.anchors {
+            if let Some(anchors) = anchor
+                .bounding_box
+                .to_usize()
+                .and_then(|bounding_box| self.index.bounding_box_anchors.get_mut(bounding_box))
+            {
+                anchors.push(anchor);
+            }
+        }
+
+        self.index.derived = (0..obstacle_count)
+            .map(|index| self.derive_anchors(index))
+            .collect();
+        self.collect_blocked_anchors();
 
-        let all_anchors = anchors.iter().copied().chain(auto_anchors.clone());
+        let all_anchors = self.anchors.iter().chain(
+            self.index
+                .derived
+                .iter()
+                .flat_map(|derived| &derived.anchors),
+        );
 
-        // Sort all X coordinates.
         self.x_coords.clear();
         self.x_coords
             .extend(all_anchors.clone().map(|anchor| anchor.position.x));
         self.x_coords.par_sort_unstable();
         self.x_coords.dedup();
 
-        // Sort all Y coordinates.
         self.y_coords.clear();
         self.y_coords
-            .extend(all_anchors.clone().map(|anchor| anchor.position.y));
+            .extend(all_anchors.map(|anchor| anchor.position.y));
         self.y_coords.par_sort_unstable();
         self.y_coords.dedup();
+    }
+
+    /// Rebuilds all lookup tables used by updates.
+    fn rebuild_index(&mut self) {
+        self.derive_all_anchors();
+
+        let mut positions: HashMap<Point, Vec<u32>> = HashMap::default();
+        let mut auto_positions: HashMap<Point, u32> = HashMap::default();
+        let mut rows: HashMap<i32, Vec<(i32, Anchor)>> = HashMap::default();
+        let mut columns: HashMap<i32, Vec<(i32, Anchor)>> = HashMap::default();
+
+        for (i, anchor) in self.anchors.iter().enumerate() {
+            positions.entry(anchor.position).or_default().push(i as u32);
+        }
+
+        let auto_anchors = self
+            .index
+            .derived
+            .iter()
+            .flat_map(|derived| &derived.anchors);
+        for anchor in auto_anchors.clone() {
+            *auto_positions.entry(anchor.position).or_insert(0) += 1;
+        }
+
+        for &anchor in self.anchors.iter().chain(auto_anchors) {
+            let Point { x, y } = anchor.position;
+            rows.entry(y).or_default().push((x, anchor));
+            columns.entry(x).or_default().push((y, anchor));
+        }
+
+        for line_anchors in rows.values_mut().chain(columns.values_mut()) {
+            line_anchors.sort_unstable_by_key(|&(coord, _)| coord);
+        }
+
+        self.index.positions = positions;
+        self.index.auto_positions = auto_positions;
+        self.index.rows = rows;
+        self.index.columns = columns;
+        self.index.built = true;
+    }
+
+    fn collect_blocked_anchors(&mut self) {
+        self.blocked_anchors.clear();
+        self.blocked_anchors.extend(
+            self.index
+                .derived
+                .iter()
+                .flat_map(|derived| derived.blocked.iter().copied()),
+        );
+    }
+
+    /// Adds an anchor to the anchor list and the lookup tables.
+    fn insert_anchor(&mut self, anchor: Anchor) {
+        let index = self.anchors.len() as u32;
+        self.anchors.push(anchor);
+
+        self.index
+            .positions
+            .entry(anchor.position)
+            .or_default()
+            .push(index);
+
+        if let Some(anchors) = anchor
+            .bounding_box
+            .to_usize()
+            .and_then(|bounding_box| self.index.bounding_box_anchors.get_mut(bounding_box))
+        {
+            anchors.push(anchor);
+        }
+
+        self.index.insert_line_anchor(anchor);
+    }
+
+    /// Removes the anchor at `index` from the anchor list and the lookup tables.
+    /// The last anchor in the list takes its place.
+    fn remove_anchor(&mut self, index: u32) -> Anchor {
+        use std::collections::hash_map::Entry;
+
+        let anchor = self.anchors.swap_remove(index as usize);
+
+        let Entry::Occupied(mut entry) = self.index.positions.entry(anchor.position) else {
+            panic!("anchor not in index");
+        };
+
+        let indices = entry.get_mut();
+        let position = indices.iter().position(|&other| other == index);
+        indices.swap_remove(position.expect("anchor not in index"));
+        if indices.is_empty() {
+            entry.remove();
+        }
+
+        if let Some(&moved_anchor) = self.anchors.get(index as usize) {
+            let old_index = self.anchors.len() as u32;
+            let indices = self
+                .index
+                .positions
+                .get_mut(&moved_anchor.position)
+                .expect("anchor not in index");
+            let moved = indices.iter_mut().find(|other| **other == old_index);
+            *moved.expect("anchor not in index") = index;
+        }
+
+        if let Some(anchors) = anchor
+            .bounding_box
+            .to_usize()
+            .and_then(|bounding_box| self.index.bounding_box_anchors.get_mut(bounding_box))
+        {
+            let position = anchors.iter().position(|&other| other == anchor);
+            anchors.swap_remove(position.expect("anchor not in index"));
+        }
+
+        self.index.remove_line_anchor(anchor);
+        anchor
+    }
+
+    /// Removes a node from the graph.
+    ///
+    /// If the node had neighbors on both sides along an axis, those neighbors are connected to each other.
+    fn remove_node(&mut self, index: NodeIndex) {
+        let neighbors = self.nodes[index].neighbors.clone();
+        for (neg_dir, pos_dir) in [
+            (Direction::NegX, Direction::PosX),
+            (Direction::NegY, Direction::PosY),
+        ] {
+            let neg_index = neighbors[neg_dir];
+            let pos_index = neighbors[pos_dir];
+
+            if neg_index != INVALID_NODE_INDEX {
+                self.nodes[neg_index].neighbors[pos_dir] = pos_index;
+            }
+
+            if pos_index != INVALID_NODE_INDEX {
+                self.nodes[pos_index].neighbors[neg_dir] = neg_index;
+            }
+        }
+
+        let position = self.nodes[index].position;
+        self.node_map.remove(&position).expect("node not in map");
+
+        let last_index = (self.nodes.0.len() - 1) as NodeIndex;
+        if index != last_index {
+            self.nodes.0.swap(index as usize, last_index as usize);
+
+            let node = &self.nodes[index];
+            let position = node.position;
+            let neighbors = node.neighbors.clone();
+
+            self.node_map
+                .insert(position, index)
+                .expect("swapped node not in map");
+
+            for dir in Direction::ALL {
+                let neighbor_index = neighbors[dir];
+                if neighbor_index != INVALID_NODE_INDEX {
+                    self.nodes[neighbor_index].neighbors[dir.opposite()] = index;
+                }
+            }
+        }
+
+        self.nodes.0.pop();
+    }
+
+    /// Finds the closest node in direction `dir` of `position` that is connected along the same axis.
+    ///
+    /// If a node exists at coordinate `limit` it is returned even if it is not connected.
+    fn find_line_neighbor(
+        &self,
+        removed_lines: &RemovedLines,
+        position: Point,
+        dir: Direction,
+        limit: i32,
+    ) -> Option<NodeIndex> {
+        let (coords, removed_coords, coord) = match dir {
+            Direction::PosX | Direction::NegX => (&self.x_coords, &removed_lines.x, position.x),
+            Direction::PosY | Direction::NegY => (&self.y_coords, &removed_lines.y, position.y),
+        };
+
+        let ascending = matches!(dir, Direction::PosX | Direction::PosY);
+        for other_coord in coords_beyond(coords, removed_coords, coord, ascending) {
+            let other_position = match dir {
+                Direction::PosX | Direction::NegX => Point {
+                    x: other_coord,
+                    y: position.y,
+                },
+                Direction::PosY | Direction::NegY => Point {
+                    x: position.x,
+                    y: other_coord,
+                },
+            };
+
+            let Some(&other_index) = self.node_map.get(&other_position) else {
+                continue;
+            };
+
+            let other_node = &self.nodes[other_index];
+            if (other_node.neighbors[dir] != INVALID_NODE_INDEX)
+                || (other_node.neighbors[dir.opposite()] != INVALID_NODE_INDEX)
+                || (other_coord == limit)
+            {
+                return Some(other_index);
+            }
+
+            // The node is not connected along this axis, so an edge could pass over it.
+        }
+
+        None
+    }
+
+    /// Connects the given node to the closest node in direction `dir`, either by splitting
+    /// an edge that passes over it or by extending the line that ends before it.
+    ///
+    /// `limit` is the coordinate of the anchor that has a sightline to the node.
+    /// Returns the node that was connected to.
+    fn extend_line_to(
+        &mut self,
+        removed_lines: &RemovedLines,
+        index: NodeIndex,
+        dir: Direction,
+        limit: i32,
+    ) -> Option<NodeIndex> {
+        if self.nodes[index].neighbors[dir] != INVALID_NODE_INDEX {
+            return None;
+        }
+
+        let position = self.nodes[index].position;
+        let neighbor_index = self.find_line_neighbor(removed_lines, position, dir, limit)?;
+
+        // If an edge passes over the node, the node takes the place of the neighbor on the other side.
+        let beyond_index = self.nodes[neighbor_index].neighbors[dir.opposite()];
+        if beyond_index != INVALID_NODE_INDEX {
+            self.nodes[beyond_index].neighbors[dir] = index;
+            self.nodes[index].neighbors[dir.opposite()] = beyond_index;
+        }
+
+        self.nodes[index].neighbors[dir] = neighbor_index;
+        self.nodes[neighbor_index].neighbors[dir.opposite()] = index;
+
+        Some(neighbor_index)
+    }
+
+    /// Builds the graph.
+    ///
+    /// If the graph had previously been built, this will reset it and reuse the resources.
+    pub(crate) fn build(
+        &mut self,
+        anchors: &[Anchor],
+        bounding_boxes: &[BoundingBox],
+        components: &[BoundingBoxIndex],
+        keep_outs: &[KeepOut],
+        minimal: bool,
+        tracks: TrackSettings,
+    ) {
+        use std::collections::hash_map::Entry;
+
+        self.bounding_boxes
+            .build(bounding_boxes, components, keep_outs);
+        self.anchors.clear();
+        self.anchors.extend_from_slice(anchors);
+        self.minimal = minimal;
+        self.tracks = tracks;
+
+        self.anchor_directions.clear();
+        for anchor in anchors {
+            *self
+                .anchor_directions
+                .entry(anchor.position)
+                .or_insert(Directions::NONE) |= anchor.connect_directions;
+        }
+
+        self.derive_all_anchors();
+
+        let auto_anchors: Vec<_> = self
+            .index
+            .derived
+            .iter()
+            .flat_map(|derived| derived.anchors.iter().copied())
+            .collect();
+        let all_anchors = anchors.iter().chain(&auto_anchors).copied();
 
         self.node_map.clear();
         self.nodes.clear();
@@ -1150,7 +2540,7 @@ impl GraphData {
             }
         }
 
-        for anchor in auto_anchors {
+        for anchor in &auto_anchors {
             // Add graph node for this anchor point.
             match self.node_map.entry(anchor.position) {
                 Entry::Occupied(entry) => {
@@ -1177,6 +2567,463 @@ impl GraphData {
         }
     }
 
+    /// Updates the graph after anchors were added or removed, or bounding boxes changed.
+    ///
+    /// Only the rows and columns affected by the change are rescanned,
+    /// the resulting graph is the same as if it had been rebuilt from scratch.
+    pub(crate) fn update(
+        &mut self,
+        added_anchors: &[Anchor],
+        removed_anchors: &[Anchor],
+        changed_bounding_boxes: &[BoundingBoxChange],
+    ) -> Result<(), GraphUpdateError> {
+        use std::collections::hash_map::Entry;
+
+        if !self.index.built {
+            self.rebuild_index();
+        }
+
+        // Validate everything first, so a failed update leaves the graph untouched.
+        let mut removed_indices = HashSet::default();
+        for removed_anchor in removed_anchors {
+            let index = self
+                .index
+                .positions
+                .get(&removed_anchor.position)
+                .and_then(|indices| {
+                    indices.iter().copied().find(|index| {
+                        (self.anchors[*index as usize] == *removed_anchor)
+                            && !removed_indices.contains(index)
+                    })
+                })
+                .ok_or(GraphUpdateError::AnchorNotFound)?;
+
+            removed_indices.insert(index);
+        }
+
+        for change in changed_bounding_boxes {
+            if self.bounding_boxes.get(change.index).is_none() {
+                return Err(GraphUpdateError::InvalidBoundingBoxIndex);
+            }
+        }
+
+        let mut dirty_rows = DirtyLines::default();
+        let mut dirty_columns = DirtyLines::default();
+        // Positions at which the set of anchors changed.
+        let mut changed_positions = HashSet::default();
+        // Bounding boxes whose auto anchors, escape stubs or blocked anchors may have changed.
+        let mut affected_bounding_boxes = HashSet::default();
+
+        // Removing the highest index first ensures no anchor that is still to be removed gets moved.
+        let mut removed_indices: Vec<_> = removed_indices.into_iter().collect();
+        removed_indices.sort_unstable();
+        for &index in removed_indices.iter().rev() {
+            let anchor = self.remove_anchor(index);
+            dirty_rows.insert(anchor.position.y);
+            dirty_columns.insert(anchor.position.x);
+            changed_positions.insert(anchor.position);
+            affected_bounding_boxes.extend(anchor.bounding_box.to_usize());
+        }
+
+        for &anchor in added_anchors {
+            self.insert_anchor(anchor);
+            dirty_rows.insert(anchor.position.y);
+            dirty_columns.insert(anchor.position.x);
+            changed_positions.insert(anchor.position);
+            affected_bounding_boxes.extend(anchor.bounding_box.to_usize());
+        }
+
+        let mut changed_areas = Vec::with_capacity(changed_bounding_boxes.len() * 2);
+        for change in changed_bounding_boxes {
+            let index = change.index.to_usize().expect("invalid bounding box index");
+            let old_bounding_box = self.bounding_boxes.bounding_boxes[index];
+
+            // Sightlines through both the old and the new area may have changed, and so did the auto anchors.
+            for bb in [old_bounding_box, change.bounding_box] {
+                // The auto anchors of the outermost track enclose the bounding box.
+                let (mut min, mut max) = (bb.center, bb.center);
+                for anchor in auto_anchors(std::slice::from_ref(&bb), self.tracks) {
+                    min.x = min.x.min(anchor.position.x);
+                    min.y = min.y.min(anchor.position.y);
+                    max.x = max.x.max(anchor.position.x);
+                    max.y = max.y.max(anchor.position.y);
+                }
+
+                dirty_rows.insert_range(min.y, max.y);
+                dirty_columns.insert_range(min.x, max.x);
+                changed_areas.push(bb);
+            }
+
+            self.bounding_boxes.set(index, change.bounding_box);
+            affected_bounding_boxes.insert(index);
+        }
+
+        // Channel tracks and escape stubs depend on the surroundings of their bounding box, so they can change
+        // even if their bounding box did not. Only bounding boxes close enough to the old or new area
+        // of a changed one for them to reach it are affected.
+        let reach = self.tracks.reach();
+        for area in changed_areas {
+            let min = Point {
+                x: area.min_x().saturating_sub(reach),
+                y: area.min_y().saturating_sub(reach),
+            };
+            let max = Point {
+                x: area.max_x().saturating_add(reach),
+                y: area.max_y().saturating_add(reach),
+            };
+
+            affected_bounding_boxes.extend(self.bounding_boxes.indices_in_area(min, max));
+        }
+
+        let mut blocked_anchors_changed = false;
+        for index in affected_bounding_boxes {
+            let derived = self.derive_anchors(index);
+            let old_derived = std::mem::replace(&mut self.index.derived[index], derived);
+            let new_derived = &self.index.derived[index];
+            blocked_anchors_changed |= old_derived.blocked != new_derived.blocked;
+
+            let (removed, added) = diff_positions(
+                old_derived
+                    .anchors
+                    .iter()
+                    .map(|anchor| anchor.position)
+                    .collect(),
+                new_derived
+                    .anchors
+                    .iter()
+                    .map(|anchor| anchor.position)
+                    .collect(),
+            );
+
+            for position in removed {
+                self.index
+                    .remove_auto_anchor(Anchor::new(position.x, position.y));
+                dirty_rows.insert(position.y);
+                dirty_columns.insert(position.x);
+                changed_positions.insert(position);
+            }
+
+            for position in added {
+                self.index
+                    .insert_auto_anchor(Anchor::new(position.x, position.y));
+                dirty_rows.insert(position.y);
+                dirty_columns.insert(position.x);
+                changed_positions.insert(position);
+            }
+        }
+
+        if blocked_anchors_changed {
+            self.collect_blocked_anchors();
+        }
+
+        // Every line that gained or lost anchors is dirty, so only those can have been added or removed.
+        let (new_x_coords, removed_x_coords) =
+            changed_lines(&self.x_coords, &dirty_columns.lines, &self.index.columns);
+        let (new_y_coords, removed_y_coords) =
+            changed_lines(&self.y_coords, &dirty_rows.lines, &self.index.rows);
+
+        // Disconnect all edges on dirty lines, they get recreated by rescanning.
+        // Nodes only exist where lines cross, so all nodes on a line are found by looking up its crossings.
+        let mut cleanup_candidates = Vec::new();
+        for y in dirty_rows.collect(&self.y_coords) {
+            for &x in &self.x_coords {
+                if let Some(&index) = self.node_map.get(&Point { x, y }) {
+                    let node = &mut self.nodes[index];
+                    node.neighbors[Direction::NegX] = INVALID_NODE_INDEX;
+                    node.neighbors[Direction::PosX] = INVALID_NODE_INDEX;
+                    cleanup_candidates.push(node.position);
+                }
+            }
+        }
+
+        for x in dirty_columns.collect(&self.x_coords) {
+            for &y in &self.y_coords {
+                if let Some(&index) = self.node_map.get(&Point { x, y }) {
+                    let node = &mut self.nodes[index];
+                    node.neighbors[Direction::NegY] = INVALID_NODE_INDEX;
+                    node.neighbors[Direction::PosY] = INVALID_NODE_INDEX;
+                    cleanup_candidates.push(node.position);
+                }
+            }
+        }
+
+        patch_coords(&mut self.x_coords, &new_x_coords, &removed_x_coords);
+        patch_coords(&mut self.y_coords, &new_y_coords, &removed_y_coords);
+        let removed_lines = RemovedLines {
+            x: removed_x_coords,
+            y: removed_y_coords,
+        };
+
+        for position in &changed_positions {
+            self.anchor_directions.remove(position);
+
+            if let Some(&index) = self.node_map.get(position) {
+                let node = &mut self.nodes[index];
+                node.is_anchor = false;
+                node.legal_directions = Directions::ALL;
+            }
+        }
+
+        let first_new_node = self.nodes.0.len();
+
+        for position in &changed_positions {
+            let Some(indices) = self.index.positions.get(position) else {
+                continue;
+            };
+
+            for &index in indices {
+                let anchor = self.anchors[index as usize];
+
+                *self
+                    .anchor_directions
+                    .entry(anchor.position)
+                    .or_insert(Directions::NONE) |= anchor.connect_directions;
+
+                // Add graph node for this anchor point.
+                match self.node_map.entry(anchor.position) {
+                    Entry::Occupied(entry) => {
+                        let node = &mut self.nodes[*entry.get()];
+                        if node.is_anchor {
+                            node.legal_directions |= anchor.connect_directions;
+                        } else {
+                            node.is_anchor = true;
+                            node.legal_directions = anchor.connect_directions;
+                        }
+                    }
+                    Entry::Vacant(entry) => {
+                        let index =
+                            self.nodes
+                                .push(anchor.position, true, anchor.connect_directions);
+                        entry.insert(index);
+                    }
+                }
+            }
+        }
+
+        let mut rescan_anchors = Vec::new();
+        for y in dirty_rows.collect(&self.y_coords) {
+            for &(x, anchor) in &self.index.rows[&y] {
+                rescan_anchors.push((anchor, true, dirty_columns.contains(x)));
+            }
+        }
+
+        for x in dirty_columns.collect(&self.x_coords) {
+            for &(y, anchor) in &self.index.columns[&x] {
+                if !dirty_rows.contains(y) {
+                    rescan_anchors.push((anchor, false, true));
+                }
+            }
+        }
+
+        for &(anchor, ..) in &rescan_anchors {
+            if !self.index.auto_positions.contains_key(&anchor.position) {
+                continue;
+            }
+
+            // Add graph node for this auto anchor point.
+            // In a minimal graph it may have been removed before, so this is also necessary for unchanged positions.
+            match self.node_map.entry(anchor.position) {
+                Entry::Occupied(entry) => {
+                    if changed_positions.contains(&anchor.position) {
+                        self.nodes[*entry.get()].legal_directions = Directions::ALL;
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    let index = self.nodes.push(anchor.position, false, Directions::ALL);
+                    entry.insert(index);
+                }
+            }
+        }
+
+        for (anchor, row_dirty, column_dirty) in rescan_anchors {
+            let anchor_index = self.node_map[&anchor.position];
+
+            if row_dirty {
+                self.scan_x(anchor, anchor_index);
+            }
+
+            if column_dirty {
+                self.scan_y(anchor, anchor_index);
+            }
+        }
+
+        // New nodes on a dirty line may lie on a clean line, which then has to be split or extended.
+        // This happens for new coordinates, or if the node had been removed from a minimal graph.
+        let mut row_targets: HashMap<i32, Vec<i32>> = HashMap::default();
+        let mut column_targets: HashMap<i32, Vec<i32>> = HashMap::default();
+        for index in first_new_node..self.nodes.0.len() {
+            let position = self.nodes[index as NodeIndex].position;
+            cleanup_candidates.push(position);
+
+            let row_dirty = dirty_rows.contains(position.y);
+            let column_dirty = dirty_columns.contains(position.x);
+
+            if row_dirty && !column_dirty {
+                column_targets
+                    .entry(position.x)
+                    .or_default()
+                    .push(position.y);
+            } else if column_dirty && !row_dirty {
+                row_targets.entry(position.y).or_default().push(position.x);
+            }
+        }
+
+        // A new coordinate crosses every clean line, otherwise only the lines with new nodes on them need to be extended.
+        let extended_rows: Vec<_> = if new_x_coords.is_empty() {
+            row_targets.keys().copied().collect()
+        } else {
+            self.index.rows.keys().copied().collect()
+        };
+        let extended_columns: Vec<_> = if new_y_coords.is_empty() {
+            column_targets.keys().copied().collect()
+        } else {
+            self.index.columns.keys().copied().collect()
+        };
+
+        let mut extensions = Vec::new();
+
+        for y in extended_rows {
+            if dirty_rows.contains(y) {
+                continue;
+            }
+
+            let Some(anchors) = self.index.rows.get(&y) else {
+                continue;
+            };
+
+            let bounding_boxes = self.bounding_boxes.iter_containing_horizontal(y);
+
+            let targets = row_targets.get(&y).map(Vec::as_slice).unwrap_or_default();
+            for &x in new_x_coords.iter().chain(targets) {
+                let [before, after] = nearest_visible_anchors(
+                    anchors,
+                    x,
+                    Directions::POS_X,
+                    Directions::NEG_X,
+                    |min, max, bounding_box| {
+                        points_have_horizontal_sightline(
+                            bounding_boxes.clone(),
+                            min,
+                            max,
+                            self.bounding_boxes.component(bounding_box),
+                        )
+                    },
+                );
+
+                if let Some(anchor) = before {
+                    extensions.push((Point { x, y }, Direction::NegX, anchor.position.x));
+                }
+
+                if let Some(anchor) = after {
+                    extensions.push((Point { x, y }, Direction::PosX, anchor.position.x));
+                }
+            }
+        }
+
+        for x in extended_columns {
+            if dirty_columns.contains(x) {
+                continue;
+            }
+
+            let Some(anchors) = self.index.columns.get(&x) else {
+                continue;
+            };
+
+            let bounding_boxes = self.bounding_boxes.iter_containing_vertical(x);
+
+            let targets = column_targets
+                .get(&x)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for &y in new_y_coords.iter().chain(targets) {
+                let [before, after] = nearest_visible_anchors(
+                    anchors,
+                    y,
+                    Directions::POS_Y,
+                    Directions::NEG_Y,
+                    |min, max, bounding_box| {
+                        points_have_vertical_sightline(
+                            bounding_boxes.clone(),
+                            min,
+                            max,
+                            self.bounding_boxes.component(bounding_box),
+                        )
+                    },
+                );
+
+                if let Some(anchor) = before {
+                    extensions.push((Point { x, y }, Direction::NegY, anchor.position.y));
+                }
+
+                if let Some(anchor) = after {
+                    extensions.push((Point { x, y }, Direction::PosY, anchor.position.y));
+                }
+            }
+        }
+
+        // Lines have to be extended in order, so each node connects to the previous one.
+        extensions.sort_unstable_by_key(|&(position, dir, _)| match dir {
+            Direction::NegX => (0, position.y, position.x),
+            Direction::PosX => (1, position.y, -position.x),
+            Direction::NegY => (2, position.x, position.y),
+            Direction::PosY => (3, position.x, -position.y),
+        });
+
+        for (position, dir, limit) in extensions {
+            let (index, _) = get_or_insert_node(&mut self.node_map, &mut self.nodes, position);
+
+            if let Some(neighbor_index) = self.extend_line_to(&removed_lines, index, dir, limit) {
+                cleanup_candidates.push(self.nodes[neighbor_index].position);
+            }
+
+            cleanup_candidates.push(position);
+        }
+
+        // Remove nodes that would not exist in a freshly built graph.
+        // Nodes of auto anchors and escape stubs always exist, even if they are not connected to anything.
+        while let Some(position) = cleanup_candidates.pop() {
+            let Some(&index) = self.node_map.get(&position) else {
+                continue;
+            };
+
+            let node = &self.nodes[index];
+            if node.is_anchor {
+                continue;
+            }
+
+            let has_neighbor = |dir: Direction| node.neighbors[dir] != INVALID_NODE_INDEX;
+            let straight_through = (has_neighbor(Direction::NegX)
+                && has_neighbor(Direction::PosX)
+                && !has_neighbor(Direction::NegY)
+                && !has_neighbor(Direction::PosY))
+                || (!has_neighbor(Direction::NegX)
+                    && !has_neighbor(Direction::PosX)
+                    && has_neighbor(Direction::NegY)
+                    && has_neighbor(Direction::PosY));
+            let on_grid = self.x_coords.binary_search(&position.x).is_ok()
+                && self.y_coords.binary_search(&position.y).is_ok();
+
+            let isolated =
+                (node.neighbor_count() == 0) && !self.index.auto_positions.contains_key(&position);
+
+            if !on_grid || isolated || (self.minimal && straight_through) {
+                // Removing the node can make its neighbors redundant.
+                for dir in Direction::ALL {
+                    let neighbor_index = node.neighbors[dir];
+                    if neighbor_index != INVALID_NODE_INDEX {
+                        cleanup_candidates.push(self.nodes[neighbor_index].position);
+                    }
+                }
+
+                self.remove_node(index);
+            }
+        }
+
+        self.assert_graph_is_valid();
+
+        Ok(())
+    }
+
     /// The nodes in the graph.
     #[inline]
     pub(crate) fn nodes(&self) -> &[Node] {
@@ -1188,4 +3035,56 @@ impl GraphData {
     pub(crate) fn find_node(&self, position: Point) -> Option<NodeIndex> {
         self.node_map.get(&position).copied()
     }
+
+    /// The settings the tracks around bounding boxes were placed with.
+    #[inline]
+    pub(crate) fn tracks(&self) -> TrackSettings {
+        self.tracks
+    }
+
+    /// The X coordinates of all lines in the graph, in ascending order.
+    #[inline]
+    pub(crate) fn x_coords(&self) -> &[i32] {
+        &self.x_coords
+    }
+
+    /// The Y coordinates of all lines in the graph, in ascending order.
+    #[inline]
+    pub(crate) fn y_coords(&self) -> &[i32] {
+        &self.y_coords
+    }
+
+    /// The anchors inside bounding boxes that have no way out of them.
+    #[inline]
+    pub(crate) fn blocked_anchors(&self) -> &[BlockedAnchor] {
+        &self.blocked_anchors
+    }
+
+    /// Determines if the axis aligned segment from `a` to `b` passes through the inside of any bounding box or hard keep-out.
+    #[inline]
+    pub(crate) fn segment_crosses_bounding_box(&self, a: Point, b: Point) -> bool {
+        self.bounding_boxes.any_crossed_by_segment(a, b)
+    }
+
+    /// The factor the cost of the whole axis aligned segment from `a` to `b` is multiplied with for passing through soft keep-outs.
+    #[inline]
+    pub(crate) fn segment_cost_multiplier(&self, a: Point, b: Point) -> u32 {
+        self.bounding_boxes.keep_out_cost_multiplier(a, b)
+    }
+
+    /// Determines if the edge from `node` in direction `dir` comes within `clearance` of any bounding box.
+    #[inline]
+    pub(crate) fn edge_is_near_bounding_box(
+        &self,
+        node: NodeIndex,
+        dir: Direction,
+        clearance: u32,
+    ) -> bool {
+        let neighbor = self.nodes[node].neighbors[dir];
+        self.bounding_boxes.any_near_segment(
+            self.nodes[node].position,
+            self.nodes[neighbor].position,
+            clearance,
+        )
+    }
 }
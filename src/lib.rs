@@ -1,27 +1,60 @@
 #![deny(unsafe_code)]
 
+mod bus;
+mod congestion;
+mod design;
 mod ffi;
 mod graph;
+mod negotiation;
 mod path_finding;
+mod radix_heap;
 mod routing;
 mod segment_tree;
+mod session;
+mod steiner;
+mod waypoints;
 
 #[cfg(test)]
 mod test;
 
 use graph::GraphData;
-use path_finding::PathFinder;
+use path_finding::{PathFinder, StartDirection};
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::mem::MaybeUninit;
 use thread_local::ThreadLocal;
 
-pub use graph::{Anchor, BoundingBox, BoundingBoxIndex, Direction, Directions, Node, Point};
-pub use path_finding::{Path, PathFindResult, PathNode, PathNodeKind};
-pub use routing::{Endpoint, NetView, RoutingError, Vertex, WireView};
+pub use congestion::CongestionCosts;
+pub use design::{
+    Junction, NetSpec, RoutableNet, RoutedDesign, RoutedNet, RoutedWire, WireAttachment,
+};
+pub use graph::{
+    Anchor, BlockedAnchor, BoundingBox, BoundingBoxChange, BoundingBoxIndex, Direction, Directions,
+    EscapeBlockedReason, GraphUpdateError, KeepOut, KeepOutKind, Node, Point, TrackSettings,
+};
+pub use negotiation::{NegotiationOutcome, NegotiationSettings};
+pub use path_finding::{CostModel, Path, PathFindResult, PathNode, PathNodeKind, SearchOptions};
+pub use routing::{
+    Diagnostic, DiagnosticKind, DirectionPreference, Endpoint, NetConstraints, NetStatus,
+    NetTopology, NetView, RoutingError, RoutingReport, Vertex, Waypoint, WaypointOrder, WireView,
+};
+pub use session::RoutingSession;
 
-type HashSet<T> = ahash::AHashSet<T>;
-type HashMap<K, V> = ahash::AHashMap<K, V>;
+/// Builds hashers with fixed keys, so iteration order of maps and sets is the same on every run.
+#[derive(Debug, Default, Clone, Copy)]
+struct FixedState;
+
+impl std::hash::BuildHasher for FixedState {
+    type Hasher = ahash::AHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        ahash::RandomState::with_seeds(0, 0, 0, 0).build_hasher()
+    }
+}
+
+type HashSet<T> = std::collections::HashSet<T, FixedState>;
+type HashMap<K, V> = std::collections::HashMap<K, V, FixedState>;
 
 pub trait ReplayCapture {
     fn begin_path_finding(
@@ -89,7 +122,105 @@ impl Graph {
     /// If the graph had previously been built, this will reset it and reuse the resources.
     #[inline]
     pub fn build(&mut self, anchors: &[Anchor], bounding_boxes: &[BoundingBox], minimal: bool) {
-        self.data.build(anchors, bounding_boxes, minimal);
+        self.build_with_tracks(anchors, bounding_boxes, minimal, TrackSettings::default());
+    }
+
+    /// Builds the graph, placing `tracks.track_count` parallel tracks around every bounding box
+    /// and up to `tracks.channel_track_count` additional ones in the channels between bounding boxes.
+    ///
+    /// With more than one track, parallel wires of different nets can run next to each other instead of
+    /// on top of each other. [`Graph::connect_nets`] and [`Graph::connect_nets_deterministic`] route the nets
+    /// whose wires ended up on top of each other again one after another to assign them distinct tracks,
+    /// [`Graph::connect_nets_congestion_aware`] and [`Graph::connect_nets_negotiated`] avoid the wires of other nets anyway.
+    /// Nets are only assigned distinct tracks without centering, since centering moves wires off their tracks.
+    ///
+    /// If the graph had previously been built, this will reset it and reuse the resources.
+    #[inline]
+    pub fn build_with_tracks(
+        &mut self,
+        anchors: &[Anchor],
+        bounding_boxes: &[BoundingBox],
+        minimal: bool,
+        tracks: TrackSettings,
+    ) {
+        self.build_with_components(anchors, bounding_boxes, &[], minimal, tracks);
+    }
+
+    /// Builds the graph out of components that consist of more than one bounding box,
+    /// placing `tracks.track_count` parallel tracks around every bounding box.
+    ///
+    /// `components[i]` is the index of the bounding box representing the component `bounding_boxes[i]` is part of,
+    /// or `BoundingBoxIndex::INVALID` if it is a component on its own. This way non-rectangular obstacles can be
+    /// described as a union of rectangles. Anchors ignore all bounding boxes of their own component when looking for sightlines.
+    /// Chains are resolved, so a bounding box pointing at one that is itself part of another component belongs to that
+    /// component too. Indices past the end of `bounding_boxes` are treated like `BoundingBoxIndex::INVALID`.
+    /// If `components` is empty, every bounding box is a component on its own.
+    ///
+    /// If the graph had previously been built, this will reset it and reuse the resources.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `components` is neither empty nor the same length as `bounding_boxes`.
+    #[inline]
+    pub fn build_with_components(
+        &mut self,
+        anchors: &[Anchor],
+        bounding_boxes: &[BoundingBox],
+        components: &[BoundingBoxIndex],
+        minimal: bool,
+        tracks: TrackSettings,
+    ) {
+        self.build_with_keep_outs(anchors, bounding_boxes, components, &[], minimal, tracks);
+    }
+
+    /// Builds the graph like [`Graph::build_with_components`], additionally keeping wires out of or away from `keep_outs`.
+    ///
+    /// Hard keep-outs block sightlines like bounding boxes do, but no anchor belongs to them.
+    /// Soft keep-outs leave the graph as is, but multiply the cost of path segments running through them.
+    /// The cost of an edge of the graph is multiplied as a whole, even if only part of it lies inside a soft keep-out.
+    /// Tracks and auto anchors around keep-outs are only placed if requested by [`KeepOut::auto_anchors`].
+    ///
+    /// If the graph had previously been built, this will reset it and reuse the resources.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `components` is neither empty nor the same length as `bounding_boxes`.
+    #[inline]
+    pub fn build_with_keep_outs(
+        &mut self,
+        anchors: &[Anchor],
+        bounding_boxes: &[BoundingBox],
+        components: &[BoundingBoxIndex],
+        keep_outs: &[KeepOut],
+        minimal: bool,
+        tracks: TrackSettings,
+    ) {
+        assert!(components.is_empty() || (components.len() == bounding_boxes.len()));
+        self.data.build(
+            anchors,
+            bounding_boxes,
+            components,
+            keep_outs,
+            minimal,
+            tracks,
+        );
+    }
+
+    /// Updates the graph after anchors were added or removed, or bounding boxes changed.
+    ///
+    /// This only rescans the rows and columns affected by the change, which is a lot cheaper than
+    /// rebuilding the whole graph if for example only a single component was moved.
+    /// The graph must have been built before. The first update after building or deserializing the graph
+    /// builds the lookup tables later updates use to find the affected anchors, so it takes longer.
+    #[inline]
+    pub fn update(
+        &mut self,
+        added_anchors: &[Anchor],
+        removed_anchors: &[Anchor],
+        changed_bounding_boxes: &[BoundingBoxChange],
+    ) -> Result<(), GraphUpdateError> {
+        self.data
+            .update(added_anchors, removed_anchors, changed_bounding_boxes)
     }
 
     /// The nodes in the graph.
@@ -98,30 +229,68 @@ impl Graph {
         self.data.nodes()
     }
 
+    /// The anchors inside bounding boxes that have no way out of them.
+    ///
+    /// Every other anchor inside its bounding box has at least one neighbor in a direction it connects in.
+    #[inline]
+    pub fn blocked_anchors(&self) -> &[BlockedAnchor] {
+        self.data.blocked_anchors()
+    }
+
     /// Finds the index of the node at the given position.
     #[inline]
     pub fn find_node(&self, position: Point) -> Option<usize> {
         self.data.find_node(position).map(|index| index as usize)
     }
 
-    /// Finds the shortest path from `start` to `end`.
+    /// Finds the shortest path from `start` to `end`, searching as specified by `options`.
     #[inline]
     pub fn find_path_replay(
         &self,
         start: Point,
         end: Point,
+        cost_model: &CostModel,
+        options: &SearchOptions,
         replay: &mut impl ReplayCapture,
     ) -> PathFindResult<Path> {
         let mut path_finder = self.path_finder.get_or_default().borrow_mut();
         path_finder
-            .find_path(&self.data, start, None, [end], false, replay)
+            .find_path(
+                &self.data,
+                start,
+                StartDirection::Any,
+                [end],
+                false,
+                cost_model,
+                options,
+                &NetConstraints::default(),
+                None,
+                replay,
+            )
             .map(Path::clone)
     }
 
     /// Finds the shortest path from `start` to `end`.
     #[inline]
-    pub fn find_path(&self, start: Point, end: Point) -> PathFindResult<Path> {
-        self.find_path_replay(start, end, &mut NoReplay)
+    pub fn find_path(
+        &self,
+        start: Point,
+        end: Point,
+        cost_model: &CostModel,
+    ) -> PathFindResult<Path> {
+        self.find_path_with_options(start, end, cost_model, &SearchOptions::default())
+    }
+
+    /// Finds the shortest path from `start` to `end`, searching as specified by `options`.
+    #[inline]
+    pub fn find_path_with_options(
+        &self,
+        start: Point,
+        end: Point,
+        cost_model: &CostModel,
+        options: &SearchOptions,
+    ) -> PathFindResult<Path> {
+        self.find_path_replay(start, end, cost_model, options, &mut NoReplay)
     }
 
     /// Finds the shortest path from `start` to `ends`, optionally visiting all `ends`.
@@ -131,6 +300,7 @@ impl Graph {
         start: Point,
         ends: &[Point],
         visit_all: bool,
+        cost_model: &CostModel,
         replay: &mut impl ReplayCapture,
     ) -> PathFindResult<Path> {
         let mut path_finder = self.path_finder.get_or_default().borrow_mut();
@@ -138,9 +308,13 @@ impl Graph {
             .find_path(
                 &self.data,
                 start,
-                None,
+                StartDirection::Any,
                 ends.iter().copied(),
                 visit_all,
+                cost_model,
+                &SearchOptions::default(),
+                &NetConstraints::default(),
+                None,
                 replay,
             )
             .map(Path::clone)
@@ -153,26 +327,31 @@ impl Graph {
         start: Point,
         ends: &[Point],
         visit_all: bool,
+        cost_model: &CostModel,
     ) -> PathFindResult<Path> {
-        self.find_path_multi_replay(start, ends, visit_all, &mut NoReplay)
+        self.find_path_multi_replay(start, ends, visit_all, cost_model, &mut NoReplay)
     }
 
-    pub fn connect_net_replay(
+    #[allow(clippy::too_many_arguments)]
+    pub fn connect_net_replay<'a, N: RoutableNet<'a> + ?Sized>(
         &self,
-        endpoints: &[Endpoint],
+        net: &N,
         vertices: &mut [MaybeUninit<Vertex>],
         wire_views: &mut [MaybeUninit<WireView>],
         perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
         replay: &mut impl ReplayCapture,
     ) -> Result<NetView, RoutingError> {
         let mut ends = Vec::new();
         let mut centering_candidates = Vec::new();
         let mut junctions = routing::JunctionMap::default();
+        let mut diagnostics = routing::NetDiagnostics::default();
         let mut net_view = MaybeUninit::uninit();
 
         routing::connect_net(
             self,
-            endpoints.iter(),
+            net.endpoints().iter(),
             0,
             0,
             &mut vertices.into(),
@@ -181,28 +360,215 @@ impl Graph {
             &mut ends,
             &mut centering_candidates,
             &mut junctions,
+            &mut diagnostics,
             perform_centering,
+            topology,
+            cost_model,
+            &net.constraints(),
+            None,
             replay,
         )?;
 
+        // There is no report to put the diagnostics of a single net into.
+        if diagnostics.status == NetStatus::Failed {
+            return Err(RoutingError::InvalidPoint);
+        }
+
         #[allow(unsafe_code)]
         Ok(unsafe { net_view.assume_init() })
     }
 
     #[inline]
-    pub fn connect_net(
+    pub fn connect_net<'a, N: RoutableNet<'a> + ?Sized>(
         &self,
-        endpoints: &[Endpoint],
+        net: &N,
         vertices: &mut [MaybeUninit<Vertex>],
         wire_views: &mut [MaybeUninit<WireView>],
         perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
     ) -> Result<NetView, RoutingError> {
         self.connect_net_replay(
-            endpoints,
+            net,
             vertices,
             wire_views,
             perform_centering,
+            topology,
+            cost_model,
             &mut NoReplay,
         )
     }
+
+    /// Connects multiple nets in parallel.
+    ///
+    /// The results of the nets are returned in the same order as `nets`.
+    /// If the graph was built with parallel tracks, see [`Graph::build_with_tracks`],
+    /// nets whose wires ended up on top of each other are routed again one after another to assign them distinct tracks.
+    #[inline]
+    pub fn connect_nets(
+        &self,
+        nets: &[NetSpec],
+        perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
+    ) -> Result<RoutedDesign, RoutingError> {
+        design::connect_nets(self, nets, perform_centering, topology, cost_model)
+    }
+
+    /// Connects multiple nets in parallel.
+    ///
+    /// The results of all nets are written into `vertices` and `wire_views` contiguously, in the order of `nets`,
+    /// so the output is identical for identical input regardless of the number of threads.
+    /// Nets are assigned distinct tracks like in [`Graph::connect_nets`].
+    /// `net_views` must be exactly as long as `nets`. The status and diagnostics of every net are written into `report`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn connect_nets_deterministic<'a, N: RoutableNet<'a> + Sync>(
+        &self,
+        nets: &[N],
+        vertices: &mut [MaybeUninit<Vertex>],
+        wire_views: &mut [MaybeUninit<WireView>],
+        net_views: &mut [MaybeUninit<NetView>],
+        perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
+        report: &mut RoutingReport,
+    ) -> Result<(), RoutingError> {
+        session::connect_nets_deterministic(
+            self,
+            nets,
+            vertices,
+            wire_views,
+            net_views,
+            perform_centering,
+            topology,
+            cost_model,
+            report,
+        )
+    }
+
+    /// Connects multiple nets one after another, making wires avoid running on top of or crossing
+    /// the wires of nets that were connected before.
+    ///
+    /// The results of all nets are written into `vertices` and `wire_views` contiguously.
+    /// `net_views` must be exactly as long as `nets`. The status and diagnostics of every net are written into `report`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn connect_nets_congestion_aware<'a, N: RoutableNet<'a>>(
+        &self,
+        nets: &[N],
+        vertices: &mut [MaybeUninit<Vertex>],
+        wire_views: &mut [MaybeUninit<WireView>],
+        net_views: &mut [MaybeUninit<NetView>],
+        perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
+        congestion_costs: CongestionCosts,
+        report: &mut RoutingReport,
+    ) -> Result<(), RoutingError> {
+        session::connect_nets_congestion_aware(
+            self,
+            nets,
+            vertices,
+            wire_views,
+            net_views,
+            perform_centering,
+            topology,
+            cost_model,
+            congestion_costs,
+            report,
+        )
+    }
+
+    /// Connects multiple nets one after another in order of descending priority, making wires avoid running on top of
+    /// or crossing the wires of nets with a higher priority. Nets with the same priority are connected in the order of `nets`.
+    ///
+    /// The results of all nets are written into `vertices` and `wire_views` contiguously, in the order of `nets`.
+    /// `net_views` must be exactly as long as `nets`. The status and diagnostics of every net are written into `report`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn connect_nets_by_priority<'a, N: RoutableNet<'a>>(
+        &self,
+        nets: &[N],
+        vertices: &mut [MaybeUninit<Vertex>],
+        wire_views: &mut [MaybeUninit<WireView>],
+        net_views: &mut [MaybeUninit<NetView>],
+        perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
+        congestion_costs: CongestionCosts,
+        report: &mut RoutingReport,
+    ) -> Result<(), RoutingError> {
+        session::connect_nets_by_priority(
+            self,
+            nets,
+            vertices,
+            wire_views,
+            net_views,
+            perform_centering,
+            topology,
+            cost_model,
+            congestion_costs,
+            report,
+        )
+    }
+
+    /// Connects the nets of a bus as a bundle of parallel wires with the same bends.
+    ///
+    /// Every net has to consist of a start and an end, in that order. The first net is routed through the graph,
+    /// and all other nets follow it at the constant distance between their start and the start of the first net.
+    /// That distance has to be a multiple of the pitch between the first two nets, given by the position of the net in the bus.
+    /// The first net keeps clear of bounding boxes by the width of the bundle where it can.
+    /// Nets with waypoints, a bend limit the bundle exceeds, a different direction preference than the first net,
+    /// an end that does not line up with the bundle, or a parallel wire that would pass through a bounding box,
+    /// are routed on their own instead.
+    ///
+    /// The results of all nets are written into `vertices` and `wire_views` contiguously.
+    /// `net_views` must be exactly as long as `nets`. The status and diagnostics of every net are written into `report`.
+    ///
+    /// Returns the number of nets that were routed as part of the bundle.
+    pub fn connect_bus<'a, N: RoutableNet<'a>>(
+        &self,
+        nets: &[N],
+        vertices: &mut [MaybeUninit<Vertex>],
+        wire_views: &mut [MaybeUninit<WireView>],
+        net_views: &mut [MaybeUninit<NetView>],
+        cost_model: &CostModel,
+        report: &mut RoutingReport,
+    ) -> Result<usize, RoutingError> {
+        bus::connect_bus(
+            self, nets, vertices, wire_views, net_views, cost_model, report,
+        )
+    }
+
+    /// Connects multiple nets, repeatedly ripping up and rerouting the nets running on top of each other
+    /// until no channel is used by more than one net or the iteration limit is reached.
+    ///
+    /// The results of all nets are written into `vertices` and `wire_views` contiguously.
+    /// `net_views` must be exactly as long as `nets`. The status and diagnostics of every net are written into `report`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn connect_nets_negotiated<'a, N: RoutableNet<'a>>(
+        &self,
+        nets: &[N],
+        vertices: &mut [MaybeUninit<Vertex>],
+        wire_views: &mut [MaybeUninit<WireView>],
+        net_views: &mut [MaybeUninit<NetView>],
+        perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
+        congestion_costs: CongestionCosts,
+        settings: NegotiationSettings,
+        report: &mut RoutingReport,
+    ) -> Result<NegotiationOutcome, RoutingError> {
+        negotiation::connect_nets_negotiated(
+            self,
+            nets,
+            vertices,
+            wire_views,
+            net_views,
+            perform_centering,
+            topology,
+            cost_model,
+            congestion_costs,
+            settings,
+            report,
+        )
+    }
 }
@@ -0,0 +1,129 @@
+use crate::congestion::{Congestion, Occupancy};
+use crate::session::{write_results, Scratch};
+use crate::*;
+use std::mem::MaybeUninit;
+
+/// Settings for negotiating congestion between nets by ripping up and rerouting them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct NegotiationSettings {
+    /// The maximum number of times congested nets are ripped up and routed again.
+    pub max_iterations: u32,
+    /// How much the cost per unit of length of running on top of a wire of another net increases every iteration.
+    pub overlap_penalty_growth: u32,
+    /// The cost per unit of length added to a channel every iteration it is used by more than one net.
+    pub history_penalty: u32,
+}
+
+impl Default for NegotiationSettings {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_iterations: 16,
+            overlap_penalty_growth: 10,
+            history_penalty: 5,
+        }
+    }
+}
+
+/// Statistics about a negotiated routing run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct NegotiationOutcome {
+    /// The number of rip-up and reroute iterations that were performed.
+    pub iterations: u32,
+    /// The number of channels still used by more than one net after the last iteration.
+    pub overused_channel_count: u32,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn connect_nets_negotiated<'a, N: RoutableNet<'a>>(
+    graph: &Graph,
+    nets: &[N],
+    vertices: &mut [MaybeUninit<Vertex>],
+    wire_views: &mut [MaybeUninit<WireView>],
+    net_views: &mut [MaybeUninit<NetView>],
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: &CostModel,
+    congestion_costs: CongestionCosts,
+    settings: NegotiationSettings,
+    report: &mut RoutingReport,
+) -> Result<NegotiationOutcome, RoutingError> {
+    assert_eq!(nets.len(), net_views.len());
+
+    let mut scratch = Scratch::default();
+    let mut occupancy = Occupancy::default();
+    let mut results = Vec::with_capacity(nets.len());
+    let mut diagnostics = Vec::with_capacity(nets.len());
+
+    for (index, net) in nets.iter().enumerate() {
+        results.push(scratch.route_net(
+            graph,
+            net.endpoints(),
+            perform_centering,
+            topology,
+            cost_model,
+            &net.constraints(),
+            Some(&mut Congestion {
+                occupancy: &mut occupancy,
+                net: index.try_into().expect("too many nets"),
+                costs: congestion_costs,
+            }),
+        )?);
+        diagnostics.push(std::mem::take(&mut scratch.diagnostics));
+    }
+
+    let mut outcome = NegotiationOutcome {
+        iterations: 0,
+        overused_channel_count: occupancy.penalize_overuse(settings.history_penalty),
+    };
+
+    while (outcome.overused_channel_count > 0) && (outcome.iterations < settings.max_iterations) {
+        outcome.iterations += 1;
+
+        // Over-using a channel gets more expensive every iteration, so eventually
+        // the nets that have the cheapest alternatives give way.
+        let costs = CongestionCosts {
+            overlap_penalty: congestion_costs.overlap_penalty.saturating_add(
+                settings
+                    .overlap_penalty_growth
+                    .saturating_mul(outcome.iterations),
+            ),
+            ..congestion_costs
+        };
+
+        for net in occupancy.congested_nets() {
+            occupancy.remove_net(net);
+
+            results[net as usize] = scratch.route_net(
+                graph,
+                nets[net as usize].endpoints(),
+                perform_centering,
+                topology,
+                cost_model,
+                &nets[net as usize].constraints(),
+                Some(&mut Congestion {
+                    occupancy: &mut occupancy,
+                    net,
+                    costs,
+                }),
+            )?;
+            diagnostics[net as usize] = std::mem::take(&mut scratch.diagnostics);
+        }
+
+        outcome.overused_channel_count = occupancy.penalize_overuse(settings.history_penalty);
+    }
+
+    write_results(
+        results
+            .iter()
+            .map(|(vertices, wire_views)| (vertices.as_slice(), wire_views.as_slice())),
+        vertices,
+        wire_views,
+        net_views,
+    )?;
+
+    report.set_nets(diagnostics);
+    Ok(outcome)
+}
@@ -1,9 +1,44 @@
-use crate::graph::{Direction, GraphData, NodeIndex, Point, INVALID_NODE_INDEX};
-use crate::{HashMap, HashSet, ReplayCapture};
-use std::borrow::Borrow;
-use std::cmp::Reverse;
+use crate::congestion::Congestion;
+use crate::graph::{Direction, Directions, GraphData, NodeIndex, Point, INVALID_NODE_INDEX};
+use crate::radix_heap::RadixHeap;
+use crate::{HashMap, HashSet, NetConstraints, ReplayCapture, WaypointOrder};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
-type PriorityQueue<I, P> = priority_queue::PriorityQueue<I, P, ahash::RandomState>;
+/// A point on the grid formed by the lines of a graph, as indices into its X and Y coordinates.
+type GridPoint = (usize, usize);
+
+/// How much more expensive it is for a fallback wire to pass through a bounding box
+/// or to leave its start in an illegal direction than to take a detour.
+const FALLBACK_OBSTACLE_WEIGHT: u32 = 64;
+
+/// How many lines of the graph beyond the bounding rectangle of its start and ends a fallback wire may use for detours.
+const FALLBACK_SEARCH_MARGIN: usize = 16;
+
+/// The largest number of grid points the area of a fallback search may contain.
+const FALLBACK_SEARCH_AREA_LIMIT: usize = 1 << 20;
+
+/// The largest number of grid points a fallback search visits before giving up.
+const FALLBACK_SEARCH_VISIT_LIMIT: usize = 1 << 16;
+
+/// The state of a point on the grid in a fallback search.
+#[derive(Debug, Clone, Copy)]
+struct FallbackCell {
+    g_score: u32,
+    /// The direction the cheapest known path arrives in, or `None` for the start.
+    dir: Option<Direction>,
+    visited: bool,
+    is_end: bool,
+}
+
+impl FallbackCell {
+    const UNREACHED: Self = Self {
+        g_score: u32::MAX,
+        dir: None,
+        visited: false,
+        is_end: false,
+    };
+}
 
 #[derive(Debug, Clone, Copy)]
 pub enum PathFindResult<T> {
@@ -25,6 +60,123 @@ impl<T> PathFindResult<T> {
     }
 }
 
+/// Weights the path finder uses to decide which path is the shortest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(C)]
+pub struct CostModel {
+    /// The cost per unit of length of a path.
+    pub length_weight: u32,
+    /// The additional cost per unit of length of a path segment that starts with a bend.
+    pub bend_length_weight: u32,
+    /// The additional cost of every bend in a path.
+    pub bend_penalty: u32,
+    /// How close a path has to come to a bounding box to be considered near it.
+    pub bounding_box_clearance: u32,
+    /// The additional cost per unit of length of a path running near a bounding box.
+    pub bounding_box_penalty: u32,
+}
+
+impl Default for CostModel {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            length_weight: 1,
+            bend_length_weight: 1,
+            bend_penalty: 0,
+            bounding_box_clearance: 0,
+            bounding_box_penalty: 0,
+        }
+    }
+}
+
+impl CostModel {
+    /// The cost of moving from `node` to its neighbor in direction `dir`.
+    fn edge_cost(
+        &self,
+        graph: &GraphData,
+        node: NodeIndex,
+        dir: Direction,
+        bend: bool,
+        constraints: &NetConstraints,
+    ) -> u32 {
+        let neighbor = graph.nodes[node].neighbors[dir];
+        let length = graph.nodes[node]
+            .position
+            .manhatten_distance_to(graph.nodes[neighbor].position);
+
+        let mut cost = length.saturating_mul(self.length_weight);
+
+        if bend {
+            cost = cost
+                .saturating_add(length.saturating_mul(self.bend_length_weight))
+                .saturating_add(self.bend_penalty);
+        }
+
+        if (self.bounding_box_penalty > 0)
+            && graph.edge_is_near_bounding_box(node, dir, self.bounding_box_clearance)
+        {
+            cost = cost.saturating_add(length.saturating_mul(self.bounding_box_penalty));
+        }
+
+        if constraints.direction_preference.penalizes(dir) {
+            cost = cost.saturating_add(length.saturating_mul(self.length_weight));
+        }
+
+        cost.saturating_mul(
+            graph.segment_cost_multiplier(
+                graph.nodes[node].position,
+                graph.nodes[neighbor].position,
+            ),
+        )
+        .saturating_mul(constraints.cost_multiplier())
+    }
+
+    /// The extra cost of leaving `node` in direction `dir` after arriving in a different direction.
+    #[inline]
+    fn bend_cost(
+        &self,
+        graph: &GraphData,
+        node: NodeIndex,
+        dir: Direction,
+        constraints: &NetConstraints,
+    ) -> u32 {
+        self.edge_cost(graph, node, dir, true, constraints)
+            .saturating_sub(self.edge_cost(graph, node, dir, false, constraints))
+    }
+
+    /// A lower bound for the cost of a path from `a` to `b`.
+    #[inline]
+    fn heuristic(&self, a: Point, b: Point) -> u32 {
+        self.distance_heuristic(a.manhatten_distance_to(b))
+    }
+
+    /// A lower bound for the cost of a path covering a Manhattan distance of `distance`.
+    #[inline]
+    fn distance_heuristic(&self, distance: u32) -> u32 {
+        distance.saturating_mul(self.length_weight)
+    }
+}
+
+/// Options for how the path finder searches, which do not change how expensive the path it finds is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Whether paths to a single end are searched for from both sides at once, which can help when the end is boxed in.
+    /// Only applies without congestion costs and bend limits. The path found is as expensive as the one found
+    /// by searching in one direction, but may be a different one if there are several equally expensive paths.
+    ///
+    /// Enabled by default, so routing uses it for every search it applies to.
+    pub bidirectional: bool,
+}
+
+impl Default for SearchOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            bidirectional: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PathNodeKind {
     Normal,
@@ -83,19 +235,576 @@ impl Path {
     }
 }
 
+/// A point a path has to reach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Target {
+    pub(crate) position: Point,
+    /// If set, the path has to travel in this direction while arriving at and leaving the target.
+    pub(crate) direction: Option<Direction>,
+}
+
+impl From<Point> for Target {
+    #[inline]
+    fn from(position: Point) -> Self {
+        Self {
+            position,
+            direction: None,
+        }
+    }
+}
+
+impl From<Target> for Point {
+    #[inline]
+    fn from(target: Target) -> Self {
+        target.position
+    }
+}
+
+/// An axis aligned segment of a wire, or a single point if both of its ends are the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Segment {
+    pub(crate) a: Point,
+    pub(crate) b: Point,
+}
+
+impl Segment {
+    #[inline]
+    pub(crate) fn new(a: Point, b: Point) -> Self {
+        debug_assert!((a.x == b.x) || (a.y == b.y), "segment is not axis aligned");
+        Self { a, b }
+    }
+
+    /// The corner of the bounding rectangle of the segment with the smallest coordinates.
+    #[inline]
+    fn min(self) -> Point {
+        Point {
+            x: self.a.x.min(self.b.x),
+            y: self.a.y.min(self.b.y),
+        }
+    }
+
+    /// The corner of the bounding rectangle of the segment with the largest coordinates.
+    #[inline]
+    fn max(self) -> Point {
+        Point {
+            x: self.a.x.max(self.b.x),
+            y: self.a.y.max(self.b.y),
+        }
+    }
+
+    /// The point on the segment closest to `point`.
+    #[inline]
+    pub(crate) fn closest_point(self, point: Point) -> Point {
+        let min = self.min();
+        let max = self.max();
+        Point {
+            x: point.x.clamp(min.x, max.x),
+            y: point.y.clamp(min.y, max.y),
+        }
+    }
+
+    /// The Manhattan distance from `point` to the closest point on the segment.
+    #[inline]
+    pub(crate) fn distance_to(self, point: Point) -> u32 {
+        point.manhatten_distance_to(self.closest_point(point))
+    }
+
+    /// Determines if `point` lies on the segment.
+    #[inline]
+    pub(crate) fn contains(self, point: Point) -> bool {
+        self.closest_point(point) == point
+    }
+
+    /// The nodes of the graph the segment passes through, including its ends.
+    fn nodes(self, graph: &GraphData) -> impl Iterator<Item = NodeIndex> + '_ {
+        // The coordinates in `coords` from `min` to `max`.
+        fn coords_between(coords: &[i32], min: i32, max: i32) -> &[i32] {
+            let start = coords.partition_point(|&coord| coord < min);
+            let end = coords.partition_point(|&coord| coord <= max);
+            &coords[start..end]
+        }
+
+        let min = self.min();
+        let max = self.max();
+
+        // All nodes the segment passes through lie on its line, so only one of their coordinates varies.
+        let horizontal = min.y == max.y;
+        let coords = if horizontal {
+            coords_between(graph.x_coords(), min.x, max.x)
+        } else {
+            coords_between(graph.y_coords(), min.y, max.y)
+        };
+
+        coords
+            .iter()
+            .map(move |&coord| {
+                if horizontal {
+                    Point { x: coord, y: min.y }
+                } else {
+                    Point { x: min.x, y: coord }
+                }
+            })
+            .filter_map(|point| graph.find_node(point))
+    }
+}
+
+impl From<Point> for Segment {
+    #[inline]
+    fn from(point: Point) -> Self {
+        Self { a: point, b: point }
+    }
+}
+
+/// How a path has to leave its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StartDirection {
+    /// The path can leave in any direction.
+    Any,
+    /// The path continues a wire arriving in this direction, so it cannot turn back.
+    Continue(Direction),
+    /// The path has to leave in exactly this direction.
+    Exactly(Direction),
+}
+
+impl StartDirection {
+    #[inline]
+    fn straight_dir(self) -> Option<Direction> {
+        match self {
+            Self::Any => None,
+            Self::Continue(dir) | Self::Exactly(dir) => Some(dir),
+        }
+    }
+
+    #[inline]
+    fn allows(self, dir: Direction) -> bool {
+        match self {
+            Self::Any | Self::Continue(_) => true,
+            Self::Exactly(required_dir) => dir == required_dir,
+        }
+    }
+}
+
+impl From<Option<Direction>> for StartDirection {
+    #[inline]
+    fn from(dir: Option<Direction>) -> Self {
+        match dir {
+            Some(dir) => Self::Continue(dir),
+            None => Self::Any,
+        }
+    }
+}
+
+/// Up to this many ends, the closest one is found by checking all of them.
+const END_INDEX_LINEAR_LIMIT: usize = 16;
+
+/// A grid over the ends of a search, to find the distance to the closest end without checking every one.
+///
+/// Ends can be single points or whole segments of wires.
+#[derive(Default)]
+struct EndIndex {
+    min: Point,
+    cell_size: i64,
+    columns: i64,
+    rows: i64,
+    /// The ends.
+    ends: Vec<Segment>,
+    /// The ends overlapping each cell, sorted by cell. An end overlapping several cells is stored once for each of them.
+    cell_ends: Vec<Segment>,
+    /// The offset into `cell_ends` at which the ends of each cell start, followed by the total number of entries.
+    /// Empty if there are few enough ends to check all of them.
+    cell_offsets: Vec<u32>,
+}
+
+impl EndIndex {
+    fn build(&mut self, ends: impl Iterator<Item = Segment>) {
+        self.ends.clear();
+        self.ends.extend(ends);
+        self.cell_ends.clear();
+        self.cell_offsets.clear();
+
+        if self.ends.len() <= END_INDEX_LINEAR_LIMIT {
+            return;
+        }
+
+        let mut min = self.ends[0].min();
+        let mut max = self.ends[0].max();
+        for end in &self.ends {
+            let (end_min, end_max) = (end.min(), end.max());
+            min.x = min.x.min(end_min.x);
+            min.y = min.y.min(end_min.y);
+            max.x = max.x.max(end_max.x);
+            max.y = max.y.max(end_max.y);
+        }
+
+        // Cells are sized so that there are about as many cells as ends, even if all ends lie on one line.
+        let width = (max.x as i64) - (min.x as i64);
+        let height = (max.y as i64) - (min.y as i64);
+        let count = self.ends.len() as i64;
+        let area_cell_size = (((width * height) / count) as f64).sqrt().ceil() as i64;
+        let line_cell_size = (width.max(height) + count - 1) / count;
+
+        self.min = min;
+        self.cell_size = area_cell_size.max(line_cell_size).max(1);
+        self.columns = (width / self.cell_size) + 1;
+        self.rows = (height / self.cell_size) + 1;
+
+        let cell_count = (self.columns * self.rows) as usize;
+        self.cell_offsets.resize(cell_count + 1, 0);
+        for i in 0..self.ends.len() {
+            for cell in self.cells_of(self.ends[i]) {
+                self.cell_offsets[cell + 1] += 1;
+            }
+        }
+
+        for cell in 0..cell_count {
+            self.cell_offsets[cell + 1] += self.cell_offsets[cell];
+        }
+
+        // Sort the ends into their cells, using the offsets as cursors and restoring them afterwards.
+        self.cell_ends
+            .resize(self.cell_offsets[cell_count] as usize, Segment::from(min));
+        for i in 0..self.ends.len() {
+            let end = self.ends[i];
+            for cell in self.cells_of(end) {
+                self.cell_ends[self.cell_offsets[cell] as usize] = end;
+                self.cell_offsets[cell] += 1;
+            }
+        }
+
+        for cell in (1..=cell_count).rev() {
+            self.cell_offsets[cell] = self.cell_offsets[cell - 1];
+        }
+        self.cell_offsets[0] = 0;
+    }
+
+    /// The column and row of the cell closest to `point`.
+    #[inline]
+    fn cell_coords(&self, point: Point) -> (i64, i64) {
+        let column = ((point.x as i64) - (self.min.x as i64)).div_euclid(self.cell_size);
+        let row = ((point.y as i64) - (self.min.y as i64)).div_euclid(self.cell_size);
+        (
+            column.clamp(0, self.columns - 1),
+            row.clamp(0, self.rows - 1),
+        )
+    }
+
+    #[inline]
+    fn cell_of(&self, point: Point) -> usize {
+        let (column, row) = self.cell_coords(point);
+        (row * self.columns + column) as usize
+    }
+
+    /// The cells `segment` overlaps.
+    fn cells_of(&self, segment: Segment) -> impl Iterator<Item = usize> {
+        let (min_column, min_row) = self.cell_coords(segment.min());
+        let (max_column, max_row) = self.cell_coords(segment.max());
+        let columns = self.columns;
+
+        (min_row..=max_row).flat_map(move |row| {
+            (min_column..=max_column).map(move |column| (row * columns + column) as usize)
+        })
+    }
+
+    /// The ends overlapping the cell at index `cell`.
+    #[inline]
+    fn cell(&self, cell: usize) -> &[Segment] {
+        &self.cell_ends[(self.cell_offsets[cell] as usize)..(self.cell_offsets[cell + 1] as usize)]
+    }
+
+    /// The Manhattan distance from `point` to the closest end.
+    fn distance(&self, point: Point) -> u32 {
+        let closest_in = |ends: &[Segment]| ends.iter().map(|end| end.distance_to(point)).min();
+
+        if self.cell_offsets.is_empty() {
+            return closest_in(&self.ends).expect("empty end point list");
+        }
+
+        // Search rings of cells around the point. An end in ring `r + 1` is more than `r` cells away,
+        // so once an end at most that far away was found, no further ring can contain a closer one.
+        let (column, row) = self.cell_coords(point);
+        let mut min_distance = u32::MAX;
+        for ring in 0..=self.columns.max(self.rows) {
+            for ring_row in (row - ring)..=(row + ring) {
+                if !(0..self.rows).contains(&ring_row) {
+                    continue;
+                }
+
+                let column_step = if ring_row.abs_diff(row) == (ring as u64) {
+                    1
+                } else {
+                    (2 * ring).max(1) as usize
+                };
+
+                for ring_column in ((column - ring)..=(column + ring)).step_by(column_step) {
+                    if !(0..self.columns).contains(&ring_column) {
+                        continue;
+                    }
+
+                    let cell = (ring_row * self.columns + ring_column) as usize;
+                    if let Some(distance) = closest_in(self.cell(cell)) {
+                        min_distance = min_distance.min(distance);
+                    }
+                }
+            }
+
+            if (min_distance as i64) <= ring * self.cell_size {
+                break;
+            }
+        }
+
+        min_distance
+    }
+
+    /// Determines if `point` lies on any end.
+    fn contains(&self, point: Point) -> bool {
+        if self.cell_offsets.is_empty() {
+            return self.ends.iter().any(|end| end.contains(point));
+        }
+
+        // An end containing the point overlaps the cell the point is in.
+        self.cell(self.cell_of(point))
+            .iter()
+            .any(|end| end.contains(point))
+    }
+}
+
+/// The index of a state in a [`SearchState`].
+type StateIndex = u32;
+
+/// The number of directions a path can arrive at a node in, plus one for the start of the path.
+const ARRIVAL_SLOTS: u32 = 5;
+
+/// The search state of a single node, or of a single way to arrive at it.
+#[derive(Clone, Copy)]
+struct NodeState {
+    /// The search this state belongs to. In any other search, the state has not been reached yet.
+    generation: u32,
+    g_score: u32,
+    /// The f-score the state is queued with, if it is queued.
+    f_score: Option<u32>,
+    predecessor: StateIndex,
+}
+
+impl NodeState {
+    const UNREACHED: Self = Self {
+        generation: 0,
+        g_score: u32::MAX,
+        f_score: None,
+        predecessor: INVALID_NODE_INDEX,
+    };
+}
+
+/// The state of an A* search, stored in flat arrays.
+///
+/// Every node has a state for every direction a path can arrive in, because whether the next segment
+/// starts with a bend, and so how expensive it is, depends on it. With a bend limit, every node additionally has
+/// these states for every number of bends up to the limit, because a path that is more expensive up to the node
+/// but has fewer bends can still be continued where the cheaper one exceeds the limit.
+///
+/// Instead of clearing the arrays, every search starts a new generation,
+/// and states that belong to an older one count as not reached yet.
+/// The open queue may contain outdated entries for states that were queued again with a lower f-score, which are skipped.
+#[derive(Default)]
+struct SearchState {
+    generation: u32,
+    /// The number of states of every node.
+    layers: u32,
+    nodes: Vec<NodeState>,
+    open_queue: RadixHeap<StateIndex>,
+    open_count: usize,
+}
+
+impl SearchState {
+    /// The number of states every node needs to follow `constraints`.
+    fn layers_for(constraints: &NetConstraints) -> u32 {
+        debug_assert!(constraints.is_valid(), "bend limit out of range");
+
+        if constraints.max_bends == 0 {
+            ARRIVAL_SLOTS
+        } else {
+            constraints
+                .max_bends
+                .saturating_add(1)
+                .saturating_mul(ARRIVAL_SLOTS)
+        }
+    }
+
+    fn clear(&mut self, node_count: usize, layers: u32) {
+        if self.generation == u32::MAX {
+            self.nodes.fill(NodeState::UNREACHED);
+            self.generation = 0;
+        }
+
+        let state_count = node_count
+            .checked_mul(layers as usize)
+            .filter(|&state_count| state_count <= (INVALID_NODE_INDEX as usize))
+            .expect("too many search states");
+
+        self.generation += 1;
+        self.layers = layers;
+        self.nodes.resize(state_count, NodeState::UNREACHED);
+        self.open_queue.clear();
+        self.open_count = 0;
+    }
+
+    /// The state of a path arriving at `node` in direction `dir` after `bends` bends,
+    /// or of a path starting at `node` if `dir` is `None`.
+    #[inline]
+    fn state(&self, node: NodeIndex, dir: Option<Direction>, bends: u32) -> StateIndex {
+        let slot = dir.map_or(ARRIVAL_SLOTS - 1, |dir| dir as u32);
+        node * self.layers + bends * ARRIVAL_SLOTS + slot
+    }
+
+    /// The node a state belongs to.
+    #[inline]
+    fn node(&self, state: StateIndex) -> NodeIndex {
+        state / self.layers
+    }
+
+    /// The direction the paths a state belongs to arrive in, or `None` for the start of a path.
+    #[inline]
+    fn arrival_dir(&self, state: StateIndex) -> Option<Direction> {
+        match (state % self.layers) % ARRIVAL_SLOTS {
+            slot if slot == (Direction::PosX as u32) => Some(Direction::PosX),
+            slot if slot == (Direction::NegX as u32) => Some(Direction::NegX),
+            slot if slot == (Direction::PosY as u32) => Some(Direction::PosY),
+            slot if slot == (Direction::NegY as u32) => Some(Direction::NegY),
+            _ => None,
+        }
+    }
+
+    /// All states of `node`.
+    #[inline]
+    fn states(&self, node: NodeIndex) -> std::ops::Range<StateIndex> {
+        (node * self.layers)..((node + 1) * self.layers)
+    }
+
+    /// The number of bends of the paths a state belongs to.
+    #[inline]
+    fn bend_count(&self, state: StateIndex) -> u32 {
+        (state % self.layers) / ARRIVAL_SLOTS
+    }
+
+    #[inline]
+    fn get(&self, index: StateIndex) -> Option<&NodeState> {
+        let node = &self.nodes[index as usize];
+        (node.generation == self.generation).then_some(node)
+    }
+
+    /// The state at `index` in this search, which is reset first if it was not reached yet.
+    #[inline]
+    fn get_mut(&mut self, index: StateIndex) -> &mut NodeState {
+        let node = &mut self.nodes[index as usize];
+        if node.generation != self.generation {
+            *node = NodeState {
+                generation: self.generation,
+                ..NodeState::UNREACHED
+            };
+        }
+
+        node
+    }
+
+    #[inline]
+    fn g_score(&self, index: StateIndex) -> Option<u32> {
+        self.get(index).map(|node| node.g_score)
+    }
+
+    #[inline]
+    fn predecessor(&self, index: StateIndex) -> Option<StateIndex> {
+        self.get(index)
+            .map(|node| node.predecessor)
+            .filter(|&pred_index| pred_index != INVALID_NODE_INDEX)
+    }
+
+    #[inline]
+    fn set_predecessor(&mut self, index: StateIndex, pred_index: StateIndex) {
+        self.get_mut(index).predecessor = pred_index;
+    }
+
+    /// All nodes that have a predecessor in this search, together with it.
+    #[cfg(debug_assertions)]
+    fn predecessors(&self) -> impl Iterator<Item = (NodeIndex, NodeIndex)> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| {
+                (node.generation == self.generation) && (node.predecessor != INVALID_NODE_INDEX)
+            })
+            .map(|(index, node)| (self.node(index as StateIndex), self.node(node.predecessor)))
+    }
+
+    /// The number of queued states.
+    #[inline]
+    fn len(&self) -> usize {
+        self.open_count
+    }
+
+    /// Queues `index` with `f_score`, replacing the f-score it is already queued with.
+    #[inline]
+    fn push(&mut self, index: StateIndex, f_score: u32) {
+        let node = self.get_mut(index);
+        if node.f_score.replace(f_score).is_none() {
+            self.open_count += 1;
+        }
+
+        self.open_queue.push(f_score, index);
+    }
+
+    #[inline]
+    fn is_current(&self, index: StateIndex, f_score: u32) -> bool {
+        self.get(index)
+            .is_some_and(|node| node.f_score == Some(f_score))
+    }
+
+    /// The lowest f-score of any queued state.
+    fn peek(&mut self) -> Option<u32> {
+        loop {
+            let (f_score, &index) = self.open_queue.peek()?;
+            if self.is_current(index, f_score) {
+                return Some(f_score);
+            }
+
+            self.open_queue.pop();
+        }
+    }
+
+    /// Removes the queued state with the lowest f-score from the queue.
+    fn pop(&mut self) -> Option<StateIndex> {
+        loop {
+            let (f_score, index) = self.open_queue.pop()?;
+            if self.is_current(index, f_score) {
+                self.nodes[index as usize].f_score = None;
+                self.open_count -= 1;
+                return Some(index);
+            }
+        }
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct PathFinder {
     end_indices: HashSet<NodeIndex>,
-    g_score: HashMap<NodeIndex, u32>,
-    predecessor: HashMap<NodeIndex, NodeIndex>,
-    open_queue: PriorityQueue<NodeIndex, Reverse<u32>>,
+    pending_end_indices: VecDeque<NodeIndex>,
+    end_directions: HashMap<NodeIndex, Direction>,
+    /// Segments of wires the current search can end anywhere on.
+    end_segments: Vec<Segment>,
+    end_index: EndIndex,
+    search: SearchState,
+    /// The search from the end in a bidirectional search, in which the predecessor of a node is its successor on the path.
+    backward_search: SearchState,
     path: Path,
+    unreachable_ends: Vec<Point>,
+    /// The grid points of the area of the last fallback search, row by row.
+    fallback_cells: Vec<FallbackCell>,
+    fallback_open_queue: RadixHeap<usize>,
+    fallback_path: Vec<Point>,
 }
 
 impl PathFinder {
     #[cfg(debug_assertions)]
     fn assert_data_is_valid(&self, graph: &GraphData) {
-        for (&node_index, &pred_index) in &self.predecessor {
+        for (node_index, pred_index) in self.search.predecessors() {
             assert_ne!(node_index, INVALID_NODE_INDEX);
             assert_ne!(pred_index, INVALID_NODE_INDEX);
 
@@ -120,12 +829,14 @@ impl PathFinder {
     fn build_path(
         &mut self,
         graph: &GraphData,
-        start_index: NodeIndex,
-        end_index: NodeIndex,
+        start_state: StateIndex,
+        end_state: StateIndex,
         replay: &mut impl ReplayCapture,
     ) {
+        let end_index = self.search.node(end_state);
+
         // If there was a previous path segment, don't duplicate the joining point.
-        if self.path.nodes.len() > 0 {
+        if !self.path.nodes.is_empty() {
             replay.path_finding_remove_path_node(self.path.nodes.len() - 1);
 
             let prev_end = self.path.nodes.pop();
@@ -151,21 +862,25 @@ impl PathFinder {
 
         replay.path_finding_insert_path_node(insert_index, end_index);
 
-        if end_index == start_index {
+        if end_state == start_state {
             return;
         }
 
-        let mut current_index = end_index;
+        let mut current_state = end_state;
         loop {
-            let pred_index = *self.predecessor.get(&current_index).expect("invalid path");
+            let pred_state = self
+                .search
+                .predecessor(current_state)
+                .expect("invalid path");
+            let pred_index = self.search.node(pred_state);
             let pred = &graph.nodes[pred_index];
 
             let dir = pred
                 .neighbors
-                .find(current_index)
+                .find(self.search.node(current_state))
                 .expect("invalid predecessor");
 
-            if pred_index == start_index {
+            if pred_state == start_state {
                 let kind = if insert_index == 0 {
                     PathNodeKind::Start
                 } else {
@@ -196,106 +911,289 @@ impl PathFinder {
 
                 replay.path_finding_insert_path_node(insert_index, pred_index);
 
-                current_index = pred_index;
+                current_state = pred_state;
             }
         }
     }
 
+    /// The ends the last search could not reach.
+    #[inline]
+    pub(crate) fn unreachable_ends(&self) -> &[Point] {
+        &self.unreachable_ends
+    }
+
+    /// Clears the search state and starts a new search at `start_index`,
+    /// which continues a path arriving from `pred_index` if there is one.
+    ///
+    /// Returns the state the search starts in.
+    fn reset_search(
+        &mut self,
+        graph: &GraphData,
+        start_index: NodeIndex,
+        pred_index: Option<NodeIndex>,
+        constraints: &NetConstraints,
+        replay: &mut impl ReplayCapture,
+    ) -> StateIndex {
+        self.search
+            .clear(graph.nodes.len(), SearchState::layers_for(constraints));
+
+        let start_state = self.search.state(start_index, None, 0);
+        self.search.get_mut(start_state).g_score = 0;
+        replay.path_finding_set_g_score(start_index, 0);
+        self.search.push(start_state, 0);
+        replay.path_finding_push_open_queue(start_index, 0);
+
+        if let Some(pred_index) = pred_index {
+            let pred_state = self.search.state(pred_index, None, 0);
+            self.search.set_predecessor(start_state, pred_state);
+            replay.path_finding_set_predecessor(start_index, pred_index);
+        }
+
+        start_state
+    }
+
+    /// Determines if a path may arrive at the end `end_index` while travelling in direction `dir`.
+    fn arrives_legally(&self, graph: &GraphData, end_index: NodeIndex, dir: Direction) -> bool {
+        let from_legal_side = graph
+            .arrival_directions(end_index)
+            .contains(dir.opposite().into());
+        let in_required_dir = self
+            .end_directions
+            .get(&end_index)
+            .map_or(true, |&required_dir| required_dir == dir);
+
+        from_legal_side && in_required_dir
+    }
+
+    /// Determines if the node `index` is one of the ends of the current search.
+    #[inline]
+    fn is_end(&self, graph: &GraphData, index: NodeIndex) -> bool {
+        self.end_indices.contains(&index)
+            || (!self.end_segments.is_empty()
+                && self.end_index.contains(graph.nodes[index].position))
+    }
+
     /// A* path finding.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn find_path<'a>(
         &'a mut self,
         graph: &GraphData,
         start: Point,
-        start_straight_dir: Option<Direction>,
-        ends: impl IntoIterator<Item = Point>,
+        start_dir: StartDirection,
+        ends: impl IntoIterator<Item: Into<Target>>,
         visit_all: bool,
+        cost_model: &CostModel,
+        options: &SearchOptions,
+        constraints: &NetConstraints,
+        congestion: Option<&Congestion>,
         replay: &mut impl ReplayCapture,
     ) -> PathFindResult<&'a Path> {
+        self.search(
+            graph,
+            start,
+            start_dir,
+            ends,
+            &[],
+            visit_all,
+            cost_model,
+            options,
+            constraints,
+            congestion,
+            replay,
+        )
+    }
+
+    /// A* path finding to the closest point on any of `segments`.
+    ///
+    /// The path can end at any node of the graph a segment passes through, not only at the ends of the segments.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn find_path_to_segments<'a>(
+        &'a mut self,
+        graph: &GraphData,
+        start: Point,
+        start_dir: StartDirection,
+        segments: &[Segment],
+        cost_model: &CostModel,
+        constraints: &NetConstraints,
+        congestion: Option<&Congestion>,
+        replay: &mut impl ReplayCapture,
+    ) -> PathFindResult<&'a Path> {
+        self.search(
+            graph,
+            start,
+            start_dir,
+            std::iter::empty::<Target>(),
+            segments,
+            false,
+            cost_model,
+            &SearchOptions::default(),
+            constraints,
+            congestion,
+            replay,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn search<'a>(
+        &'a mut self,
+        graph: &GraphData,
+        start: Point,
+        start_dir: StartDirection,
+        ends: impl IntoIterator<Item: Into<Target>>,
+        end_segments: &[Segment],
+        visit_all: bool,
+        cost_model: &CostModel,
+        options: &SearchOptions,
+        constraints: &NetConstraints,
+        congestion: Option<&Congestion>,
+        replay: &mut impl ReplayCapture,
+    ) -> PathFindResult<&'a Path> {
+        debug_assert!(
+            !visit_all || end_segments.is_empty(),
+            "segments cannot be visited in order",
+        );
+
         let Some(mut start_index) = graph.find_node(start) else {
-            println!(
-                "Start point ({}, {}) does not exist in the graph",
-                start.x, start.y
-            );
             return PathFindResult::InvalidStartPoint;
         };
 
+        // Unless the ends are visited closest first, only the next end in order is a target at any time.
+        let in_order = visit_all && (constraints.waypoint_order != WaypointOrder::Nearest);
+
         self.end_indices.clear();
+        self.pending_end_indices.clear();
+        self.end_directions.clear();
+        self.end_segments.clear();
+        self.end_segments.extend_from_slice(end_segments);
         self.path.clear();
+        self.unreachable_ends.clear();
 
         let mut total_neighbor_count = 0;
         for end in ends {
-            let end = *end.borrow();
+            let end: Target = end.into();
 
-            let Some(end_index) = graph.find_node(end) else {
-                println!(
-                    "End point ({}, {}) does not exist in the graph",
-                    end.x, end.y
-                );
+            let Some(end_index) = graph.find_node(end.position) else {
                 return PathFindResult::InvalidEndPoint;
             };
 
+            if let Some(dir) = end.direction {
+                self.end_directions.entry(end_index).or_insert(dir);
+            }
+
             let end_node = &graph.nodes[end_index];
             let neighbor_count = end_node.neighbor_count();
 
             if neighbor_count > 0 {
-                if self.end_indices.insert(end_index) {
+                if in_order {
+                    if !self.pending_end_indices.contains(&end_index) {
+                        self.pending_end_indices.push_back(end_index);
+                    }
+                } else if self.end_indices.insert(end_index) {
                     total_neighbor_count += neighbor_count;
                 }
             } else {
-                #[cfg(debug_assertions)]
-                println!("Waypoint ({}, {}) unreachable, skipping", end.x, end.y);
+                self.unreachable_ends.push(end.position);
             }
         }
 
-        replay.begin_path_finding(start_index, self.end_indices.iter().copied(), visit_all);
+        replay.begin_path_finding(
+            start_index,
+            self.end_indices
+                .iter()
+                .chain(&self.pending_end_indices)
+                .copied()
+                .chain(
+                    self.end_segments
+                        .iter()
+                        .flat_map(|segment| segment.nodes(graph)),
+                ),
+            visit_all,
+        );
+
+        // Without congestion or a bend limit the cost of a path does not depend on how it was found,
+        // so a single end can be searched for from both sides.
+        if options.bidirectional
+            && !visit_all
+            && congestion.is_none()
+            && (constraints.max_bends == 0)
+            && (self.end_indices.len() == 1)
+        {
+            let end_index = *self.end_indices.iter().next().unwrap();
+            if end_index != start_index {
+                let found = self.find_path_bidirectional(
+                    graph,
+                    start_index,
+                    start_dir,
+                    end_index,
+                    cost_model,
+                    constraints,
+                    replay,
+                );
 
-        self.g_score.clear();
-        self.predecessor.clear();
-        self.open_queue.clear();
+                match found {
+                    Some(found) => {
+                        if !found {
+                            self.unreachable_ends.push(graph.nodes[end_index].position);
+                        }
 
-        self.g_score.insert(start_index, 0);
-        replay.path_finding_set_g_score(start_index, 0);
-        self.open_queue.push(start_index, Reverse(0));
-        replay.path_finding_push_open_queue(start_index, 0);
+                        return self.finish(replay);
+                    }
+                    None => replay.path_finding_clear_state(),
+                }
+            }
+        }
+
+        let mut start_state = self.reset_search(graph, start_index, None, constraints, replay);
+        let mut start_dir = start_dir;
 
         'outer: loop {
-            if total_neighbor_count == 0 {
+            if in_order && self.end_indices.is_empty() {
+                if let Some(end_index) = self.pending_end_indices.pop_front() {
+                    self.end_indices.insert(end_index);
+                    total_neighbor_count = graph.nodes[end_index].neighbor_count();
+                }
+            }
+
+            if (total_neighbor_count == 0) && self.end_segments.is_empty() {
                 // There cannot possibly be a path, abort.
                 break 'outer;
             }
 
-            while let Some((current_index, _)) = self.open_queue.pop() {
+            self.end_index.build(
+                self.end_indices
+                    .iter()
+                    .map(|&end_index| graph.nodes[end_index].position.into())
+                    .chain(self.end_segments.iter().copied()),
+            );
+
+            while let Some(current_state) = self.search.pop() {
+                let current_index = self.search.node(current_state);
                 replay.path_finding_pop_open_queue(current_index);
 
                 let current_node = &graph.nodes[current_index];
 
-                let pred_index = self.predecessor.get(&current_index).copied();
+                let pred_index = self
+                    .search
+                    .predecessor(current_state)
+                    .map(|pred_state| self.search.node(pred_state));
 
                 // Shortest path to one end found, construct it.
-                if self.end_indices.contains(&current_index) {
+                if self.is_end(graph, current_index) {
                     self.assert_data_is_valid(graph);
-                    self.build_path(graph, start_index, current_index, replay);
+                    self.build_path(graph, start_state, current_state, replay);
 
                     if visit_all {
                         self.end_indices.remove(&current_index);
                         total_neighbor_count -= current_node.neighbor_count();
                         start_index = current_index;
+                        start_dir = match self.end_directions.get(&current_index) {
+                            Some(&dir) => StartDirection::Exactly(dir),
+                            None => StartDirection::Any,
+                        };
 
                         replay.path_finding_clear_state();
-
-                        self.g_score.clear();
-                        self.predecessor.clear();
-                        self.open_queue.clear();
-
-                        self.g_score.insert(start_index, 0);
-                        replay.path_finding_set_g_score(start_index, 0);
-                        self.open_queue.push(start_index, Reverse(0));
-                        replay.path_finding_push_open_queue(start_index, 0);
-
-                        if let Some(pred_index) = pred_index {
-                            self.predecessor.insert(start_index, pred_index);
-                            replay.path_finding_set_predecessor(start_index, pred_index);
-                        }
+                        start_state =
+                            self.reset_search(graph, start_index, pred_index, constraints, replay);
 
                         continue 'outer;
                     } else {
@@ -318,7 +1216,7 @@ impl PathFinder {
 
                         pred_to_current_dir
                     })
-                    .or(start_straight_dir);
+                    .or(start_dir.straight_dir());
 
                 for dir in Direction::ALL {
                     if Some(dir.opposite()) == straight_dir {
@@ -326,70 +1224,128 @@ impl PathFinder {
                         continue;
                     }
 
+                    if (current_index == start_index) && !start_dir.allows(dir) {
+                        continue;
+                    }
+
                     let neighbor_index = current_node.neighbors[dir];
                     if neighbor_index == INVALID_NODE_INDEX {
                         continue;
                     }
 
+                    // Ends can only be reached from their legal sides, and in the direction they have to be passed through in.
+                    if self.is_end(graph, neighbor_index)
+                        && !self.arrives_legally(graph, neighbor_index, dir)
+                    {
+                        continue;
+                    }
+
                     let neighbor_node = &graph.nodes[neighbor_index];
                     debug_assert_eq!(neighbor_node.neighbors[dir.opposite()], current_index);
 
-                    // Calculate the new path lenght.
-                    let new_g_score = self.g_score[&current_index]
-                        + current_node
-                            .position
-                            .manhatten_distance_to(neighbor_node.position)
-                            * if Some(dir) == straight_dir { 1 } else { 2 };
+                    // Edges used by other nets are more expensive, or cannot be used at all.
+                    let congestion_cost = match congestion {
+                        Some(congestion) => {
+                            match congestion.edge_cost(graph, current_index, dir, straight_dir) {
+                                Some(cost) => cost,
+                                None => continue,
+                            }
+                        }
+                        None => 0,
+                    };
+
+                    // Paths with too many bends are not continued. Without a limit, bends are not counted.
+                    let bend = Some(dir) != straight_dir;
+                    let new_bend_count = if constraints.max_bends == 0 {
+                        0
+                    } else {
+                        self.search.bend_count(current_state)
+                            + u32::from(bend && straight_dir.is_some())
+                    };
+                    if !constraints.allows_bends(new_bend_count) {
+                        continue;
+                    }
+
+                    let neighbor_state =
+                        self.search.state(neighbor_index, Some(dir), new_bend_count);
+
+                    // Calculate the new path cost.
+                    let new_g_score = self
+                        .search
+                        .g_score(current_state)
+                        .expect("unreached node in open queue")
+                        .saturating_add(cost_model.edge_cost(
+                            graph,
+                            current_index,
+                            dir,
+                            bend,
+                            constraints,
+                        ))
+                        .saturating_add(congestion_cost);
 
                     // Check whether the new path length is shorter than the previous one.
-                    let update = match self.g_score.get(&neighbor_index) {
-                        Some(&g_score) => new_g_score < g_score,
+                    let update = match self.search.g_score(neighbor_state) {
+                        Some(g_score) => new_g_score < g_score,
                         None => true,
                     };
 
                     if update {
                         // Shorter path found, update it.
-                        self.g_score.insert(neighbor_index, new_g_score);
+                        let neighbor = self.search.get_mut(neighbor_state);
+                        neighbor.g_score = new_g_score;
                         replay.path_finding_set_g_score(neighbor_index, new_g_score);
-                        self.predecessor.insert(neighbor_index, current_index);
+                        neighbor.predecessor = current_state;
                         replay.path_finding_set_predecessor(neighbor_index, current_index);
 
                         // Calculate the new approximate total cost.
-                        let new_f_score = new_g_score
-                            + self
-                                .end_indices
-                                .iter()
-                                .map(|&end_index| &graph.nodes[end_index])
-                                .map(|end| {
-                                    neighbor_node.position.manhatten_distance_to(end.position)
-                                })
-                                .min()
-                                .expect("empty end point list");
-
-                        self.open_queue.push(neighbor_index, Reverse(new_f_score));
+                        let new_f_score = new_g_score.saturating_add(
+                            cost_model
+                                .distance_heuristic(self.end_index.distance(neighbor_node.position))
+                                .saturating_mul(constraints.cost_multiplier()),
+                        );
+
+                        self.search.push(neighbor_state, new_f_score);
                         replay.path_finding_push_open_queue(neighbor_index, new_f_score);
                     }
                 }
             }
 
-            #[cfg(debug_assertions)]
-            {
-                print!("Unable to find path to remaining waypoints [");
-                for (i, &end_index) in self.end_indices.iter().enumerate() {
-                    if i > 0 {
-                        print!(", ");
-                    }
-
-                    let end_node = &graph.nodes[end_index];
-                    print!("({}, {})", end_node.position.x, end_node.position.y);
-                }
-                println!("]");
+            // An end that cannot be reached in order is skipped, and the next one is searched from the same start.
+            if in_order && !self.pending_end_indices.is_empty() {
+                let end_index = self
+                    .end_indices
+                    .drain()
+                    .next()
+                    .expect("empty end point list");
+                self.unreachable_ends.push(graph.nodes[end_index].position);
+
+                let pred_index = self
+                    .search
+                    .predecessor(start_state)
+                    .map(|pred_state| self.search.node(pred_state));
+                replay.path_finding_clear_state();
+                start_state =
+                    self.reset_search(graph, start_index, pred_index, constraints, replay);
+
+                continue 'outer;
             }
 
+            let first_remaining = self.unreachable_ends.len();
+            self.unreachable_ends.extend(
+                self.end_indices
+                    .iter()
+                    .map(|&end_index| graph.nodes[end_index].position),
+            );
+            self.unreachable_ends[first_remaining..].sort_unstable_by_key(|end| (end.x, end.y));
+
             break 'outer;
         }
 
-        if self.path.nodes.len() > 0 {
+        self.finish(replay)
+    }
+
+    fn finish(&mut self, replay: &mut impl ReplayCapture) -> PathFindResult<&Path> {
+        if !self.path.nodes.is_empty() {
             replay.end_path_finding(true);
             PathFindResult::Found(&self.path)
         } else {
@@ -397,4 +1353,550 @@ impl PathFinder {
             PathFindResult::NotFound
         }
     }
+
+    /// The cost of the path joining the forward path to `forward_state` with the backward path from `backward_state`,
+    /// which have to belong to the same node.
+    ///
+    /// Returns `None` if the two paths cannot be joined.
+    #[allow(clippy::too_many_arguments)]
+    fn meeting_cost(
+        &self,
+        graph: &GraphData,
+        start_dir: StartDirection,
+        forward_state: StateIndex,
+        backward_state: StateIndex,
+        cost_model: &CostModel,
+        constraints: &NetConstraints,
+    ) -> Option<u32> {
+        let forward_g_score = self.search.g_score(forward_state)?;
+        let backward_g_score = self.backward_search.g_score(backward_state)?;
+
+        let Some(out_dir) = self.backward_search.arrival_dir(backward_state) else {
+            // The forward search reached the end itself.
+            return Some(forward_g_score);
+        };
+
+        // Only the start of the path has no arrival direction in the forward search.
+        let in_dir = self.search.arrival_dir(forward_state);
+        let is_start = in_dir.is_none();
+        let in_dir = in_dir.or(start_dir.straight_dir());
+
+        if (in_dir == Some(out_dir.opposite())) || (is_start && !start_dir.allows(out_dir)) {
+            return None;
+        }
+
+        // The backward search could not know whether the path bends here.
+        let meeting_index = self.search.node(forward_state);
+        let bend_cost = if Some(out_dir) != in_dir {
+            cost_model.bend_cost(graph, meeting_index, out_dir, constraints)
+        } else {
+            0
+        };
+
+        Some(
+            forward_g_score
+                .saturating_add(backward_g_score)
+                .saturating_add(bend_cost),
+        )
+    }
+
+    /// Bidirectional A* path finding from `start_index` to `end_index`.
+    ///
+    /// Both searches track every node separately for every direction a path can pass through it in,
+    /// so the path found is as cheap as possible even though the cost of a bend depends on the segment after it.
+    /// The backward search adds the extra cost of a bend to a segment once it knows the segment before it.
+    /// Both searches are guided by the average of the heuristics towards either end, which lets them stop
+    /// as soon as the two cheapest queued states together cannot lead to a cheaper path.
+    /// Only the forward search is reported to `replay`.
+    ///
+    /// Returns whether a path was found, or `None` if the best path found runs through a node twice,
+    /// in which case the search has to be repeated in one direction.
+    #[allow(clippy::too_many_arguments)]
+    fn find_path_bidirectional(
+        &mut self,
+        graph: &GraphData,
+        start_index: NodeIndex,
+        start_dir: StartDirection,
+        end_index: NodeIndex,
+        cost_model: &CostModel,
+        constraints: &NetConstraints,
+        replay: &mut impl ReplayCapture,
+    ) -> Option<bool> {
+        let start = graph.nodes[start_index].position;
+        let end = graph.nodes[end_index].position;
+        let heuristic = |a: Point, b: Point| {
+            cost_model
+                .heuristic(a, b)
+                .saturating_mul(constraints.cost_multiplier()) as i64
+        };
+
+        // Twice the potentials of both searches, shifted by the heuristic of the whole path so they are never negative.
+        let total_heuristic = heuristic(start, end);
+        let forward_key = |g_score: u32, position: Point| {
+            let key = 2 * (g_score as i64) + heuristic(position, end) - heuristic(position, start)
+                + total_heuristic;
+            key.clamp(0, u32::MAX as i64) as u32
+        };
+        let backward_key = |g_score: u32, position: Point| {
+            let key = 2 * (g_score as i64) + heuristic(position, start) - heuristic(position, end)
+                + total_heuristic;
+            key.clamp(0, u32::MAX as i64) as u32
+        };
+
+        self.search.clear(graph.nodes.len(), ARRIVAL_SLOTS);
+        let start_state = self.search.state(start_index, None, 0);
+        self.search.get_mut(start_state).g_score = 0;
+        replay.path_finding_set_g_score(start_index, 0);
+        self.search.push(start_state, forward_key(0, start));
+        replay.path_finding_push_open_queue(start_index, heuristic(start, end) as u32);
+
+        self.backward_search.clear(graph.nodes.len(), ARRIVAL_SLOTS);
+        let end_state = self.backward_search.state(end_index, None, 0);
+        self.backward_search.get_mut(end_state).g_score = 0;
+        self.backward_search.push(end_state, backward_key(0, end));
+
+        // The cost of the best path found so far, and the states of the node its two halves meet at.
+        let mut best: Option<(u32, StateIndex, StateIndex)> = None;
+        fn update_best(
+            best: &mut Option<(u32, StateIndex, StateIndex)>,
+            cost: Option<u32>,
+            forward_state: StateIndex,
+            backward_state: StateIndex,
+        ) {
+            if let Some(cost) = cost {
+                if best.map_or(true, |(best_cost, _, _)| cost < best_cost) {
+                    *best = Some((cost, forward_state, backward_state));
+                }
+            }
+        }
+
+        while let (Some(forward_min), Some(backward_min)) =
+            (self.search.peek(), self.backward_search.peek())
+        {
+            // No path through a state that is still queued on either side can be cheaper than the best one.
+            if let Some((best_cost, _, _)) = best {
+                if (forward_min as i64) + (backward_min as i64)
+                    >= 2 * ((best_cost as i64) + total_heuristic)
+                {
+                    break;
+                }
+            }
+
+            // Always continue on the side with fewer queued states.
+            if self.search.len() <= self.backward_search.len() {
+                let current_state = self.search.pop().unwrap();
+                let current_index = self.search.node(current_state);
+                replay.path_finding_pop_open_queue(current_index);
+
+                if current_index == end_index {
+                    continue;
+                }
+
+                let current_node = &graph.nodes[current_index];
+                let current_g_score = self
+                    .search
+                    .g_score(current_state)
+                    .expect("unreached state in open queue");
+                let arrival_dir = self.search.arrival_dir(current_state);
+                let straight_dir = arrival_dir.or(start_dir.straight_dir());
+
+                for dir in Direction::ALL {
+                    if (Some(dir.opposite()) == straight_dir)
+                        || (arrival_dir.is_none() && !start_dir.allows(dir))
+                    {
+                        continue;
+                    }
+
+                    let neighbor_index = current_node.neighbors[dir];
+                    if (neighbor_index == INVALID_NODE_INDEX)
+                        || ((neighbor_index == end_index)
+                            && !self.arrives_legally(graph, end_index, dir))
+                    {
+                        continue;
+                    }
+
+                    let bend = Some(dir) != straight_dir;
+                    let new_g_score = current_g_score.saturating_add(cost_model.edge_cost(
+                        graph,
+                        current_index,
+                        dir,
+                        bend,
+                        constraints,
+                    ));
+
+                    let neighbor_state = self.search.state(neighbor_index, Some(dir), 0);
+                    let update = match self.search.g_score(neighbor_state) {
+                        Some(g_score) => new_g_score < g_score,
+                        None => true,
+                    };
+
+                    if update {
+                        let neighbor_position = graph.nodes[neighbor_index].position;
+                        let neighbor = self.search.get_mut(neighbor_state);
+                        neighbor.g_score = new_g_score;
+                        replay.path_finding_set_g_score(neighbor_index, new_g_score);
+                        neighbor.predecessor = current_state;
+                        replay.path_finding_set_predecessor(neighbor_index, current_index);
+
+                        self.search
+                            .push(neighbor_state, forward_key(new_g_score, neighbor_position));
+                        replay.path_finding_push_open_queue(
+                            neighbor_index,
+                            new_g_score.saturating_add(heuristic(neighbor_position, end) as u32),
+                        );
+
+                        for backward_state in self.backward_search.states(neighbor_index) {
+                            update_best(
+                                &mut best,
+                                self.meeting_cost(
+                                    graph,
+                                    start_dir,
+                                    neighbor_state,
+                                    backward_state,
+                                    cost_model,
+                                    constraints,
+                                ),
+                                neighbor_state,
+                                backward_state,
+                            );
+                        }
+                    }
+                }
+            } else {
+                let current_state = self.backward_search.pop().unwrap();
+                let current_index = self.backward_search.node(current_state);
+
+                if current_index == start_index {
+                    continue;
+                }
+
+                let current_node = &graph.nodes[current_index];
+                let current_g_score = self
+                    .backward_search
+                    .g_score(current_state)
+                    .expect("unreached state in open queue");
+                let out_dir = self.backward_search.arrival_dir(current_state);
+
+                for dir in Direction::ALL {
+                    if Some(dir) == out_dir {
+                        continue;
+                    }
+
+                    let neighbor_index = current_node.neighbors[dir];
+                    if (neighbor_index == INVALID_NODE_INDEX) || (neighbor_index == end_index) {
+                        continue;
+                    }
+
+                    // The path runs from the neighbor to the current node.
+                    let path_dir = dir.opposite();
+                    if (current_index == end_index)
+                        && !self.arrives_legally(graph, end_index, path_dir)
+                    {
+                        continue;
+                    }
+
+                    // If the path bends at the current node, the segment after it gets more expensive.
+                    let bend_cost = match out_dir {
+                        Some(out_dir) if out_dir != path_dir => {
+                            cost_model.bend_cost(graph, current_index, out_dir, constraints)
+                        }
+                        _ => 0,
+                    };
+
+                    let new_g_score = current_g_score
+                        .saturating_add(cost_model.edge_cost(
+                            graph,
+                            neighbor_index,
+                            path_dir,
+                            false,
+                            constraints,
+                        ))
+                        .saturating_add(bend_cost);
+
+                    let neighbor_state =
+                        self.backward_search
+                            .state(neighbor_index, Some(path_dir), 0);
+                    let update = match self.backward_search.g_score(neighbor_state) {
+                        Some(g_score) => new_g_score < g_score,
+                        None => true,
+                    };
+
+                    if update {
+                        let neighbor_position = graph.nodes[neighbor_index].position;
+                        let neighbor = self.backward_search.get_mut(neighbor_state);
+                        neighbor.g_score = new_g_score;
+                        neighbor.predecessor = current_state;
+                        self.backward_search
+                            .push(neighbor_state, backward_key(new_g_score, neighbor_position));
+
+                        for forward_state in self.search.states(neighbor_index) {
+                            update_best(
+                                &mut best,
+                                self.meeting_cost(
+                                    graph,
+                                    start_dir,
+                                    forward_state,
+                                    neighbor_state,
+                                    cost_model,
+                                    constraints,
+                                ),
+                                forward_state,
+                                neighbor_state,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        let Some((_, meeting_state, backward_state)) = best else {
+            return Some(false);
+        };
+
+        // Join the backward path to the forward one, unless they overlap.
+        let mut backward_path = HashSet::default();
+        let mut current_state = backward_state;
+        while let Some(next_state) = self.backward_search.predecessor(current_state) {
+            backward_path.insert(self.backward_search.node(next_state));
+            current_state = next_state;
+        }
+
+        let mut current_state = meeting_state;
+        while let Some(pred_state) = self.search.predecessor(current_state) {
+            if backward_path.contains(&self.search.node(pred_state)) {
+                return None;
+            }
+
+            current_state = pred_state;
+        }
+
+        let mut current_state = meeting_state;
+        let mut current_backward_state = backward_state;
+        while let Some(next_backward_state) =
+            self.backward_search.predecessor(current_backward_state)
+        {
+            let current_index = self.search.node(current_state);
+            let next_index = self.backward_search.node(next_backward_state);
+            let dir = graph.nodes[current_index]
+                .neighbors
+                .find(next_index)
+                .expect("invalid successor");
+
+            let next_state = self.search.state(next_index, Some(dir), 0);
+            self.search.set_predecessor(next_state, current_state);
+            replay.path_finding_set_predecessor(next_index, current_index);
+
+            current_state = next_state;
+            current_backward_state = next_backward_state;
+        }
+
+        self.assert_data_is_valid(graph);
+        self.build_path(graph, start_state, current_state, replay);
+        Some(true)
+    }
+
+    /// Finds a path from `start` to the closest of `ends` for a fallback wire, if the graph does not contain one.
+    ///
+    /// The search runs on the grid formed by all lines of the graph instead of its edges,
+    /// so passing through bounding boxes and leaving `start` in an illegal direction are possible,
+    /// but so expensive that they only happen if there is no other way.
+    /// Only the lines in a margin around the bounding rectangle of `start` and `ends` are searched,
+    /// and the search gives up if the area or the number of visited points get too large.
+    ///
+    /// The path consists of its start, its corners and its end.
+    pub(crate) fn find_fallback_path<'a>(
+        &'a mut self,
+        graph: &GraphData,
+        start: Point,
+        start_dirs: Directions,
+        ends: &[Segment],
+        cost_model: &CostModel,
+    ) -> Option<&'a [Point]> {
+        let x_coords = graph.x_coords();
+        let y_coords = graph.y_coords();
+        let to_grid = |point: Point| {
+            let x = x_coords.binary_search(&point.x).ok()?;
+            let y = y_coords.binary_search(&point.y).ok()?;
+            Some((x, y))
+        };
+        let to_point = |(x, y): GridPoint| Point {
+            x: x_coords[x],
+            y: y_coords[y],
+        };
+
+        let start_cell = to_grid(start)?;
+
+        // The grid points at the ends of every segment on the grid.
+        let end_cells = || {
+            ends.iter()
+                .filter_map(move |end| Some((to_grid(end.min())?, to_grid(end.max())?)))
+        };
+
+        let mut end_min = (usize::MAX, usize::MAX);
+        let mut end_max = (0, 0);
+        for (min_cell, max_cell) in end_cells() {
+            end_min = (end_min.0.min(min_cell.0), end_min.1.min(min_cell.1));
+            end_max = (end_max.0.max(max_cell.0), end_max.1.max(max_cell.1));
+        }
+
+        if end_min.0 > end_max.0 {
+            return None;
+        }
+
+        let min = (
+            start_cell
+                .0
+                .min(end_min.0)
+                .saturating_sub(FALLBACK_SEARCH_MARGIN),
+            start_cell
+                .1
+                .min(end_min.1)
+                .saturating_sub(FALLBACK_SEARCH_MARGIN),
+        );
+        let max = (
+            (start_cell.0.max(end_max.0) + FALLBACK_SEARCH_MARGIN).min(x_coords.len() - 1),
+            (start_cell.1.max(end_max.1) + FALLBACK_SEARCH_MARGIN).min(y_coords.len() - 1),
+        );
+
+        let width = max.0 - min.0 + 1;
+        let height = max.1 - min.1 + 1;
+        if width.saturating_mul(height) > FALLBACK_SEARCH_AREA_LIMIT {
+            return None;
+        }
+
+        let to_index = |(x, y): GridPoint| (x - min.0) + (y - min.1) * width;
+        let to_cell = |index: usize| (min.0 + index % width, min.1 + index / width);
+
+        self.fallback_cells.clear();
+        self.fallback_cells
+            .resize(width * height, FallbackCell::UNREACHED);
+        // Every grid point a segment passes through is an end, not only its ends.
+        for (min_cell, max_cell) in end_cells() {
+            for y in min_cell.1..=max_cell.1 {
+                for x in min_cell.0..=max_cell.0 {
+                    self.fallback_cells[to_index((x, y))].is_end = true;
+                }
+            }
+        }
+
+        // The distance to the bounding rectangle of the ends is a lower bound for the distance to the closest end.
+        let end_min_point = to_point(end_min);
+        let end_max_point = to_point(end_max);
+        let heuristic = |point: Point| {
+            let closest = Point {
+                x: point.x.clamp(end_min_point.x, end_max_point.x),
+                y: point.y.clamp(end_min_point.y, end_max_point.y),
+            };
+            cost_model.heuristic(point, closest)
+        };
+
+        self.fallback_open_queue.clear();
+
+        let start_index = to_index(start_cell);
+        self.fallback_cells[start_index].g_score = 0;
+        self.fallback_open_queue.push(heuristic(start), start_index);
+
+        let mut end_index = None;
+        let mut visited_count = 0;
+        while let Some((_, index)) = self.fallback_open_queue.pop() {
+            let cell = &mut self.fallback_cells[index];
+            if cell.visited {
+                continue;
+            }
+
+            cell.visited = true;
+            if cell.is_end {
+                end_index = Some(index);
+                break;
+            }
+
+            visited_count += 1;
+            if visited_count > FALLBACK_SEARCH_VISIT_LIMIT {
+                return None;
+            }
+
+            let current = to_cell(index);
+            let current_point = to_point(current);
+            let current_g_score = cell.g_score;
+            let straight_dir = cell.dir;
+
+            for dir in Direction::ALL {
+                if Some(dir.opposite()) == straight_dir {
+                    // The path came from here.
+                    continue;
+                }
+
+                let neighbor = match dir {
+                    Direction::PosX if current.0 < max.0 => (current.0 + 1, current.1),
+                    Direction::NegX if current.0 > min.0 => (current.0 - 1, current.1),
+                    Direction::PosY if current.1 < max.1 => (current.0, current.1 + 1),
+                    Direction::NegY if current.1 > min.1 => (current.0, current.1 - 1),
+                    _ => continue,
+                };
+
+                let neighbor_index = to_index(neighbor);
+                if self.fallback_cells[neighbor_index].visited {
+                    continue;
+                }
+
+                let neighbor_point = to_point(neighbor);
+                let length = current_point.manhatten_distance_to(neighbor_point);
+
+                let mut cost = length.saturating_mul(cost_model.length_weight);
+
+                if straight_dir.is_some_and(|straight_dir| straight_dir != dir) {
+                    cost = cost
+                        .saturating_add(length.saturating_mul(cost_model.bend_length_weight))
+                        .saturating_add(cost_model.bend_penalty);
+                }
+
+                let leaves_start_illegally =
+                    (current == start_cell) && !start_dirs.contains(dir.into());
+                if leaves_start_illegally
+                    || graph.segment_crosses_bounding_box(current_point, neighbor_point)
+                {
+                    cost = cost.saturating_add(length.saturating_mul(FALLBACK_OBSTACLE_WEIGHT));
+                }
+
+                cost = cost
+                    .saturating_mul(graph.segment_cost_multiplier(current_point, neighbor_point));
+
+                let new_g_score = current_g_score.saturating_add(cost);
+                let neighbor_cell = &mut self.fallback_cells[neighbor_index];
+                if new_g_score < neighbor_cell.g_score {
+                    neighbor_cell.g_score = new_g_score;
+                    neighbor_cell.dir = Some(dir);
+
+                    let new_f_score = new_g_score.saturating_add(heuristic(neighbor_point));
+                    self.fallback_open_queue.push(new_f_score, neighbor_index);
+                }
+            }
+        }
+
+        // Walk back to the start, only keeping the points where the path changes direction.
+        self.fallback_path.clear();
+
+        let mut current = to_cell(end_index?);
+        self.fallback_path.push(to_point(current));
+
+        let mut prev_dir = None;
+        while let Some(dir) = self.fallback_cells[to_index(current)].dir {
+            if prev_dir.is_some_and(|prev_dir| prev_dir != dir) {
+                self.fallback_path.push(to_point(current));
+            }
+
+            prev_dir = Some(dir);
+            current = match dir {
+                Direction::PosX => (current.0 - 1, current.1),
+                Direction::NegX => (current.0 + 1, current.1),
+                Direction::PosY => (current.0, current.1 - 1),
+                Direction::NegY => (current.0, current.1 + 1),
+            };
+        }
+
+        self.fallback_path.push(to_point(current));
+        self.fallback_path.reverse();
+
+        Some(&self.fallback_path)
+    }
 }
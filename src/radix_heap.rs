@@ -0,0 +1,99 @@
+/// The number of buckets needed for `u32` keys: one for keys equal to the last popped key,
+/// and one for every bit position in which a key can first differ from it.
+const BUCKET_COUNT: usize = (u32::BITS as usize) + 1;
+
+/// A min-heap for `u32` keys that never decrease below the last popped key, as is the case in A* with a consistent heuristic.
+///
+/// Items are kept in buckets by the highest bit in which their key differs from the last popped key,
+/// so pushing is constant time and every item is moved to a lower bucket at most 32 times before it is popped.
+/// Items with equal keys are popped in the reverse order they were pushed in.
+pub(crate) struct RadixHeap<T> {
+    last_key: u32,
+    buckets: [Vec<(u32, T)>; BUCKET_COUNT],
+}
+
+impl<T> Default for RadixHeap<T> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            last_key: 0,
+            buckets: std::array::from_fn(|_| Vec::new()),
+        }
+    }
+}
+
+impl<T> RadixHeap<T> {
+    #[inline]
+    fn bucket_index(&self, key: u32) -> usize {
+        (u32::BITS - (key ^ self.last_key).leading_zeros()) as usize
+    }
+
+    /// Removes all items, keeping the allocated memory.
+    pub(crate) fn clear(&mut self) {
+        for bucket in &mut self.buckets {
+            bucket.clear();
+        }
+
+        self.last_key = 0;
+    }
+
+    /// Adds an item. Its key must not be less than the key of the last popped item.
+    #[inline]
+    pub(crate) fn push(&mut self, key: u32, item: T) {
+        debug_assert!(
+            key >= self.last_key,
+            "key decreased below the last popped key"
+        );
+
+        // Should the key decrease anyway, the item is popped next instead of breaking the heap.
+        let bucket_index = if key >= self.last_key {
+            self.bucket_index(key)
+        } else {
+            0
+        };
+
+        self.buckets[bucket_index].push((key, item));
+    }
+
+    /// Moves the items with the smallest key into the first bucket, if it is empty.
+    fn refill(&mut self) {
+        if !self.buckets[0].is_empty() {
+            return;
+        }
+
+        let Some(bucket_index) = self.buckets.iter().position(|bucket| !bucket.is_empty()) else {
+            return;
+        };
+
+        let mut bucket = std::mem::take(&mut self.buckets[bucket_index]);
+        self.last_key = bucket
+            .iter()
+            .map(|&(key, _)| key)
+            .min()
+            .expect("empty bucket");
+
+        // Relative to the new smallest key, every item of the bucket belongs into a lower one.
+        for (key, item) in bucket.drain(..) {
+            let new_bucket_index = self.bucket_index(key);
+            debug_assert!(new_bucket_index < bucket_index);
+            self.buckets[new_bucket_index].push((key, item));
+        }
+
+        // Keep the allocation for the next items ending up in this bucket.
+        self.buckets[bucket_index] = bucket;
+    }
+
+    /// The smallest key in the heap.
+    #[inline]
+    pub(crate) fn peek(&mut self) -> Option<(u32, &T)> {
+        self.refill();
+        self.buckets[0].last().map(|(key, item)| (*key, item))
+    }
+
+    /// Removes the item with the smallest key.
+    #[inline]
+    pub(crate) fn pop(&mut self) -> Option<(u32, T)> {
+        self.refill();
+        self.buckets[0].pop()
+    }
+}
@@ -1,4 +1,7 @@
+use crate::congestion::Congestion;
 use crate::graph::{NodeIndex, INVALID_NODE_INDEX};
+use crate::path_finding::{Segment, StartDirection, Target};
+use crate::steiner::SteinerPoints;
 use crate::*;
 use serde::{Deserialize, Serialize};
 use std::borrow::{Borrow, Cow};
@@ -22,6 +25,24 @@ impl<'a, T> Array<'a, T> {
         self.len = new_len;
         Ok(())
     }
+
+    #[inline]
+    pub(crate) fn extend_from_slice(&mut self, vals: &[T]) -> Result<(), ()>
+    where
+        T: Copy,
+    {
+        let new_len = self.len.checked_add(vals.len()).ok_or(())?;
+        if self.data.len() < new_len {
+            return Err(());
+        }
+
+        for (dst, &val) in self.data[self.len..new_len].iter_mut().zip(vals) {
+            dst.write(val);
+        }
+
+        self.len = new_len;
+        Ok(())
+    }
 }
 
 impl<'a, T> From<&'a mut [MaybeUninit<T>]> for Array<'a, T> {
@@ -70,7 +91,7 @@ impl From<Point> for Vertex {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct WireView(u16);
 
@@ -110,7 +131,7 @@ impl WireView {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub struct NetView {
     /// The offset into `wire_views` this nets wires start at.
@@ -150,6 +171,52 @@ where
     max_pair.ok_or(())
 }
 
+fn pick_closest_pair<'a, Iter>(mut endpoints: Iter) -> Result<(Iter::Item, Iter::Item), ()>
+where
+    Iter: Clone + Iterator<Item: Borrow<Endpoint<'a>>>,
+{
+    let mut min_dist = u32::MAX;
+    let mut min_pair: Option<(Iter::Item, Iter::Item)> = None;
+
+    while let Some(a) = endpoints.next() {
+        let a_pos = a.borrow().position;
+
+        let mut min_b: Option<Iter::Item> = None;
+        for b in endpoints.clone() {
+            let b_pos = b.borrow().position;
+
+            let dist = a_pos.manhatten_distance_to(b_pos);
+            if dist < min_dist {
+                min_dist = dist;
+                min_b = Some(b);
+            }
+        }
+
+        if let Some(min_b) = min_b {
+            min_pair = Some((a, min_b));
+        }
+    }
+
+    min_pair.ok_or(())
+}
+
+/// The index of the endpoint closest to any of `ends`.
+fn pick_closest_endpoint<'a>(endpoints: &[impl Borrow<Endpoint<'a>>], ends: &[Segment]) -> usize {
+    endpoints
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, endpoint)| {
+            let endpoint: &Endpoint = (*endpoint).borrow();
+            let position = endpoint.position;
+            ends.iter()
+                .map(|end| end.distance_to(position))
+                .min()
+                .unwrap_or(u32::MAX)
+        })
+        .map(|(index, _)| index)
+        .expect("no endpoints left")
+}
+
 pub(crate) struct CenteringCandidate {
     node_a: NodeIndex,
     node_b: NodeIndex,
@@ -160,13 +227,15 @@ fn push_vertices(
     path: &Path,
     graph: &GraphData,
     vertices: &mut Array<Vertex>,
-    ends: &mut Vec<Point>,
+    ends: &mut Vec<Segment>,
     centering_candidates: &mut Vec<CenteringCandidate>,
+    congestion: Option<&mut Congestion>,
     replay: &mut impl ReplayCapture,
 ) -> Result<usize, ()> {
-    ends.reserve(path.nodes().len());
-    for node in path.nodes() {
-        ends.push(node.position);
+    if let Some(congestion) = congestion {
+        congestion
+            .occupancy
+            .insert_path(graph, path, congestion.net);
     }
 
     let mut path_len = 0usize;
@@ -191,6 +260,8 @@ fn push_vertices(
             vertices.push(prev_node.position.into())?;
             replay.routing_push_vertex(prev_node.position.into());
             path_len += 1;
+
+            ends.push(Segment::new(prev_node.position, node.position));
         }
 
         prev_prev_dir = prev_node.map(|prev_node| prev_node.bend_direction);
@@ -201,65 +272,87 @@ fn push_vertices(
         vertices.push(prev_node.position.into())?;
         replay.routing_push_vertex(prev_node.position.into());
         path_len += 1;
+
+        if path_len == 1 {
+            ends.push(prev_node.position.into());
+        }
     }
 
     Ok(path_len)
 }
 
-fn find_fallback_junction(endpoint: Point, ends: &[Point]) -> Point {
-    let mut min_dist = endpoint.manhatten_distance_to(ends[0]);
+/// The point on any of `ends` closest to `endpoint`.
+fn find_fallback_junction(endpoint: Point, ends: &[Segment]) -> Point {
+    let mut min_dist = ends[0].distance_to(endpoint);
     let mut min_end = ends[0];
 
     for &end in &ends[1..] {
-        let dist = endpoint.manhatten_distance_to(end);
+        let dist = end.distance_to(endpoint);
         if dist < min_dist {
             min_dist = dist;
             min_end = end;
         }
     }
 
-    min_end
+    min_end.closest_point(endpoint)
 }
 
+/// The direction of the axis aligned segment from `a` to `b`.
+fn segment_direction(a: Point, b: Point) -> Direction {
+    if b.x > a.x {
+        Direction::PosX
+    } else if b.x < a.x {
+        Direction::NegX
+    } else if b.y < a.y {
+        Direction::NegY
+    } else {
+        Direction::PosY
+    }
+}
+
+/// Pushes a wire from `start` to the closest of `ends` that does not follow the edges of the graph.
+///
+/// Returns the length of the wire, the end it leads to and the direction it arrives there from.
+#[allow(clippy::too_many_arguments)]
 fn push_fallback_vertices(
+    graph: &GraphData,
+    path_finder: &mut PathFinder,
     start: Point,
-    end: Point,
+    ends: &[Segment],
     start_dirs: Directions,
+    cost_model: &CostModel,
     vertices: &mut Array<Vertex>,
     replay: &mut impl ReplayCapture,
-) -> Result<(usize, Direction), ()> {
+) -> Result<(usize, Point, Direction), ()> {
+    // Prefer a detour around obstacles, only cut straight through them if the points are not on the grid of the graph.
+    if let Some(path) = path_finder.find_fallback_path(graph, start, start_dirs, ends, cost_model) {
+        if let [.., prev_last, last] = *path {
+            for &point in path {
+                vertices.push(point.into())?;
+                replay.routing_push_vertex(point.into());
+            }
+
+            return Ok((path.len(), last, segment_direction(prev_last, last)));
+        }
+    }
+
+    let end = find_fallback_junction(start, ends);
     let mut path_len = 0usize;
 
     vertices.push(start.into())?;
     replay.routing_push_vertex(start.into());
     path_len += 1;
 
-    let (middle, dir) = if start_dirs.intersects(Directions::X) {
-        let middle = Point {
+    let middle = if start_dirs.intersects(Directions::X) {
+        Point {
             x: end.x,
             y: start.y,
-        };
-
-        let dir = if end.y < start.y {
-            Direction::NegY
-        } else {
-            Direction::PosY
-        };
-
-        (middle, dir)
+        }
     } else {
-        let middle = Point {
-            x: end.x,
-            y: start.y,
-        };
-
-        let dir = if end.x < start.x {
-            Direction::NegX
-        } else {
-            Direction::PosX
-        };
-
-        (middle, dir)
+        Point {
+            x: start.x,
+            y: end.y,
+        }
     };
 
     if (middle != start) && (middle != end) {
@@ -272,7 +365,48 @@ fn push_fallback_vertices(
     replay.routing_push_vertex(end.into());
     path_len += 1;
 
-    Ok((path_len, dir))
+    let dir = if middle != end {
+        segment_direction(middle, end)
+    } else {
+        segment_direction(start, end)
+    };
+
+    Ok((path_len, end, dir))
+}
+
+/// Marks the edges of the graph a fallback wire runs along as used by the net that is being routed.
+fn occupy_fallback_wire(
+    graph: &GraphData,
+    fallback_vertices: &[Vertex],
+    congestion: Option<&mut Congestion>,
+) {
+    if let Some(congestion) = congestion {
+        let points: Vec<_> = fallback_vertices
+            .iter()
+            .map(|vertex| Point {
+                x: vertex.x as i32,
+                y: vertex.y as i32,
+            })
+            .collect();
+
+        congestion
+            .occupancy
+            .insert_segments(graph, &points, congestion.net);
+    }
+}
+
+/// Adds the segments of a fallback wire to `ends`, so other wires can connect to it anywhere along them.
+fn push_fallback_segments(fallback_vertices: &[Vertex], ends: &mut Vec<Segment>) {
+    let to_point = |vertex: &Vertex| Point {
+        x: vertex.x as i32,
+        y: vertex.y as i32,
+    };
+
+    ends.extend(
+        fallback_vertices
+            .windows(2)
+            .map(|segment| Segment::new(to_point(&segment[0]), to_point(&segment[1]))),
+    );
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -280,95 +414,334 @@ pub enum RoutingError {
     NotEnoughEndpoints,
     VertexBufferOverflow,
     WireViewBufferOverflow,
+    /// An endpoint or waypoint of the net does not lie on a node of the graph.
+    ///
+    /// Only returned when routing a single net, functions routing multiple nets report it in the status of the net instead.
     InvalidPoint,
+    /// Consecutive wire segments of the same wire did not meet.
+    DisconnectedWire,
+    /// The constraints of the net are out of range.
+    InvalidConstraints,
+}
+
+/// How completely a net was routed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum NetStatus {
+    /// All endpoints and waypoints were connected through the graph.
+    #[default]
+    Routed = 0,
+    /// All endpoints were connected through the graph, but some waypoints could not be reached and were skipped.
+    Partial = 1,
+    /// At least one wire could not be routed through the graph and was replaced by a fallback wire.
+    Fallback = 2,
+    /// The net could not be routed at all and has no wires.
+    Failed = 3,
+}
+
+/// The kind of problem a [`Diagnostic`] describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum DiagnosticKind {
+    /// The waypoint at `end` could not be reached from the wire starting at `start`, and was skipped.
+    UnreachableWaypoint = 0,
+    /// No path between `start` and `end` was found, so a fallback wire was generated that may cross obstacles.
+    FallbackWire = 1,
+    /// The endpoint at `start`, or one of its waypoints at `end`, does not lie on a node of the graph.
+    /// `start` and `end` are the same if the endpoint itself is invalid. The net is not routed.
+    InvalidEndpoint = 2,
 }
 
+/// A problem that occurred while routing a net.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub start: Point,
+    pub end: Point,
+}
+
+/// The status of a routed net and the problems that occurred while routing it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct NetDiagnostics {
+    pub(crate) status: NetStatus,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+impl NetDiagnostics {
+    #[inline]
+    fn clear(&mut self) {
+        self.status = NetStatus::Routed;
+        self.diagnostics.clear();
+    }
+
+    fn push(&mut self, kind: DiagnosticKind, start: Point, end: Point) {
+        let status = match kind {
+            DiagnosticKind::UnreachableWaypoint => NetStatus::Partial,
+            DiagnosticKind::FallbackWire => NetStatus::Fallback,
+            DiagnosticKind::InvalidEndpoint => NetStatus::Failed,
+        };
+
+        self.status = self.status.max(status);
+        self.diagnostics.push(Diagnostic { kind, start, end });
+    }
+
+    fn push_unreachable_waypoints(
+        &mut self,
+        start: Point,
+        path_finder: &PathFinder,
+        steiner_points: &SteinerPoints,
+    ) {
+        // Steiner points are not requested by the caller, so missing one is not worth reporting.
+        for &waypoint in path_finder.unreachable_ends() {
+            if !steiner_points.contains(waypoint) {
+                self.push(DiagnosticKind::UnreachableWaypoint, start, waypoint);
+            }
+        }
+    }
+}
+
+/// How completely every net was routed by one of the functions connecting multiple nets into shared buffers,
+/// and the problems that occurred while routing them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RoutingReport {
+    nets: Vec<NetDiagnostics>,
+}
+
+impl RoutingReport {
+    /// Creates an empty report.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of nets in the report.
+    #[inline]
+    pub fn net_count(&self) -> usize {
+        self.nets.len()
+    }
+
+    /// How completely a net was routed.
+    #[inline]
+    pub fn status(&self, net: usize) -> NetStatus {
+        self.nets[net].status
+    }
+
+    /// The problems that occurred while routing a net.
+    #[inline]
+    pub fn diagnostics(&self, net: usize) -> &[Diagnostic] {
+        &self.nets[net].diagnostics
+    }
+
+    /// Replaces the nets in the report.
+    pub(crate) fn set_nets(&mut self, nets: impl IntoIterator<Item = NetDiagnostics>) {
+        self.nets.clear();
+        self.nets.extend(nets);
+    }
+}
+
+/// The waypoints of an endpoint followed by the Steiner points its wire passes through.
+fn waypoint_targets<'a>(
+    endpoint: &'a Endpoint,
+    steiner_points: &'a SteinerPoints,
+) -> impl DoubleEndedIterator<Item = Target> + 'a {
+    endpoint.waypoint_targets().chain(
+        steiner_points
+            .of(endpoint.position)
+            .iter()
+            .map(|&point| Target::from(point)),
+    )
+}
+
+/// The waypoints of the root wire, in the order they are passed to the path finder.
+fn root_waypoints(
+    root_start: &Endpoint,
+    root_end: &Endpoint,
+    order: WaypointOrder,
+    steiner_points: &SteinerPoints,
+) -> Vec<Target> {
+    let start_waypoints = waypoint_targets(root_start, steiner_points);
+    // The root wire travels towards its end, so it passes through the waypoints of the end backwards.
+    let end_waypoints = waypoint_targets(root_end, steiner_points).map(|target| Target {
+        direction: target.direction.map(Direction::opposite),
+        ..target
+    });
+
+    match order {
+        WaypointOrder::Nearest => start_waypoints.chain(end_waypoints).collect(),
+        WaypointOrder::Given => start_waypoints.chain(end_waypoints.rev()).collect(),
+        WaypointOrder::Optimized => {
+            let mut waypoints: Vec<_> = start_waypoints.chain(end_waypoints).collect();
+            waypoints::optimize_order(root_start.position, &mut waypoints, Some(root_end.position));
+            waypoints
+        }
+    }
+}
+
+/// The waypoints of a branch wire, in the order they are passed to the path finder.
+fn branch_waypoints(
+    endpoint: &Endpoint,
+    order: WaypointOrder,
+    steiner_points: &SteinerPoints,
+) -> Vec<Target> {
+    let mut waypoints: Vec<_> = waypoint_targets(endpoint, steiner_points).collect();
+    if order == WaypointOrder::Optimized {
+        waypoints::optimize_order(endpoint.position, &mut waypoints, None);
+    }
+
+    waypoints
+}
+
+/// How a wire has to continue after arriving at `last_waypoint` in direction `last_waypoint_dir`.
+fn continue_after(
+    waypoints: &[Target],
+    last_waypoint: Point,
+    last_waypoint_dir: Option<Direction>,
+) -> StartDirection {
+    let required_dir = waypoints
+        .iter()
+        .find(|waypoint| waypoint.position == last_waypoint)
+        .and_then(|waypoint| waypoint.direction);
+
+    match (last_waypoint_dir, required_dir) {
+        (Some(_), Some(required_dir)) => StartDirection::Exactly(required_dir),
+        (last_waypoint_dir, _) => last_waypoint_dir.into(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn route_root_wire<'a>(
     graph: &GraphData,
     path_finder: &mut PathFinder,
     root_start: &Endpoint<'a>,
     root_end: &Endpoint<'a>,
+    steiner_points: &SteinerPoints,
     vertices: &mut Array<Vertex>,
     wire_views: &mut Array<WireView>,
-    ends: &mut Vec<Point>,
+    ends: &mut Vec<Segment>,
     centering_candidates: &mut Vec<CenteringCandidate>,
+    diagnostics: &mut NetDiagnostics,
+    cost_model: &CostModel,
+    constraints: &NetConstraints,
+    mut congestion: Option<&mut Congestion>,
     replay: &mut impl ReplayCapture,
 ) -> Result<u32, RoutingError> {
     replay.routing_begin_root_wire(root_start.position, root_end.position);
 
     let mut wire_count = 0;
 
-    let waypoints = root_start
-        .waypoints
-        .iter()
-        .copied()
-        .chain(root_end.waypoints.iter().copied());
-
-    let (last_waypoint, last_waypoint_dir) =
-        match path_finder.find_path(graph, root_start.position, None, waypoints, true, replay) {
-            PathFindResult::Found(path) => {
-                if path.nodes().len() < 2 {
-                    (root_start.position, None)
-                } else {
-                    let path_len =
-                        push_vertices(path, graph, vertices, ends, centering_candidates, replay)
-                            .map_err(|_| RoutingError::VertexBufferOverflow)?;
-
-                    assert!(path_len >= 2);
+    let waypoints = root_waypoints(
+        root_start,
+        root_end,
+        constraints.waypoint_order,
+        steiner_points,
+    );
 
-                    wire_views
-                        .push(WireView::new(path_len, false, true).expect("path too long"))
-                        .map_err(|_| RoutingError::WireViewBufferOverflow)?;
-                    replay.routing_end_wire_segment(false);
-
-                    let (last, head) = path.nodes().split_last().unwrap();
-                    let prev_last = head.last().unwrap();
-
-                    wire_count += 1;
-                    (last.position, prev_last.bend_direction)
-                }
-            }
-            PathFindResult::NotFound => (root_start.position, None),
-            PathFindResult::InvalidStartPoint | PathFindResult::InvalidEndPoint => {
-                return Err(RoutingError::InvalidPoint);
+    let (last_waypoint, last_waypoint_dir) = match path_finder.find_path(
+        graph,
+        root_start.position,
+        StartDirection::Any,
+        waypoints.iter().copied(),
+        true,
+        cost_model,
+        &SearchOptions::default(),
+        constraints,
+        congestion.as_deref(),
+        replay,
+    ) {
+        PathFindResult::Found(path) => {
+            if path.nodes().len() < 2 {
+                (root_start.position, None)
+            } else {
+                let path_len = push_vertices(
+                    path,
+                    graph,
+                    vertices,
+                    ends,
+                    centering_candidates,
+                    congestion.as_deref_mut(),
+                    replay,
+                )
+                .map_err(|_| RoutingError::VertexBufferOverflow)?;
+
+                assert!(path_len >= 2);
+
+                wire_views
+                    .push(WireView::new(path_len, false, true).expect("path too long"))
+                    .map_err(|_| RoutingError::WireViewBufferOverflow)?;
+                replay.routing_end_wire_segment(false);
+
+                let (last, head) = path.nodes().split_last().unwrap();
+                let prev_last = head.last().unwrap();
+
+                wire_count += 1;
+                (last.position, prev_last.bend_direction)
             }
-        };
+        }
+        PathFindResult::NotFound => (root_start.position, None),
+        PathFindResult::InvalidStartPoint | PathFindResult::InvalidEndPoint => {
+            unreachable!("endpoints and waypoints are checked before routing")
+        }
+    };
+
+    diagnostics.push_unreachable_waypoints(root_start.position, path_finder, steiner_points);
 
     let path_len = match path_finder.find_path(
         graph,
         last_waypoint,
-        last_waypoint_dir,
+        continue_after(&waypoints, last_waypoint, last_waypoint_dir),
         [root_end.position],
         false,
+        cost_model,
+        &SearchOptions::default(),
+        constraints,
+        congestion.as_deref(),
         replay,
     ) {
-        PathFindResult::Found(path) => {
-            push_vertices(path, graph, vertices, ends, centering_candidates, replay)
-                .map_err(|_| RoutingError::VertexBufferOverflow)?
-        }
+        PathFindResult::Found(path) => push_vertices(
+            path,
+            graph,
+            vertices,
+            ends,
+            centering_candidates,
+            congestion,
+            replay,
+        )
+        .map_err(|_| RoutingError::VertexBufferOverflow)?,
         PathFindResult::NotFound => {
-            println!(
-                "no path between ({}, {}) and ({}, {}) found, generating fallback wire",
-                last_waypoint.x, last_waypoint.y, root_end.position.x, root_end.position.y
+            diagnostics.push(
+                DiagnosticKind::FallbackWire,
+                last_waypoint,
+                root_end.position,
             );
 
             let root_end_node = &graph.nodes[graph.find_node(root_end.position).unwrap()];
-            let (path_len, _) = push_fallback_vertices(
+            let (path_len, _, _) = push_fallback_vertices(
+                graph,
+                path_finder,
                 root_end.position,
-                last_waypoint,
+                &[last_waypoint.into()],
                 root_end_node.legal_directions,
+                cost_model,
                 vertices,
                 replay,
             )
             .map_err(|_| RoutingError::VertexBufferOverflow)?;
 
             assert!(path_len >= 2);
-            ends.push(root_end.position);
+
+            // The fallback wire starts at the end of the root wire, turn it around so the root wire is continuous.
+            let vertex_count = vertices.len;
+            vertices[(vertex_count - path_len)..].reverse();
+
+            let fallback_vertices = &vertices[(vertex_count - path_len)..];
+            occupy_fallback_wire(graph, fallback_vertices, congestion);
+            push_fallback_segments(fallback_vertices, ends);
 
             path_len
         }
         PathFindResult::InvalidStartPoint | PathFindResult::InvalidEndPoint => {
-            return Err(RoutingError::InvalidPoint);
+            unreachable!("endpoints and waypoints are checked before routing")
         }
     };
 
@@ -393,7 +766,7 @@ pub(crate) enum JunctionKind {
         inbound_dir: [Direction; 2],
     },
     /// The junction is in a state the centering algorithm cannot deal with, so ignore it.
-    Degenerate,
+    Degenerate { vertex_index: Vec<usize> },
 }
 
 impl JunctionKind {
@@ -428,7 +801,16 @@ impl JunctionKind {
                 Some((vertex_index[0], inbound_dir[0])),
                 Some((vertex_index[1], inbound_dir[1])),
             ]),
-            JunctionKind::Degenerate => Iter::Degenerate,
+            JunctionKind::Degenerate { .. } => Iter::Degenerate,
+        }
+    }
+
+    /// The indices of the last vertices of all wires ending in the junction.
+    pub(crate) fn wire_ends(&self) -> &[usize] {
+        match self {
+            JunctionKind::Single { vertex_index, .. } => std::slice::from_ref(vertex_index),
+            JunctionKind::Double { vertex_index, .. } => vertex_index,
+            JunctionKind::Degenerate { vertex_index } => vertex_index,
         }
     }
 }
@@ -463,153 +845,217 @@ fn insert_junction(
                         inbound_dir: [prev_inbound_dir, inbound_dir],
                     };
                 }
-                JunctionKind::Double { .. } => {
+                JunctionKind::Double {
+                    vertex_index: prev_vertex_index,
+                    ..
+                } => {
                     // With normal routing this is impossible, because it requires routing
                     // on top of an existing wire that should have been connected to instead.
                     // However if a wire cannot be routed it ignores geometry so there is
                     // a small chance for it to happen.
-                    *kind = JunctionKind::Degenerate;
+                    *kind = JunctionKind::Degenerate {
+                        vertex_index: vec![
+                            prev_vertex_index[0],
+                            prev_vertex_index[1],
+                            vertex_index,
+                        ],
+                    };
                 }
-                JunctionKind::Degenerate => (),
+                JunctionKind::Degenerate {
+                    vertex_index: ref mut prev_vertex_index,
+                } => prev_vertex_index.push(vertex_index),
             }
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn route_branch_wires<'a>(
     graph: &GraphData,
     path_finder: &mut PathFinder,
     endpoints: impl Iterator<Item: Borrow<Endpoint<'a>>>,
     root_start: &Endpoint<'a>,
     root_end: &Endpoint<'a>,
+    steiner_points: &SteinerPoints,
     vertices: &mut Array<Vertex>,
     wire_views: &mut Array<WireView>,
-    ends: &mut Vec<Point>,
+    ends: &mut Vec<Segment>,
     centering_candidates: &mut Vec<CenteringCandidate>,
     junctions: &mut JunctionMap,
+    diagnostics: &mut NetDiagnostics,
+    topology: NetTopology,
+    cost_model: &CostModel,
+    constraints: &NetConstraints,
+    mut congestion: Option<&mut Congestion>,
     replay: &mut impl ReplayCapture,
 ) -> Result<u32, RoutingError> {
     let mut wire_count = 0;
 
-    for endpoint in endpoints {
-        let endpoint = endpoint.borrow();
-
-        if (endpoint.position != root_start.position) && (endpoint.position != root_end.position) {
-            replay.routing_begin_branch_wire(endpoint.position);
-
-            let end_count = ends.len();
+    let mut remaining: Vec<_> = endpoints
+        .filter(|endpoint| {
+            let position = endpoint.borrow().position;
+            (position != root_start.position) && (position != root_end.position)
+        })
+        .collect();
+
+    // With Steiner points, the endpoints are connected outwards along the tree they were found for,
+    // so the Steiner point a wire joins at has always been routed already.
+    let follow_tree = !steiner_points.is_empty();
+    if follow_tree {
+        remaining.sort_by_key(|endpoint| steiner_points.rank(endpoint.borrow().position));
+    }
 
-            let (last_waypoint, last_waypoint_dir) = match path_finder.find_path(
-                graph,
-                endpoint.position,
-                None,
-                endpoint.waypoints.iter().copied(),
-                true,
-                replay,
-            ) {
-                PathFindResult::Found(path) => {
-                    if path.nodes().len() < 2 {
-                        (endpoint.position, None)
-                    } else {
-                        let path_len = push_vertices(
-                            path,
-                            graph,
-                            vertices,
-                            ends,
-                            centering_candidates,
-                            replay,
-                        )
-                        .map_err(|_| RoutingError::VertexBufferOverflow)?;
-
-                        assert!(path_len >= 2);
-
-                        wire_views
-                            .push(WireView::new(path_len, false, false).expect("path too long"))
-                            .map_err(|_| RoutingError::WireViewBufferOverflow)?;
-                        replay.routing_end_wire_segment(false);
-
-                        let (last, head) = path.nodes().split_last().unwrap();
-                        let prev_last = head.last().unwrap();
-
-                        wire_count += 1;
-                        (last.position, prev_last.bend_direction)
-                    }
-                }
-                PathFindResult::NotFound => (endpoint.position, None),
-                PathFindResult::InvalidStartPoint | PathFindResult::InvalidEndPoint => {
-                    return Err(RoutingError::InvalidPoint);
-                }
-            };
+    while !remaining.is_empty() {
+        // For a Steiner tree without Steiner points, always connect the endpoint closest to the already routed wires next,
+        // so the junctions it creates act as Steiner points for the endpoints after it.
+        let next = match topology {
+            NetTopology::Steiner if !follow_tree => pick_closest_endpoint(&remaining, ends),
+            _ => 0,
+        };
 
-            let path_len = match path_finder.find_path(
-                graph,
-                last_waypoint,
-                last_waypoint_dir,
-                ends[..end_count].iter().copied(),
-                false,
-                replay,
-            ) {
-                PathFindResult::Found(path) => {
-                    if path.nodes().len() < 2 {
-                        if last_waypoint_dir.is_some() {
-                            wire_views.last_mut().unwrap().set_ends_in_junction();
-                        }
+        let endpoint = remaining.remove(next);
+        let endpoint = endpoint.borrow();
 
-                        continue;
-                    }
+        replay.routing_begin_branch_wire(endpoint.position);
 
-                    let path_len =
-                        push_vertices(path, graph, vertices, ends, centering_candidates, replay)
-                            .map_err(|_| RoutingError::VertexBufferOverflow)?;
+        let end_count = ends.len();
 
-                    assert!(path_len >= 2);
+        let waypoints = branch_waypoints(endpoint, constraints.waypoint_order, steiner_points);
 
-                    let (last, head) = path.nodes().split_last().unwrap();
-                    let prev_last = head.last().unwrap();
-                    insert_junction(
-                        junctions,
-                        last.position,
-                        vertices.len - 1,
-                        prev_last.bend_direction.unwrap(),
-                    );
-
-                    path_len
-                }
-                PathFindResult::NotFound => {
-                    println!(
-                        "no path between ({}, {}) and root net found, generating fallback wire",
-                        last_waypoint.x, last_waypoint.y
-                    );
-
-                    let junction_pos = find_fallback_junction(endpoint.position, ends);
-                    let endpoint_node = &graph.nodes[graph.find_node(endpoint.position).unwrap()];
-                    let (path_len, junction_dir) = push_fallback_vertices(
-                        endpoint.position,
-                        junction_pos,
-                        endpoint_node.legal_directions,
+        let (last_waypoint, last_waypoint_dir) = match path_finder.find_path(
+            graph,
+            endpoint.position,
+            StartDirection::Any,
+            waypoints.iter().copied(),
+            true,
+            cost_model,
+            &SearchOptions::default(),
+            constraints,
+            congestion.as_deref(),
+            replay,
+        ) {
+            PathFindResult::Found(path) => {
+                if path.nodes().len() < 2 {
+                    (endpoint.position, None)
+                } else {
+                    let path_len = push_vertices(
+                        path,
+                        graph,
                         vertices,
+                        ends,
+                        centering_candidates,
+                        congestion.as_deref_mut(),
                         replay,
                     )
                     .map_err(|_| RoutingError::VertexBufferOverflow)?;
 
                     assert!(path_len >= 2);
-                    insert_junction(junctions, junction_pos, vertices.len - 1, junction_dir);
-                    ends.push(endpoint.position);
 
-                    path_len
+                    wire_views
+                        .push(WireView::new(path_len, false, false).expect("path too long"))
+                        .map_err(|_| RoutingError::WireViewBufferOverflow)?;
+                    replay.routing_end_wire_segment(false);
+
+                    let (last, head) = path.nodes().split_last().unwrap();
+                    let prev_last = head.last().unwrap();
+
+                    wire_count += 1;
+                    (last.position, prev_last.bend_direction)
                 }
-                PathFindResult::InvalidStartPoint | PathFindResult::InvalidEndPoint => {
-                    return Err(RoutingError::InvalidPoint);
+            }
+            PathFindResult::NotFound => (endpoint.position, None),
+            PathFindResult::InvalidStartPoint | PathFindResult::InvalidEndPoint => {
+                unreachable!("endpoints and waypoints are checked before routing")
+            }
+        };
+
+        diagnostics.push_unreachable_waypoints(endpoint.position, path_finder, steiner_points);
+
+        let path_len = match path_finder.find_path_to_segments(
+            graph,
+            last_waypoint,
+            continue_after(&waypoints, last_waypoint, last_waypoint_dir),
+            &ends[..end_count],
+            cost_model,
+            constraints,
+            congestion.as_deref(),
+            replay,
+        ) {
+            PathFindResult::Found(path) => {
+                if path.nodes().len() < 2 {
+                    // The last waypoint already lies on the wires routed so far.
+                    if let Some(last_waypoint_dir) = last_waypoint_dir {
+                        wire_views.last_mut().unwrap().set_ends_in_junction();
+                        insert_junction(
+                            junctions,
+                            last_waypoint,
+                            vertices.len - 1,
+                            last_waypoint_dir,
+                        );
+                    }
+
+                    continue;
                 }
-            };
 
-            wire_views
-                .push(WireView::new(path_len, true, false).expect("path too long"))
-                .map_err(|_| RoutingError::WireViewBufferOverflow)?;
-            replay.routing_end_wire_segment(true);
+                let path_len = push_vertices(
+                    path,
+                    graph,
+                    vertices,
+                    ends,
+                    centering_candidates,
+                    congestion.as_deref_mut(),
+                    replay,
+                )
+                .map_err(|_| RoutingError::VertexBufferOverflow)?;
+
+                assert!(path_len >= 2);
+
+                let (last, head) = path.nodes().split_last().unwrap();
+                let prev_last = head.last().unwrap();
+                insert_junction(
+                    junctions,
+                    last.position,
+                    vertices.len - 1,
+                    prev_last.bend_direction.unwrap(),
+                );
+
+                path_len
+            }
+            PathFindResult::NotFound => {
+                // Continue from the last waypoint, the wire leading up to it has already been routed.
+                let last_waypoint_node = &graph.nodes[graph.find_node(last_waypoint).unwrap()];
+                let (path_len, junction_pos, junction_dir) = push_fallback_vertices(
+                    graph,
+                    path_finder,
+                    last_waypoint,
+                    &ends[..end_count],
+                    last_waypoint_node.legal_directions,
+                    cost_model,
+                    vertices,
+                    replay,
+                )
+                .map_err(|_| RoutingError::VertexBufferOverflow)?;
+                diagnostics.push(DiagnosticKind::FallbackWire, last_waypoint, junction_pos);
+
+                assert!(path_len >= 2);
+                insert_junction(junctions, junction_pos, vertices.len - 1, junction_dir);
+                let fallback_vertices = &vertices[(vertices.len - path_len)..];
+                occupy_fallback_wire(graph, fallback_vertices, congestion.as_deref_mut());
+                push_fallback_segments(fallback_vertices, ends);
+
+                path_len
+            }
+            PathFindResult::InvalidStartPoint | PathFindResult::InvalidEndPoint => {
+                unreachable!("endpoints and waypoints are checked before routing")
+            }
+        };
 
-            wire_count += 1;
-        }
+        wire_views
+            .push(WireView::new(path_len, true, false).expect("path too long"))
+            .map_err(|_| RoutingError::WireViewBufferOverflow)?;
+        replay.routing_end_wire_segment(true);
+
+        wire_count += 1;
     }
 
     replay.routing_end_wire();
@@ -659,7 +1105,7 @@ fn are_connected_vertically(
             through_anchor = true;
         }
         match junctions.get(&node.position) {
-            Some(JunctionKind::Degenerate) => {
+            Some(JunctionKind::Degenerate { .. }) => {
                 through_anchor = true;
                 through_junction = true;
             }
@@ -707,7 +1153,7 @@ fn are_connected_horizontally(
             through_anchor = true;
         }
         match junctions.get(&node.position) {
-            Some(JunctionKind::Degenerate) => {
+            Some(JunctionKind::Degenerate { .. }) => {
                 through_anchor = true;
                 through_junction = true;
             }
@@ -987,8 +1433,6 @@ fn center_wires(
 
         for (&junction_point, junction_kind) in junctions {
             for (junction_vertex, junction_dir) in junction_kind.iter() {
-                let junction_vertex = junction_vertex as usize;
-
                 match offset {
                     NudgeOffset::None => unreachable!(),
                     NudgeOffset::Horizontal(offset) => {
@@ -999,23 +1443,19 @@ fn center_wires(
 
                         if (junction_point.x == node_a.position.x)
                             && matches!(junction_dir, Direction::NegX | Direction::PosX)
+                            && (junction_point.y >= min_y)
+                            && (junction_point.y <= max_y)
                         {
-                            if (junction_point.y >= min_y) && (junction_point.y <= max_y) {
-                                vertices[junction_vertex].x += offset;
-                            }
+                            vertices[junction_vertex].x += offset;
                         }
 
                         if junction_point.x == (node_a.position.x + (offset as i32)) {
                             match junction_dir {
-                                Direction::PosY => {
-                                    if junction_point.y == max_y {
-                                        vertices[junction_vertex].y = min_y as f32;
-                                    }
+                                Direction::PosY if junction_point.y == max_y => {
+                                    vertices[junction_vertex].y = min_y as f32;
                                 }
-                                Direction::NegY => {
-                                    if junction_point.y == min_y {
-                                        vertices[junction_vertex].y = max_y as f32;
-                                    }
+                                Direction::NegY if junction_point.y == min_y => {
+                                    vertices[junction_vertex].y = max_y as f32;
                                 }
                                 _ => (),
                             }
@@ -1029,23 +1469,19 @@ fn center_wires(
 
                         if (junction_point.y == node_a.position.y)
                             && matches!(junction_dir, Direction::NegY | Direction::PosY)
+                            && (junction_point.x >= min_x)
+                            && (junction_point.x <= max_x)
                         {
-                            if (junction_point.x >= min_x) && (junction_point.x <= max_x) {
-                                vertices[junction_vertex].y += offset;
-                            }
+                            vertices[junction_vertex].y += offset;
                         }
 
                         if junction_point.y == (node_a.position.y + (offset as i32)) {
                             match junction_dir {
-                                Direction::PosX => {
-                                    if junction_point.x == max_x {
-                                        vertices[junction_vertex].x = min_x as f32;
-                                    }
+                                Direction::PosX if junction_point.x == max_x => {
+                                    vertices[junction_vertex].x = min_x as f32;
                                 }
-                                Direction::NegX => {
-                                    if junction_point.x == min_x {
-                                        vertices[junction_vertex].x = max_x as f32;
-                                    }
+                                Direction::NegX if junction_point.x == min_x => {
+                                    vertices[junction_vertex].x = max_x as f32;
                                 }
                                 _ => (),
                             }
@@ -1057,12 +1493,187 @@ fn center_wires(
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A point the wire of an endpoint has to pass through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Waypoint {
+    pub position: Point,
+    /// If set, the direction the wire has to travel in while passing through the waypoint,
+    /// as seen when going away from the endpoint.
+    #[serde(default)]
+    pub direction: Option<Direction>,
+}
+
+impl Waypoint {
+    #[inline]
+    pub const fn new(position: Point) -> Self {
+        Self {
+            position,
+            direction: None,
+        }
+    }
+
+    #[inline]
+    pub const fn with_direction(self, direction: Direction) -> Self {
+        Self {
+            direction: Some(direction),
+            ..self
+        }
+    }
+}
+
+impl From<Point> for Waypoint {
+    #[inline]
+    fn from(position: Point) -> Self {
+        Self::new(position)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Endpoint<'a> {
     pub position: Point,
-    pub waypoints: Cow<'a, [Point]>,
+    pub waypoints: Cow<'a, [Waypoint]>,
+}
+
+impl Endpoint<'_> {
+    /// The waypoints of the endpoint, in the order they are listed.
+    fn waypoint_targets(&self) -> impl DoubleEndedIterator<Item = Target> + '_ {
+        self.waypoints.iter().map(|waypoint| Target {
+            position: waypoint.position,
+            direction: waypoint.direction,
+        })
+    }
+}
+
+/// Which orientation the wires of a net prefer to run in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u32)]
+pub enum DirectionPreference {
+    /// Horizontal and vertical segments are treated the same.
+    #[default]
+    None = 0,
+    /// Vertical segments cost twice as much per unit of length.
+    Horizontal = 1,
+    /// Horizontal segments cost twice as much per unit of length.
+    Vertical = 2,
+}
+
+impl DirectionPreference {
+    /// Determines if segments running in direction `dir` go against this preference.
+    #[inline]
+    pub(crate) fn penalizes(self, dir: Direction) -> bool {
+        match self {
+            Self::None => false,
+            Self::Horizontal => matches!(dir, Direction::PosY | Direction::NegY),
+            Self::Vertical => matches!(dir, Direction::PosX | Direction::NegX),
+        }
+    }
+}
+
+/// The order in which a wire visits the waypoints of its endpoints.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u32)]
+pub enum WaypointOrder {
+    /// The wire always continues to the waypoint closest to where it currently is.
+    #[default]
+    Nearest = 0,
+    /// The wire visits the waypoints in the order they are listed, starting at the endpoint they belong to.
+    /// The waypoints of the endpoint a root wire ends in are visited in reverse.
+    Given = 1,
+    /// The wire visits the waypoints in the order that makes the tour through them the shortest.
+    Optimized = 2,
+}
+
+/// Rules the wires of a single net have to follow on top of the ones every net follows.
+///
+/// The default does not constrain the net in any way.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+#[repr(C)]
+pub struct NetConstraints {
+    /// The maximum number of bends of a wire between two consecutive waypoints or endpoints.
+    ///
+    /// 0 means that the number of bends is not limited, so wires without any bends cannot be requested.
+    /// Paths exceeding the limit are discarded during the search, so a net that cannot satisfy it gets a fallback wire instead.
+    /// The search tracks every node separately for every number of bends up to the limit, so its memory grows with the limit.
+    /// Limits above [`NetConstraints::MAX_BEND_LIMIT`] are rejected with [`RoutingError::InvalidConstraints`].
+    pub max_bends: u32,
+    /// Which orientation the wires of the net prefer to run in.
+    pub direction_preference: DirectionPreference,
+    /// If greater than 1, the factor the cost of the paths of the net is multiplied with before congestion costs are added,
+    /// so the net avoids the wires of other nets less.
+    pub cost_multiplier: u32,
+    /// The order in which the wires of the net visit waypoints.
+    pub waypoint_order: WaypointOrder,
+}
+
+impl NetConstraints {
+    /// The largest bend limit a net can have.
+    pub const MAX_BEND_LIMIT: u32 = 16;
+
+    #[inline]
+    pub const fn with_max_bends(self, max_bends: u32) -> Self {
+        Self { max_bends, ..self }
+    }
+
+    #[inline]
+    pub const fn with_direction_preference(
+        self,
+        direction_preference: DirectionPreference,
+    ) -> Self {
+        Self {
+            direction_preference,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub const fn with_cost_multiplier(self, cost_multiplier: u32) -> Self {
+        Self {
+            cost_multiplier,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub const fn with_waypoint_order(self, waypoint_order: WaypointOrder) -> Self {
+        Self {
+            waypoint_order,
+            ..self
+        }
+    }
+
+    /// Determines if the constraints can be routed with.
+    #[inline]
+    pub(crate) fn is_valid(&self) -> bool {
+        self.max_bends <= Self::MAX_BEND_LIMIT
+    }
+
+    /// Determines if a path with `bends` bends is allowed.
+    #[inline]
+    pub(crate) fn allows_bends(&self, bends: u32) -> bool {
+        (self.max_bends == 0) || (bends <= self.max_bends)
+    }
+
+    #[inline]
+    pub(crate) fn cost_multiplier(&self) -> u32 {
+        self.cost_multiplier.max(1)
+    }
+}
+
+/// The shape of the wire tree connecting the endpoints of a net.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u32)]
+pub enum NetTopology {
+    /// A root wire between the two most distant endpoints, with all other endpoints branching off of it in order.
+    #[default]
+    RootAndBranches = 0,
+    /// A rectilinear Steiner tree.
+    /// The wires join at Steiner points found with the iterated 1-Steiner heuristic.
+    /// Nets with waypoints or many endpoints instead always connect the endpoint closest to the already routed wires next.
+    Steiner = 1,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn connect_net<'a>(
     graph: &Graph,
     endpoints: impl Clone + Iterator<Item: Borrow<Endpoint<'a>>>,
@@ -1071,19 +1682,32 @@ pub(crate) fn connect_net<'a>(
     vertices: &mut Array<Vertex>,
     wire_views: &mut Array<WireView>,
     net_view: &mut MaybeUninit<NetView>,
-    ends: &mut Vec<Point>,
+    ends: &mut Vec<Segment>,
     centering_candidates: &mut Vec<CenteringCandidate>,
     junctions: &mut JunctionMap,
+    diagnostics: &mut NetDiagnostics,
     perform_centering: bool,
+    topology: NetTopology,
+    cost_model: &CostModel,
+    constraints: &NetConstraints,
+    mut congestion: Option<&mut Congestion>,
     replay: &mut impl ReplayCapture,
 ) -> Result<(), RoutingError> {
+    if !constraints.is_valid() {
+        return Err(RoutingError::InvalidConstraints);
+    }
+
     let path_finder = &mut *graph.path_finder.get_or_default().borrow_mut();
-    let (root_start, root_end) =
-        pick_root_path(endpoints.clone()).map_err(|_| RoutingError::NotEnoughEndpoints)?;
+    let (root_start, root_end) = match topology {
+        NetTopology::RootAndBranches => pick_root_path(endpoints.clone()),
+        NetTopology::Steiner => pick_closest_pair(endpoints.clone()),
+    }
+    .map_err(|_| RoutingError::NotEnoughEndpoints)?;
 
     ends.clear();
     centering_candidates.clear();
     junctions.clear();
+    diagnostics.clear();
 
     let wire_offset = (wire_base_offset + wire_views.len)
         .try_into()
@@ -1092,15 +1716,58 @@ pub(crate) fn connect_net<'a>(
         .try_into()
         .expect("too many vertices");
 
+    // A net with points off the graph is reported instead of routed, so the other nets can still be routed.
+    for endpoint in endpoints.clone() {
+        let endpoint = endpoint.borrow();
+
+        let points = std::iter::once(endpoint.position)
+            .chain(endpoint.waypoints.iter().map(|waypoint| waypoint.position));
+        for point in points {
+            if graph.data.find_node(point).is_none() {
+                diagnostics.push(DiagnosticKind::InvalidEndpoint, endpoint.position, point);
+            }
+        }
+    }
+
+    if diagnostics.status == NetStatus::Failed {
+        net_view.write(NetView {
+            wire_offset,
+            wire_count: 0,
+            vertex_offset,
+        });
+
+        return Ok(());
+    }
+
+    // Steiner points are only added to nets without waypoints, because the wires of the others do not
+    // run between the endpoints directly.
+    let has_waypoints = endpoints
+        .clone()
+        .any(|endpoint| !endpoint.borrow().waypoints.is_empty());
+    let steiner_points = match topology {
+        NetTopology::Steiner if !has_waypoints => SteinerPoints::find(
+            &graph.data,
+            endpoints.clone().map(|endpoint| endpoint.borrow().position),
+            root_start.borrow().position,
+            root_end.borrow().position,
+        ),
+        _ => SteinerPoints::default(),
+    };
+
     let root_wire_count = route_root_wire(
         &graph.data,
         path_finder,
         root_start.borrow(),
         root_end.borrow(),
+        &steiner_points,
         vertices,
         wire_views,
         ends,
         centering_candidates,
+        diagnostics,
+        cost_model,
+        constraints,
+        congestion.as_deref_mut(),
         replay,
     )?;
 
@@ -1110,16 +1777,22 @@ pub(crate) fn connect_net<'a>(
         endpoints,
         root_start.borrow(),
         root_end.borrow(),
+        &steiner_points,
         vertices,
         wire_views,
         ends,
         centering_candidates,
         junctions,
+        diagnostics,
+        topology,
+        cost_model,
+        constraints,
+        congestion,
         replay,
     )?;
 
     if perform_centering {
-        center_wires(&centering_candidates, &graph.data, vertices, junctions);
+        center_wires(centering_candidates, &graph.data, vertices, junctions);
     }
 
     net_view.write(NetView {
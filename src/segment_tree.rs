@@ -39,12 +39,41 @@ impl<T: std::fmt::Debug> SegmentTree<T> {
         use rayon::prelude::*;
 
         self.segments.clear();
-        self.segments.extend(segments.into_iter());
+        self.segments.extend(segments);
         self.segments
             .par_sort_unstable_by_key(|segment| segment.start_inclusive);
         self.max_segment_len = self.segments.iter().map(Segment::len).max().unwrap_or(0);
     }
 
+    /// Inserts a single segment, keeping the segments sorted.
+    pub fn insert(&mut self, segment: Segment<T>) {
+        let index = self
+            .segments
+            .partition_point(|other| other.start_inclusive <= segment.start_inclusive);
+        self.max_segment_len = self.max_segment_len.max(segment.len());
+        self.segments.insert(index, segment);
+    }
+
+    /// Removes the first segment starting at `start_inclusive` whose value matches `predicate`.
+    ///
+    /// The maximum segment length is not reduced, so queries stay correct but may have to skip more segments
+    /// until the tree is rebuilt.
+    pub fn remove(
+        &mut self,
+        start_inclusive: i32,
+        mut predicate: impl FnMut(&T) -> bool,
+    ) -> Option<Segment<T>> {
+        let first = self
+            .segments
+            .partition_point(|segment| segment.start_inclusive < start_inclusive);
+        let offset = self.segments[first..]
+            .iter()
+            .take_while(|segment| segment.start_inclusive == start_inclusive)
+            .position(|segment| predicate(&segment.value))?;
+
+        Some(self.segments.remove(first + offset))
+    }
+
     fn find_start_index(&self, position: i32) -> usize {
         match self.segments.binary_search_by(|segment| {
             (segment.start_inclusive + self.max_segment_len).cmp(&position)
@@ -110,7 +139,22 @@ impl<T: std::fmt::Debug> SegmentTree<T> {
 
         ContainingSegmentIter {
             inner: self.segments[start_index..end_index].iter(),
-            position,
+            start: position,
+            end: position,
+        }
+    }
+
+    /// Iterates all segments that overlap the range from `start` to `end` (inclusive).
+    pub fn iter_overlapping(&self, start: i32, end: i32) -> ContainingSegmentIter<'_, T> {
+        assert!(start <= end);
+
+        let start_index = self.find_start_index(start);
+        let end_index = self.find_end_index(end).max(start_index);
+
+        ContainingSegmentIter {
+            inner: self.segments[start_index..end_index].iter(),
+            start,
+            end,
         }
     }
 }
@@ -118,17 +162,18 @@ impl<T: std::fmt::Debug> SegmentTree<T> {
 #[derive(Clone)]
 pub struct ContainingSegmentIter<'a, T> {
     inner: std::slice::Iter<'a, Segment<T>>,
-    position: i32,
+    start: i32,
+    end: i32,
 }
 
 impl<'a, T> Iterator for ContainingSegmentIter<'a, T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(segment) = self.inner.next() {
-            debug_assert!(segment.start_inclusive <= self.position);
+        for segment in self.inner.by_ref() {
+            debug_assert!(segment.start_inclusive <= self.end);
 
-            if segment.end_inclusive >= self.position {
+            if segment.end_inclusive >= self.start {
                 return Some(&segment.value);
             }
         }
@@ -292,4 +337,68 @@ mod tests {
         assert_eq!(tree.iter_containing(11).count(), 1);
         assert_eq!(tree.iter_containing(21).count(), 0);
     }
+
+    #[test]
+    fn overlapping() {
+        let mut tree = SegmentTree::default();
+        tree.build([
+            Segment {
+                start_inclusive: -20,
+                end_inclusive: -10,
+                value: (),
+            },
+            Segment {
+                start_inclusive: -5,
+                end_inclusive: 5,
+                value: (),
+            },
+            Segment {
+                start_inclusive: 10,
+                end_inclusive: 20,
+                value: (),
+            },
+        ]);
+
+        assert_eq!(tree.iter_overlapping(-30, -21).count(), 0);
+        assert_eq!(tree.iter_overlapping(-30, -20).count(), 1);
+        assert_eq!(tree.iter_overlapping(-9, -6).count(), 0);
+        assert_eq!(tree.iter_overlapping(-10, -5).count(), 2);
+        assert_eq!(tree.iter_overlapping(-1, 1).count(), 1);
+        assert_eq!(tree.iter_overlapping(-30, 30).count(), 3);
+        assert_eq!(tree.iter_overlapping(21, 30).count(), 0);
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut tree = SegmentTree::default();
+        tree.build([
+            Segment {
+                start_inclusive: -20,
+                end_inclusive: -10,
+                value: 0,
+            },
+            Segment {
+                start_inclusive: 10,
+                end_inclusive: 20,
+                value: 1,
+            },
+        ]);
+
+        tree.insert(Segment {
+            start_inclusive: -5,
+            end_inclusive: 25,
+            value: 2,
+        });
+        assert_eq!(tree.iter_containing(22).copied().collect::<Vec<_>>(), [2]);
+        assert_eq!(tree.iter_containing(15).count(), 2);
+
+        assert!(tree.remove(-5, |&value| value == 1).is_none());
+        assert_eq!(
+            tree.remove(-5, |&value| value == 2).map(|s| s.value),
+            Some(2)
+        );
+        assert_eq!(tree.iter_containing(22).count(), 0);
+        assert_eq!(tree.iter_containing(15).copied().collect::<Vec<_>>(), [1]);
+        assert_eq!(tree.iter_overlapping(-30, 30).count(), 2);
+    }
 }
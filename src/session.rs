@@ -0,0 +1,690 @@
+use crate::congestion::{Congestion, Occupancy};
+use crate::path_finding::Segment;
+use crate::routing::{Array, CenteringCandidate, JunctionMap, NetDiagnostics};
+use crate::*;
+use rayon::prelude::*;
+use std::borrow::{Borrow, Cow};
+use std::mem::MaybeUninit;
+
+const INITIAL_VERTEX_CAPACITY: usize = 128;
+const INITIAL_WIRE_VIEW_CAPACITY: usize = 32;
+
+fn to_owned_endpoint(endpoint: &Endpoint) -> Endpoint<'static> {
+    Endpoint {
+        position: endpoint.position,
+        waypoints: Cow::Owned(endpoint.waypoints.to_vec()),
+    }
+}
+
+#[derive(Default)]
+struct SessionNet {
+    endpoints: Vec<Endpoint<'static>>,
+    constraints: NetConstraints,
+    vertices: Vec<Vertex>,
+    wire_views: Vec<WireView>,
+    diagnostics: NetDiagnostics,
+    dirty: bool,
+}
+
+impl SessionNet {
+    /// Determines if any wire of this net crosses the given region.
+    fn crosses(&self, region: BoundingBox) -> bool {
+        let min_x = region.min_x() as f32;
+        let min_y = region.min_y() as f32;
+        let max_x = region.max_x() as f32;
+        let max_y = region.max_y() as f32;
+
+        let mut vertices = self.vertices.as_slice();
+        for wire_view in &self.wire_views {
+            let (wire_vertices, tail) = vertices.split_at(wire_view.vertex_count());
+            vertices = tail;
+
+            for segment in wire_vertices.windows(2) {
+                let [a, b] = segment else { unreachable!() };
+
+                if (a.x.max(b.x) >= min_x)
+                    && (a.x.min(b.x) <= max_x)
+                    && (a.y.max(b.y) >= min_y)
+                    && (a.y.min(b.y) <= max_y)
+                {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Scratch {
+    vertices: Vec<MaybeUninit<Vertex>>,
+    wire_views: Vec<MaybeUninit<WireView>>,
+    ends: Vec<Segment>,
+    centering_candidates: Vec<CenteringCandidate>,
+    /// The junctions of the last routed net.
+    pub(crate) junctions: JunctionMap,
+    /// The diagnostics of the last routed net.
+    pub(crate) diagnostics: NetDiagnostics,
+}
+
+impl Scratch {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn route_net(
+        &mut self,
+        graph: &Graph,
+        endpoints: &[Endpoint],
+        perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
+        constraints: &NetConstraints,
+        congestion: Option<&mut Congestion>,
+    ) -> Result<(Vec<Vertex>, Vec<WireView>), RoutingError> {
+        let mut vertices = Vec::new();
+        let mut wire_views = Vec::new();
+        self.route_net_into(
+            graph,
+            endpoints,
+            perform_centering,
+            topology,
+            cost_model,
+            constraints,
+            congestion,
+            &mut vertices,
+            &mut wire_views,
+        )?;
+
+        Ok((vertices, wire_views))
+    }
+
+    /// Routes a net and appends its vertices and wires to `vertices` and `wire_views`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn route_net_into(
+        &mut self,
+        graph: &Graph,
+        endpoints: &[Endpoint],
+        perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
+        constraints: &NetConstraints,
+        mut congestion: Option<&mut Congestion>,
+        out_vertices: &mut Vec<Vertex>,
+        out_wire_views: &mut Vec<WireView>,
+    ) -> Result<(), RoutingError> {
+        if self.vertices.is_empty() {
+            self.vertices
+                .resize(INITIAL_VERTEX_CAPACITY, MaybeUninit::uninit());
+        }
+
+        if self.wire_views.is_empty() {
+            self.wire_views
+                .resize(INITIAL_WIRE_VIEW_CAPACITY, MaybeUninit::uninit());
+        }
+
+        // The size of the result is not known up front, so grow the buffers until it fits.
+        loop {
+            let mut vertices: Array<Vertex> = self.vertices.as_mut_slice().into();
+            let mut wire_views: Array<WireView> = self.wire_views.as_mut_slice().into();
+            let mut net_view = MaybeUninit::uninit();
+
+            let result = routing::connect_net(
+                graph,
+                endpoints.iter(),
+                0,
+                0,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_view,
+                &mut self.ends,
+                &mut self.centering_candidates,
+                &mut self.junctions,
+                &mut self.diagnostics,
+                perform_centering,
+                topology,
+                cost_model,
+                constraints,
+                congestion.as_deref_mut(),
+                &mut NoReplay,
+            );
+
+            if let (Err(_), Some(congestion)) = (&result, congestion.as_deref_mut()) {
+                congestion.occupancy.remove_net(congestion.net);
+            }
+
+            match result {
+                Ok(()) => {
+                    out_vertices.extend_from_slice(&vertices);
+                    out_wire_views.extend_from_slice(&wire_views);
+                    return Ok(());
+                }
+                Err(RoutingError::VertexBufferOverflow) => {
+                    let new_len = self.vertices.len() * 2;
+                    self.vertices.resize(new_len, MaybeUninit::uninit());
+                }
+                Err(RoutingError::WireViewBufferOverflow) => {
+                    let new_len = self.wire_views.len() * 2;
+                    self.wire_views.resize(new_len, MaybeUninit::uninit());
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Keeps the routing results of a set of nets,
+/// so after an edit only the nets affected by it have to be routed again.
+///
+/// Nets are identified by their index in the list passed to [`RoutingSession::set_nets`].
+#[derive(Default)]
+pub struct RoutingSession {
+    nets: Vec<SessionNet>,
+    perform_centering: Option<bool>,
+    topology: Option<NetTopology>,
+    cost_model: Option<CostModel>,
+}
+
+impl RoutingSession {
+    /// Creates an empty session.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of nets in the session.
+    #[inline]
+    pub fn net_count(&self) -> usize {
+        self.nets.len()
+    }
+
+    /// Sets the nets of the session.
+    ///
+    /// Nets whose endpoints changed, as well as new nets, will be routed by the next call to [`RoutingSession::route`].
+    pub fn set_nets<'a, N>(&mut self, nets: impl IntoIterator<Item = N>)
+    where
+        N: IntoIterator<Item: Borrow<Endpoint<'a>>>,
+    {
+        let mut net_count = 0;
+        for (index, endpoints) in nets.into_iter().enumerate() {
+            let endpoints: Vec<_> = endpoints
+                .into_iter()
+                .map(|endpoint| to_owned_endpoint(endpoint.borrow()))
+                .collect();
+
+            if let Some(net) = self.nets.get_mut(index) {
+                if net.endpoints != endpoints {
+                    net.endpoints = endpoints;
+                    net.dirty = true;
+                }
+            } else {
+                self.nets.push(SessionNet {
+                    endpoints,
+                    dirty: true,
+                    ..Default::default()
+                });
+            }
+
+            net_count += 1;
+        }
+
+        self.nets.truncate(net_count);
+    }
+
+    /// Sets the rules the wires of a net have to follow.
+    ///
+    /// If they changed, the net will be routed by the next call to [`RoutingSession::route`].
+    pub fn set_constraints(&mut self, net: usize, constraints: NetConstraints) {
+        let net = &mut self.nets[net];
+        if net.constraints != constraints {
+            net.constraints = constraints;
+            net.dirty = true;
+        }
+    }
+
+    /// Marks a net as needing to be routed again.
+    #[inline]
+    pub fn invalidate_net(&mut self, net: usize) {
+        self.nets[net].dirty = true;
+    }
+
+    /// Marks all nets whose wires cross `region` as needing to be routed again.
+    ///
+    /// When a bounding box in the graph changes, this should be called with both its old and its new extent.
+    pub fn invalidate_region(&mut self, region: BoundingBox) {
+        for net in &mut self.nets {
+            if !net.dirty && net.crosses(region) {
+                net.dirty = true;
+            }
+        }
+    }
+
+    /// Marks all nets as needing to be routed again.
+    pub fn invalidate_all(&mut self) {
+        for net in &mut self.nets {
+            net.dirty = true;
+        }
+    }
+
+    /// Routes all nets that changed since the last call, reusing the results of all other nets.
+    ///
+    /// Returns the number of nets that were routed.
+    pub fn route(
+        &mut self,
+        graph: &Graph,
+        perform_centering: bool,
+        topology: NetTopology,
+        cost_model: &CostModel,
+    ) -> Result<usize, RoutingError> {
+        if self.perform_centering != Some(perform_centering) {
+            self.invalidate_all();
+            self.perform_centering = Some(perform_centering);
+        }
+
+        if self.topology != Some(topology) {
+            self.invalidate_all();
+            self.topology = Some(topology);
+        }
+
+        if self.cost_model.as_ref() != Some(cost_model) {
+            self.invalidate_all();
+            self.cost_model = Some(*cost_model);
+        }
+
+        let routed_count = self.nets.iter().filter(|net| net.dirty).count();
+
+        self.nets
+            .par_iter_mut()
+            .filter(|net| net.dirty)
+            .try_for_each_init(Scratch::default, |scratch, net| {
+                let (vertices, wire_views) = scratch.route_net(
+                    graph,
+                    &net.endpoints,
+                    perform_centering,
+                    topology,
+                    cost_model,
+                    &net.constraints,
+                    None,
+                )?;
+
+                net.vertices = vertices;
+                net.wire_views = wire_views;
+                net.diagnostics = std::mem::take(&mut scratch.diagnostics);
+                net.dirty = false;
+                Ok(())
+            })?;
+
+        Ok(routed_count)
+    }
+
+    /// The vertices of a net, as of the last call to [`RoutingSession::route`].
+    #[inline]
+    pub fn vertices(&self, net: usize) -> &[Vertex] {
+        &self.nets[net].vertices
+    }
+
+    /// The wires of a net, as of the last call to [`RoutingSession::route`].
+    #[inline]
+    pub fn wire_views(&self, net: usize) -> &[WireView] {
+        &self.nets[net].wire_views
+    }
+
+    /// How completely a net was routed, as of the last call to [`RoutingSession::route`].
+    #[inline]
+    pub fn status(&self, net: usize) -> NetStatus {
+        self.nets[net].diagnostics.status
+    }
+
+    /// The problems that occurred while routing a net, as of the last call to [`RoutingSession::route`].
+    #[inline]
+    pub fn diagnostics(&self, net: usize) -> &[Diagnostic] {
+        &self.nets[net].diagnostics.diagnostics
+    }
+
+    /// The total number of vertices in all nets.
+    pub fn vertex_count(&self) -> usize {
+        self.nets.iter().map(|net| net.vertices.len()).sum()
+    }
+
+    /// The total number of wires in all nets.
+    pub fn wire_view_count(&self) -> usize {
+        self.nets.iter().map(|net| net.wire_views.len()).sum()
+    }
+
+    /// Writes the results of all nets into contiguous buffers, in the same layout as [`Graph::connect_net`].
+    ///
+    /// `net_views` must be exactly as long as the number of nets in the session.
+    pub fn write_results(
+        &self,
+        vertices: &mut [MaybeUninit<Vertex>],
+        wire_views: &mut [MaybeUninit<WireView>],
+        net_views: &mut [MaybeUninit<NetView>],
+    ) -> Result<(), RoutingError> {
+        assert_eq!(net_views.len(), self.nets.len());
+
+        write_results(
+            self.nets
+                .iter()
+                .map(|net| (net.vertices.as_slice(), net.wire_views.as_slice())),
+            vertices,
+            wire_views,
+            net_views,
+        )
+    }
+}
+
+/// The results of the nets routed by one parallel job.
+#[derive(Default)]
+struct ChunkResults {
+    scratch: Scratch,
+    vertices: Vec<Vertex>,
+    wire_views: Vec<WireView>,
+    /// The index of each net, and where its vertices and wires start.
+    nets: Vec<(usize, usize, usize)>,
+    /// The diagnostics of each net, in the same order as `nets`.
+    diagnostics: Vec<NetDiagnostics>,
+}
+
+impl ChunkResults {
+    /// The vertices and wires of the net at position `index` in this chunk.
+    fn net(&self, index: usize) -> (&[Vertex], &[WireView]) {
+        let (_, vertex_start, wire_start) = self.nets[index];
+        let (vertex_end, wire_end) = match self.nets.get(index + 1) {
+            Some(&(_, vertex_end, wire_end)) => (vertex_end, wire_end),
+            None => (self.vertices.len(), self.wire_views.len()),
+        };
+
+        (
+            &self.vertices[vertex_start..vertex_end],
+            &self.wire_views[wire_start..wire_end],
+        )
+    }
+}
+
+/// A net routed again by [`assign_distinct_tracks`].
+pub(crate) struct ReroutedNet {
+    pub(crate) index: usize,
+    pub(crate) vertices: Vec<Vertex>,
+    pub(crate) wire_views: Vec<WireView>,
+    pub(crate) junctions: JunctionMap,
+    pub(crate) diagnostics: NetDiagnostics,
+}
+
+/// Routes the nets whose wires run on top of the ones of other nets again, one after another and avoiding
+/// the wires of all other nets, so that parallel wires of different nets end up on distinct tracks.
+///
+/// `routed` returns the vertices and wires of the net at an index, as routed in parallel.
+/// Only graphs built with parallel tracks have room for distinct tracks, and centering moves wires off their tracks,
+/// so nothing is routed again otherwise. Returns the nets that were routed again, in ascending order.
+pub(crate) fn assign_distinct_tracks<'a, 'b, N: RoutableNet<'a>>(
+    graph: &Graph,
+    nets: &[N],
+    routed: impl Fn(usize) -> (&'b [Vertex], &'b [WireView]),
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: &CostModel,
+) -> Result<Vec<ReroutedNet>, RoutingError> {
+    if !graph.data.tracks().has_parallel_tracks() || perform_centering {
+        return Ok(Vec::new());
+    }
+
+    let mut occupancy = Occupancy::default();
+    for index in 0..nets.len() {
+        let (vertices, wire_views) = routed(index);
+        let net = index.try_into().expect("too many nets");
+        occupancy.insert_wires(&graph.data, vertices, wire_views, net);
+    }
+
+    let mut scratch = Scratch::default();
+    let mut rerouted = Vec::new();
+    for net in occupancy.congested_nets() {
+        occupancy.remove_net(net);
+
+        let index = net as usize;
+        let (vertices, wire_views) = scratch.route_net(
+            graph,
+            nets[index].endpoints(),
+            perform_centering,
+            topology,
+            cost_model,
+            &nets[index].constraints(),
+            Some(&mut Congestion {
+                occupancy: &mut occupancy,
+                net,
+                costs: CongestionCosts::default(),
+            }),
+        )?;
+
+        rerouted.push(ReroutedNet {
+            index,
+            vertices,
+            wire_views,
+            junctions: std::mem::take(&mut scratch.junctions),
+            diagnostics: std::mem::take(&mut scratch.diagnostics),
+        });
+    }
+
+    Ok(rerouted)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn connect_nets_deterministic<'a, N: RoutableNet<'a> + Sync>(
+    graph: &Graph,
+    nets: &[N],
+    vertices: &mut [MaybeUninit<Vertex>],
+    wire_views: &mut [MaybeUninit<WireView>],
+    net_views: &mut [MaybeUninit<NetView>],
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: &CostModel,
+    report: &mut RoutingReport,
+) -> Result<(), RoutingError> {
+    assert_eq!(nets.len(), net_views.len());
+
+    // Every job routes into its own growable buffers, so a large net cannot overflow a fixed share of the output.
+    let chunks: Vec<ChunkResults> = nets
+        .par_iter()
+        .enumerate()
+        .try_fold(ChunkResults::default, |mut chunk, (index, net)| {
+            chunk
+                .nets
+                .push((index, chunk.vertices.len(), chunk.wire_views.len()));
+
+            chunk.scratch.route_net_into(
+                graph,
+                net.endpoints(),
+                perform_centering,
+                topology,
+                cost_model,
+                &net.constraints(),
+                None,
+                &mut chunk.vertices,
+                &mut chunk.wire_views,
+            )?;
+
+            let diagnostics = std::mem::take(&mut chunk.scratch.diagnostics);
+            chunk.diagnostics.push(diagnostics);
+
+            Ok(chunk)
+        })
+        .collect::<Result<_, _>>()?;
+
+    // Merge the chunks in the order of the nets, so the output does not depend on scheduling.
+    let mut locations = vec![(0, 0); nets.len()];
+    for (chunk_index, chunk) in chunks.iter().enumerate() {
+        for (index_in_chunk, &(net_index, _, _)) in chunk.nets.iter().enumerate() {
+            locations[net_index] = (chunk_index, index_in_chunk);
+        }
+    }
+
+    let routed = |index: usize| {
+        let (chunk_index, index_in_chunk) = locations[index];
+        chunks[chunk_index].net(index_in_chunk)
+    };
+
+    let rerouted =
+        assign_distinct_tracks(graph, nets, routed, perform_centering, topology, cost_model)?;
+    let rerouted_net = |index: usize| {
+        rerouted
+            .binary_search_by_key(&index, |net| net.index)
+            .ok()
+            .map(|position| &rerouted[position])
+    };
+
+    write_results(
+        (0..nets.len()).map(|index| match rerouted_net(index) {
+            Some(net) => (net.vertices.as_slice(), net.wire_views.as_slice()),
+            None => routed(index),
+        }),
+        vertices,
+        wire_views,
+        net_views,
+    )?;
+
+    report.set_nets((0..nets.len()).map(|index| match rerouted_net(index) {
+        Some(net) => net.diagnostics.clone(),
+        None => {
+            let (chunk_index, index_in_chunk) = locations[index];
+            chunks[chunk_index].diagnostics[index_in_chunk].clone()
+        }
+    }));
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn connect_nets_congestion_aware<'a, N: RoutableNet<'a>>(
+    graph: &Graph,
+    nets: &[N],
+    vertices: &mut [MaybeUninit<Vertex>],
+    wire_views: &mut [MaybeUninit<WireView>],
+    net_views: &mut [MaybeUninit<NetView>],
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: &CostModel,
+    congestion_costs: CongestionCosts,
+    report: &mut RoutingReport,
+) -> Result<(), RoutingError> {
+    assert_eq!(nets.len(), net_views.len());
+
+    let mut vertices = vertices.into();
+    let mut wire_views = wire_views.into();
+    let mut ends = Vec::new();
+    let mut centering_candidates = Vec::new();
+    let mut junctions = JunctionMap::default();
+    let mut diagnostics = NetDiagnostics::default();
+    let mut occupancy = Occupancy::default();
+    let mut net_diagnostics = Vec::with_capacity(nets.len());
+
+    for (index, (net, net_view)) in nets.iter().zip(net_views).enumerate() {
+        routing::connect_net(
+            graph,
+            net.endpoints().iter(),
+            0,
+            0,
+            &mut vertices,
+            &mut wire_views,
+            net_view,
+            &mut ends,
+            &mut centering_candidates,
+            &mut junctions,
+            &mut diagnostics,
+            perform_centering,
+            topology,
+            cost_model,
+            &net.constraints(),
+            Some(&mut Congestion {
+                occupancy: &mut occupancy,
+                net: index.try_into().expect("too many nets"),
+                costs: congestion_costs,
+            }),
+            &mut NoReplay,
+        )?;
+
+        net_diagnostics.push(diagnostics.clone());
+    }
+
+    report.set_nets(net_diagnostics);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn connect_nets_by_priority<'a, N: RoutableNet<'a>>(
+    graph: &Graph,
+    nets: &[N],
+    vertices: &mut [MaybeUninit<Vertex>],
+    wire_views: &mut [MaybeUninit<WireView>],
+    net_views: &mut [MaybeUninit<NetView>],
+    perform_centering: bool,
+    topology: NetTopology,
+    cost_model: &CostModel,
+    congestion_costs: CongestionCosts,
+    report: &mut RoutingReport,
+) -> Result<(), RoutingError> {
+    assert_eq!(nets.len(), net_views.len());
+
+    // The sort is stable, so nets of the same priority are routed in the order they were given in.
+    let mut order: Vec<usize> = (0..nets.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(nets[index].priority()));
+
+    let mut scratch = Scratch::default();
+    let mut occupancy = Occupancy::default();
+    let mut results = vec![(Vec::new(), Vec::new()); nets.len()];
+    let mut diagnostics = vec![NetDiagnostics::default(); nets.len()];
+
+    for index in order {
+        let net = &nets[index];
+        results[index] = scratch.route_net(
+            graph,
+            net.endpoints(),
+            perform_centering,
+            topology,
+            cost_model,
+            &net.constraints(),
+            Some(&mut Congestion {
+                occupancy: &mut occupancy,
+                net: index.try_into().expect("too many nets"),
+                costs: congestion_costs,
+            }),
+        )?;
+        diagnostics[index] = std::mem::take(&mut scratch.diagnostics);
+    }
+
+    write_results(
+        results
+            .iter()
+            .map(|(vertices, wire_views)| (vertices.as_slice(), wire_views.as_slice())),
+        vertices,
+        wire_views,
+        net_views,
+    )?;
+
+    report.set_nets(diagnostics);
+    Ok(())
+}
+
+/// Writes the results of individually routed nets into contiguous buffers.
+pub(crate) fn write_results<'a>(
+    nets: impl Iterator<Item = (&'a [Vertex], &'a [WireView])>,
+    vertices: &mut [MaybeUninit<Vertex>],
+    wire_views: &mut [MaybeUninit<WireView>],
+    net_views: &mut [MaybeUninit<NetView>],
+) -> Result<(), RoutingError> {
+    let mut vertices: Array<Vertex> = vertices.into();
+    let mut wire_views: Array<WireView> = wire_views.into();
+
+    for ((net_vertices, net_wire_views), net_view) in nets.zip(net_views) {
+        net_view.write(NetView {
+            wire_offset: wire_views.len.try_into().expect("too many wires"),
+            wire_count: net_wire_views.len().try_into().expect("too many wires"),
+            vertex_offset: vertices.len.try_into().expect("too many vertices"),
+        });
+
+        vertices
+            .extend_from_slice(net_vertices)
+            .map_err(|_| RoutingError::VertexBufferOverflow)?;
+        wire_views
+            .extend_from_slice(net_wire_views)
+            .map_err(|_| RoutingError::WireViewBufferOverflow)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use crate::graph::GraphData;
+use crate::Point;
+
+/// Nets with more endpoints than this are routed without Steiner points, because finding them takes too long.
+const TERMINAL_LIMIT: usize = 16;
+
+/// The length of the rectilinear minimum spanning tree of `points`, and the neighbors of every point in it.
+fn spanning_tree(points: &[Point]) -> (u64, Vec<Vec<usize>>) {
+    let mut neighbors = vec![Vec::new(); points.len()];
+    let mut in_tree = vec![false; points.len()];
+    // The distance of every point to the tree, and the point in the tree it is closest to.
+    let mut closest = vec![(u32::MAX, 0); points.len()];
+    closest[0].0 = 0;
+
+    let mut length = 0;
+    for _ in 0..points.len() {
+        let next = (0..points.len())
+            .filter(|&index| !in_tree[index])
+            .min_by_key(|&index| closest[index].0)
+            .unwrap();
+
+        let (distance, parent) = closest[next];
+        in_tree[next] = true;
+        length += distance as u64;
+        if next != 0 {
+            neighbors[next].push(parent);
+            neighbors[parent].push(next);
+        }
+
+        for (index, &point) in points.iter().enumerate() {
+            let distance = points[next].manhatten_distance_to(point);
+            if !in_tree[index] && (distance < closest[index].0) {
+                closest[index] = (distance, next);
+            }
+        }
+    }
+
+    (length, neighbors)
+}
+
+/// Points at which the wires of a net routed as a Steiner tree join each other,
+/// together with the endpoints whose wires pass through them.
+#[derive(Debug, Default)]
+pub(crate) struct SteinerPoints {
+    /// Every endpoint and the Steiner points its wire visits, in order.
+    /// The endpoints are sorted so that the wires they are joining are routed before them.
+    chains: Vec<(Point, Vec<Point>)>,
+}
+
+impl SteinerPoints {
+    /// Finds Steiner points for a net with endpoints at `terminals`, using the iterated 1-Steiner heuristic
+    /// on the points of the Hanan grid that lie on the graph.
+    ///
+    /// Every Steiner point is passed through by the wire of one of the endpoints it connects,
+    /// the ones on the way from `root_start` to `root_end` by the root wire.
+    pub(crate) fn find(
+        graph: &GraphData,
+        terminals: impl Iterator<Item = Point>,
+        root_start: Point,
+        root_end: Point,
+    ) -> Self {
+        let mut points: Vec<Point> = Vec::new();
+        for terminal in terminals {
+            if !points.contains(&terminal) {
+                points.push(terminal);
+            }
+        }
+
+        let terminal_count = points.len();
+        if !(3..=TERMINAL_LIMIT).contains(&terminal_count) {
+            return Self::default();
+        }
+
+        let mut xs: Vec<_> = points.iter().map(|point| point.x).collect();
+        let mut ys: Vec<_> = points.iter().map(|point| point.y).collect();
+        xs.sort_unstable();
+        xs.dedup();
+        ys.sort_unstable();
+        ys.dedup();
+
+        let candidates: Vec<_> = xs
+            .iter()
+            .flat_map(|&x| ys.iter().map(move |&y| Point { x, y }))
+            .filter(|candidate| {
+                !points.contains(candidate) && graph.find_node(*candidate).is_some()
+            })
+            .collect();
+
+        // Every round adds the candidate that shortens the spanning tree the most, until none of them shortens it.
+        let (mut length, mut neighbors) = spanning_tree(&points);
+        loop {
+            let mut best = None;
+            for &candidate in &candidates {
+                if points.contains(&candidate) {
+                    continue;
+                }
+
+                points.push(candidate);
+                let (candidate_length, _) = spanning_tree(&points);
+                points.pop();
+
+                if candidate_length < best.map_or(length, |(_, best_length)| best_length) {
+                    best = Some((candidate, candidate_length));
+                }
+            }
+
+            let Some((candidate, _)) = best else {
+                break;
+            };
+            points.push(candidate);
+
+            // Steiner points joining fewer than three wires do not make the tree any shorter.
+            loop {
+                (length, neighbors) = spanning_tree(&points);
+                let redundant =
+                    (terminal_count..points.len()).find(|&index| neighbors[index].len() < 3);
+                match redundant {
+                    Some(index) => {
+                        points.remove(index);
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        if points.len() == terminal_count {
+            return Self::default();
+        }
+
+        // Hang the tree from the end of the root wire.
+        let root_end = points.iter().position(|&point| point == root_end).unwrap();
+        // Every point is visited after its parent.
+        let mut parents = vec![usize::MAX; points.len()];
+        let mut visit_order = vec![0; points.len()];
+        let mut stack = vec![root_end];
+        parents[root_end] = root_end;
+        for order in 0.. {
+            let Some(index) = stack.pop() else {
+                break;
+            };
+
+            visit_order[index] = order;
+            for &neighbor in &neighbors[index] {
+                if parents[neighbor] == usize::MAX {
+                    parents[neighbor] = index;
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        // Every Steiner point is carried by the wire coming from one of its children,
+        // preferably the one towards the start of the root wire so the root wire passes through it.
+        let mut carriers = vec![usize::MAX; points.len()];
+        let mut index = points
+            .iter()
+            .position(|&point| point == root_start)
+            .unwrap();
+        while index != root_end {
+            carriers[parents[index]] = index;
+            index = parents[index];
+        }
+
+        for index in terminal_count..points.len() {
+            if carriers[index] == usize::MAX {
+                carriers[index] = neighbors[index]
+                    .iter()
+                    .copied()
+                    .find(|&neighbor| neighbor != parents[index])
+                    .unwrap();
+            }
+        }
+
+        // A wire joins the tree at the parent of the last point it visits,
+        // which is always routed by a wire whose last point was visited earlier.
+        let mut chains = Vec::new();
+        for terminal in 0..terminal_count {
+            let mut chain = Vec::new();
+            let mut index = terminal;
+            while (parents[index] >= terminal_count) && (carriers[parents[index]] == index) {
+                index = parents[index];
+                chain.push(points[index]);
+            }
+
+            chains.push((visit_order[index], points[terminal], chain));
+        }
+
+        chains.sort_unstable_by_key(|&(order, _, _)| order);
+        Self {
+            chains: chains
+                .into_iter()
+                .map(|(_, terminal, chain)| (terminal, chain))
+                .collect(),
+        }
+    }
+
+    /// The Steiner points the wire of the endpoint at `endpoint` passes through, in the order it visits them.
+    pub(crate) fn of(&self, endpoint: Point) -> &[Point] {
+        self.chains
+            .iter()
+            .find(|(terminal, _)| *terminal == endpoint)
+            .map_or(&[], |(_, chain)| chain)
+    }
+
+    /// Whether any Steiner points were found.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.chains.is_empty()
+    }
+
+    /// The position of the endpoint at `endpoint` in the order the endpoints have to be connected in.
+    pub(crate) fn rank(&self, endpoint: Point) -> usize {
+        self.chains
+            .iter()
+            .position(|(terminal, _)| *terminal == endpoint)
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Whether `point` is one of the Steiner points.
+    pub(crate) fn contains(&self, point: Point) -> bool {
+        self.chains.iter().any(|(_, chain)| chain.contains(&point))
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(unsafe_code)]
 
 use std::ffi::c_void;
+use std::mem::MaybeUninit;
 
 use crate::ffi::*;
 use crate::*;
@@ -18,6 +19,58 @@ const ANCHORS: &[Anchor] = &[
     Anchor::new(0, 4),
 ];
 
+/// An endpoint without waypoints.
+fn endpoint(x: i32, y: i32) -> crate::Endpoint<'static> {
+    crate::Endpoint {
+        position: Point { x, y },
+        waypoints: Default::default(),
+    }
+}
+
+/// Groups `(net ID, position)` pairs into nets of endpoints without waypoints.
+fn group_nets(
+    endpoints: impl IntoIterator<Item = (usize, Point)>,
+) -> Vec<Vec<crate::Endpoint<'static>>> {
+    let mut nets = Vec::new();
+    for (net_id, position) in endpoints {
+        if net_id >= nets.len() {
+            nets.resize_with(net_id + 1, Vec::new);
+        }
+
+        nets[net_id].push(endpoint(position.x, position.y));
+    }
+
+    nets
+}
+
+/// The values of a routing output buffer that has been written to.
+fn assume_init<T: Copy>(values: &[MaybeUninit<T>]) -> Vec<T> {
+    values
+        .iter()
+        .map(|value| unsafe { value.assume_init() })
+        .collect()
+}
+
+/// The vertices of each wire of a net.
+fn net_wires(
+    vertices: &[MaybeUninit<Vertex>],
+    wire_views: &[MaybeUninit<WireView>],
+    net_view: &NetView,
+) -> Vec<Vec<Vertex>> {
+    let wire_offset = net_view.wire_offset as usize;
+    let wire_count = net_view.wire_count as usize;
+
+    let mut vertex_offset = net_view.vertex_offset as usize;
+    assume_init(&wire_views[wire_offset..][..wire_count])
+        .into_iter()
+        .map(|wire_view| {
+            let wire_vertices = assume_init(&vertices[vertex_offset..][..wire_view.vertex_count()]);
+            vertex_offset += wire_view.vertex_count();
+            wire_vertices
+        })
+        .collect()
+}
+
 fn init() -> usize {
     use std::sync::Once;
 
@@ -61,7 +114,7 @@ extern "C" fn routing_push_vertex(_: *mut c_void, _: Vertex) {}
 extern "C" fn routing_end_wire_segment(_: *mut c_void, _: bool) {}
 extern "C" fn routing_end_wire(_: *mut c_void) {}
 
-fn test_impl(graph: &Graph, net_points: [Point; 2], expected: &[Vertex]) {
+fn test_impl(graph: &Graph, net_points: [Point; 2], cost_model: CostModel, expected: &[Vertex]) {
     let thread_count = init();
 
     let endpoints = [
@@ -80,30 +133,38 @@ fn test_impl(graph: &Graph, net_points: [Point; 2], expected: &[Vertex]) {
     let nets = [Net {
         endpoint_offset: 0,
         endpoint_count: 2,
+        constraints: NetConstraints::default(),
+        priority: 0,
     }];
 
     let mut vertices = vec![Vertex::default(); expected.len() * thread_count];
     let mut wire_views = vec![WireView::default(); thread_count];
     let mut net_views = vec![NetView::default(); 1];
+    let mut net_statuses = vec![NetStatus::default(); 1];
+    let mut diagnostics = DiagnosticsBuffer {
+        net_statuses: net_statuses.as_mut_slice().into(),
+        diagnostics: [].as_mut_slice().into(),
+        diagnostic_count: 0,
+    };
 
     let replay = ReplayCallbacks {
         context: std::ptr::null_mut(),
 
-        begin_path_finding: begin_path_finding,
-        path_finding_set_g_score: path_finding_set_g_score,
-        path_finding_push_open_queue: path_finding_push_open_queue,
-        path_finding_set_predecessor: path_finding_set_predecessor,
-        path_finding_pop_open_queue: path_finding_pop_open_queue,
-        path_finding_clear_state: path_finding_clear_state,
-        path_finding_insert_path_node: path_finding_insert_path_node,
-        path_finding_remove_path_node: path_finding_remove_path_node,
-        end_path_finding: end_path_finding,
-
-        routing_begin_root_wire: routing_begin_root_wire,
-        routing_begin_branch_wire: routing_begin_branch_wire,
-        routing_push_vertex: routing_push_vertex,
-        routing_end_wire_segment: routing_end_wire_segment,
-        routing_end_wire: routing_end_wire,
+        begin_path_finding,
+        path_finding_set_g_score,
+        path_finding_push_open_queue,
+        path_finding_set_predecessor,
+        path_finding_pop_open_queue,
+        path_finding_clear_state,
+        path_finding_insert_path_node,
+        path_finding_remove_path_node,
+        end_path_finding,
+
+        routing_begin_root_wire,
+        routing_begin_branch_wire,
+        routing_push_vertex,
+        routing_end_wire_segment,
+        routing_end_wire,
     };
 
     let result = unsafe {
@@ -116,7 +177,10 @@ fn test_impl(graph: &Graph, net_points: [Point; 2], expected: &[Vertex]) {
             wire_views.as_mut_slice().into(),
             net_views.as_mut_slice().into(),
             true,
+            NetTopology::RootAndBranches,
+            cost_model,
             replay,
+            &mut diagnostics,
         )
     };
 
@@ -139,6 +203,7 @@ fn straight_impl(minimal: bool) {
     test_impl(
         &graph,
         [Point { x: 0, y: 2 }, Point { x: 4, y: 2 }],
+        CostModel::default(),
         &[Vertex { x: 0.0, y: 2.0 }, Vertex { x: 4.0, y: 2.0 }],
     );
 }
@@ -150,6 +215,7 @@ fn one_bend_impl(minimal: bool) {
     test_impl(
         &graph,
         [Point { x: 0, y: 0 }, Point { x: 4, y: 4 }],
+        CostModel::default(),
         &[
             Vertex { x: 0.0, y: 0.0 },
             Vertex { x: 4.0, y: 0.0 },
@@ -171,6 +237,7 @@ fn two_bends_impl(minimal: bool) {
     test_impl(
         &graph,
         [Point { x: 0, y: 0 }, Point { x: 4, y: 0 }],
+        CostModel::default(),
         &[
             Vertex { x: 0.0, y: 0.0 },
             Vertex { x: 0.0, y: 3.0 },
@@ -180,6 +247,34 @@ fn two_bends_impl(minimal: bool) {
     );
 }
 
+fn bounding_box_clearance_impl(minimal: bool) {
+    const BOUNDING_BOXES: &[BoundingBox] = &[BoundingBox {
+        center: Point { x: 2, y: 0 },
+        half_width: 1,
+        half_height: 2,
+    }];
+
+    let mut graph = Graph::default();
+    graph.build(ANCHORS, BOUNDING_BOXES, minimal);
+
+    // Running along the bounding box is more expensive than the detour.
+    test_impl(
+        &graph,
+        [Point { x: 0, y: 0 }, Point { x: 4, y: 0 }],
+        CostModel {
+            bounding_box_clearance: 1,
+            bounding_box_penalty: 10,
+            ..Default::default()
+        },
+        &[
+            Vertex { x: 0.0, y: 0.0 },
+            Vertex { x: 0.0, y: 4.0 },
+            Vertex { x: 4.0, y: 4.0 },
+            Vertex { x: 4.0, y: 0.0 },
+        ],
+    );
+}
+
 #[test]
 fn straight() {
     straight_impl(false);
@@ -210,6 +305,16 @@ fn two_bends_minimal() {
     two_bends_impl(true);
 }
 
+#[test]
+fn bounding_box_clearance() {
+    bounding_box_clearance_impl(false);
+}
+
+#[test]
+fn bounding_box_clearance_minimal() {
+    bounding_box_clearance_impl(true);
+}
+
 #[cfg(test)]
 mod visual {
     use crate::*;
@@ -294,7 +399,7 @@ mod visual {
                 .iter()
                 .filter_map(|&dir| node.get_neighbor(dir))
             {
-                if class.len() > 0 {
+                if !class.is_empty() {
                     write!(class, " ").unwrap();
                 }
 
@@ -342,3 +447,3166 @@ mod visual {
         svg_out(ANCHORS, BOUNDING_BOXES, &graph, "graph_minimal.svg");
     }
 }
+
+#[cfg(test)]
+mod update {
+    use crate::*;
+    use std::collections::BTreeMap;
+
+    include!("../test_data/graph.rs");
+
+    type CanonicalNode = (bool, [Option<(i32, i32)>; 4]);
+
+    fn canonical(graph: &Graph) -> BTreeMap<(i32, i32), CanonicalNode> {
+        let nodes = graph.nodes();
+        nodes
+            .iter()
+            .map(|node| {
+                let neighbors = Direction::ALL.map(|dir| {
+                    node.get_neighbor(dir)
+                        .map(|index| (nodes[index].position.x, nodes[index].position.y))
+                });
+
+                (
+                    (node.position.x, node.position.y),
+                    (node.is_anchor, neighbors),
+                )
+            })
+            .collect()
+    }
+
+    fn move_component(
+        anchors: &mut [Anchor],
+        bounding_boxes: &mut [BoundingBox],
+        index: u32,
+        offset: Point,
+    ) -> (Vec<Anchor>, Vec<Anchor>, BoundingBoxChange) {
+        let index = BoundingBoxIndex::from_u32(index).unwrap();
+
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for anchor in anchors.iter_mut() {
+            if anchor.bounding_box == index {
+                removed.push(*anchor);
+                anchor.position.x += offset.x;
+                anchor.position.y += offset.y;
+                added.push(*anchor);
+            }
+        }
+
+        let bounding_box = &mut bounding_boxes[index.to_usize().unwrap()];
+        bounding_box.center.x += offset.x;
+        bounding_box.center.y += offset.y;
+
+        let change = BoundingBoxChange {
+            index,
+            bounding_box: *bounding_box,
+        };
+
+        (added, removed, change)
+    }
+
+    fn move_components_impl(minimal: bool, tracks: TrackSettings) {
+        let mut anchors = ANCHORS.to_vec();
+        let mut bounding_boxes = BOUNDING_BOXES.to_vec();
+
+        let mut graph = Graph::default();
+        graph.build_with_tracks(&anchors, &bounding_boxes, minimal, tracks);
+
+        let moves = [
+            (0, Point { x: 40, y: -20 }),
+            (3, Point { x: -130, y: 10 }),
+            (17, Point { x: 5, y: 300 }),
+            (0, Point { x: -40, y: 20 }),
+        ];
+
+        for (index, offset) in moves {
+            let (added, removed, change) =
+                move_component(&mut anchors, &mut bounding_boxes, index, offset);
+            graph.update(&added, &removed, &[change]).unwrap();
+
+            let mut expected = Graph::default();
+            expected.build_with_tracks(&anchors, &bounding_boxes, minimal, tracks);
+
+            assert!(canonical(&graph) == canonical(&expected));
+        }
+    }
+
+    #[test]
+    fn move_components() {
+        move_components_impl(false, TrackSettings::default());
+    }
+
+    #[test]
+    fn move_components_minimal() {
+        move_components_impl(true, TrackSettings::default());
+    }
+
+    const TRACKS: TrackSettings = TrackSettings {
+        track_count: 3,
+        wire_spacing: 3,
+        grid_pitch: 5,
+        channel_track_count: 2,
+    };
+
+    #[test]
+    fn move_components_tracks() {
+        move_components_impl(false, TRACKS);
+    }
+
+    #[test]
+    fn move_components_tracks_minimal() {
+        move_components_impl(true, TRACKS);
+    }
+
+    fn add_remove_anchors_impl(minimal: bool) {
+        let mut anchors = ANCHORS.to_vec();
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, BOUNDING_BOXES, minimal);
+
+        let added = [Anchor::new(-500, -500), Anchor::new(700, 1000)];
+        anchors.extend_from_slice(&added);
+        graph.update(&added, &[], &[]).unwrap();
+
+        let mut expected = Graph::default();
+        expected.build(&anchors, BOUNDING_BOXES, minimal);
+        assert!(canonical(&graph) == canonical(&expected));
+
+        let removed = [anchors.remove(7), anchors.pop().unwrap()];
+        graph.update(&[], &removed, &[]).unwrap();
+
+        expected.build(&anchors, BOUNDING_BOXES, minimal);
+        assert!(canonical(&graph) == canonical(&expected));
+    }
+
+    #[test]
+    fn add_remove_anchors() {
+        add_remove_anchors_impl(false);
+    }
+
+    #[test]
+    fn add_remove_anchors_minimal() {
+        add_remove_anchors_impl(true);
+    }
+
+    #[test]
+    fn move_components_escape_stubs() {
+        // Moving the second component next to the first one leaves no room for the first track in between.
+        let mut anchors = vec![
+            Anchor::new(2, 0)
+                .with_bounding_box(BoundingBoxIndex::from_u32(0).unwrap())
+                .with_connect_direction(Directions::POS_X),
+            Anchor::new(30, 0).with_bounding_box(BoundingBoxIndex::from_u32(1).unwrap()),
+            Anchor::new(-20, 20),
+        ];
+        let mut bounding_boxes = vec![
+            BoundingBox {
+                center: Point { x: 0, y: 0 },
+                half_width: 2,
+                half_height: 2,
+            },
+            BoundingBox {
+                center: Point { x: 30, y: 0 },
+                half_width: 2,
+                half_height: 2,
+            },
+        ];
+
+        let mut graph = Graph::default();
+        graph.build_with_tracks(&anchors, &bounding_boxes, false, TRACKS);
+
+        for offset in [Point { x: -24, y: 1 }, Point { x: 24, y: -1 }] {
+            let (added, removed, change) =
+                move_component(&mut anchors, &mut bounding_boxes, 1, offset);
+            graph.update(&added, &removed, &[change]).unwrap();
+
+            let mut expected = Graph::default();
+            expected.build_with_tracks(&anchors, &bounding_boxes, false, TRACKS);
+
+            assert!(canonical(&graph) == canonical(&expected));
+        }
+    }
+
+    #[test]
+    fn move_composite_component() {
+        // An L-shaped component made of two bounding boxes, with the pin on the first one facing the second.
+        let mut anchors = vec![
+            Anchor::new(8, 0)
+                .with_bounding_box(BoundingBoxIndex::from_u32(0).unwrap())
+                .with_connect_direction(Directions::POS_Y),
+            Anchor::new(8, 20),
+            Anchor::new(-20, 10),
+        ];
+        let mut bounding_boxes = vec![
+            BoundingBox {
+                center: Point { x: 0, y: 0 },
+                half_width: 10,
+                half_height: 2,
+            },
+            BoundingBox {
+                center: Point { x: 8, y: 8 },
+                half_width: 2,
+                half_height: 6,
+            },
+        ];
+        let components = [
+            BoundingBoxIndex::INVALID,
+            BoundingBoxIndex::from_u32(0).unwrap(),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build_with_components(&anchors, &bounding_boxes, &components, false, TRACKS);
+
+        for offset in [Point { x: -30, y: 0 }, Point { x: 30, y: 0 }] {
+            let (added, removed, change) =
+                move_component(&mut anchors, &mut bounding_boxes, 1, offset);
+            graph.update(&added, &removed, &[change]).unwrap();
+
+            let mut expected = Graph::default();
+            expected.build_with_components(&anchors, &bounding_boxes, &components, false, TRACKS);
+
+            assert!(canonical(&graph) == canonical(&expected));
+        }
+    }
+
+    #[test]
+    fn update_after_deserialize() {
+        // The lookup tables of updates are not serialized, so the first update has to rebuild them.
+        let mut anchors = ANCHORS.to_vec();
+        let mut bounding_boxes = BOUNDING_BOXES.to_vec();
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &bounding_boxes, true);
+        let bytes = rmp_serde::to_vec(&graph).unwrap();
+        let mut graph: Graph = rmp_serde::from_slice(&bytes).unwrap();
+
+        let (added, removed, change) = move_component(
+            &mut anchors,
+            &mut bounding_boxes,
+            3,
+            Point { x: -130, y: 10 },
+        );
+        graph.update(&added, &removed, &[change]).unwrap();
+
+        let mut expected = Graph::default();
+        expected.build(&anchors, &bounding_boxes, true);
+        assert!(canonical(&graph) == canonical(&expected));
+    }
+
+    #[test]
+    fn invalid_update() {
+        let mut graph = Graph::default();
+        graph.build(ANCHORS, BOUNDING_BOXES, false);
+        let expected = canonical(&graph);
+
+        let result = graph.update(&[Anchor::new(1, 2)], &[Anchor::new(-1, -1)], &[]);
+        assert_eq!(result, Err(GraphUpdateError::AnchorNotFound));
+
+        let result = graph.update(
+            &[],
+            &[],
+            &[BoundingBoxChange {
+                index: BoundingBoxIndex::from_usize(BOUNDING_BOXES.len()).unwrap(),
+                bounding_box: BOUNDING_BOXES[0],
+            }],
+        );
+        assert_eq!(result, Err(GraphUpdateError::InvalidBoundingBoxIndex));
+
+        assert!(canonical(&graph) == expected);
+    }
+}
+
+mod escape {
+    use crate::*;
+
+    const TRACKS: TrackSettings = TrackSettings {
+        track_count: 1,
+        wire_spacing: 5,
+        grid_pitch: 0,
+        channel_track_count: 0,
+    };
+
+    /// A pin on the right edge of a component, with a second component `gap` units to its right.
+    fn build(gap: i32) -> Graph {
+        let anchors = [
+            Anchor::new(2, 0)
+                .with_bounding_box(BoundingBoxIndex::from_u32(0).unwrap())
+                .with_connect_direction(Directions::POS_X),
+            Anchor::new(-20, 20),
+        ];
+        let bounding_boxes = [
+            BoundingBox {
+                center: Point { x: 0, y: 0 },
+                half_width: 2,
+                half_height: 2,
+            },
+            BoundingBox {
+                center: Point { x: 4 + gap, y: 0 },
+                half_width: 2,
+                half_height: 2,
+            },
+        ];
+
+        let mut graph = Graph::default();
+        graph.build_with_tracks(&anchors, &bounding_boxes, false, TRACKS);
+        graph
+    }
+
+    #[test]
+    fn stub_in_gap() {
+        // The first track lies inside the other component, so the pin escapes into the gap instead.
+        let graph = build(4);
+        assert!(graph.blocked_anchors().is_empty());
+
+        let pin = &graph.nodes()[graph.find_node(Point { x: 2, y: 0 }).unwrap()];
+        let stub = pin.get_neighbor(Direction::PosX).unwrap();
+        assert_eq!(graph.nodes()[stub].position, Point { x: 4, y: 0 });
+    }
+
+    #[test]
+    fn obstructed() {
+        let graph = build(1);
+        assert_eq!(
+            graph.blocked_anchors(),
+            [BlockedAnchor {
+                position: Point { x: 2, y: 0 },
+                reason: EscapeBlockedReason::Obstructed,
+            }]
+        );
+    }
+
+    /// Follows the edges from `start` in direction `dir` as far as possible.
+    fn walk(graph: &Graph, start: Point, dir: Direction) -> Point {
+        let mut index = graph.find_node(start).unwrap();
+        while let Some(next) = graph.nodes()[index].get_neighbor(dir) {
+            index = next;
+        }
+
+        graph.nodes()[index].position
+    }
+
+    #[test]
+    fn composite_component() {
+        // An L-shaped component made of two bounding boxes, with the pin on the first one facing the second.
+        let anchors = [
+            Anchor::new(8, 0)
+                .with_bounding_box(BoundingBoxIndex::from_u32(0).unwrap())
+                .with_connect_direction(Directions::POS_Y),
+            Anchor::new(8, 20),
+        ];
+        let bounding_boxes = [
+            BoundingBox {
+                center: Point { x: 0, y: 0 },
+                half_width: 10,
+                half_height: 2,
+            },
+            BoundingBox {
+                center: Point { x: 8, y: 8 },
+                half_width: 2,
+                half_height: 6,
+            },
+        ];
+
+        // As separate components, the second bounding box blocks the pin.
+        let mut graph = Graph::default();
+        graph.build(&anchors, &bounding_boxes, false);
+        assert_ne!(
+            walk(&graph, Point { x: 8, y: 0 }, Direction::PosY),
+            Point { x: 8, y: 20 }
+        );
+
+        // As parts of the same component, the pin sees through the second bounding box.
+        let components = [
+            BoundingBoxIndex::INVALID,
+            BoundingBoxIndex::from_u32(0).unwrap(),
+        ];
+        graph.build_with_components(
+            &anchors,
+            &bounding_boxes,
+            &components,
+            false,
+            TrackSettings::default(),
+        );
+        assert!(graph.blocked_anchors().is_empty());
+        assert_eq!(
+            walk(&graph, Point { x: 8, y: 0 }, Direction::PosY),
+            Point { x: 8, y: 20 }
+        );
+    }
+
+    #[test]
+    fn chained_component() {
+        // A U-shaped component whose second bounding box points at the third one, which points at the first one.
+        let anchors = [
+            Anchor::new(8, 0)
+                .with_bounding_box(BoundingBoxIndex::from_u32(0).unwrap())
+                .with_connect_direction(Directions::POS_Y),
+            Anchor::new(8, 20),
+        ];
+        let bounding_boxes = [
+            BoundingBox {
+                center: Point { x: 0, y: 0 },
+                half_width: 10,
+                half_height: 2,
+            },
+            BoundingBox {
+                center: Point { x: 8, y: 8 },
+                half_width: 2,
+                half_height: 6,
+            },
+            BoundingBox {
+                center: Point { x: -8, y: 8 },
+                half_width: 2,
+                half_height: 6,
+            },
+        ];
+        let components = [
+            BoundingBoxIndex::INVALID,
+            BoundingBoxIndex::from_u32(2).unwrap(),
+            BoundingBoxIndex::from_u32(0).unwrap(),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build_with_components(
+            &anchors,
+            &bounding_boxes,
+            &components,
+            false,
+            TrackSettings::default(),
+        );
+        assert!(graph.blocked_anchors().is_empty());
+        assert_eq!(
+            walk(&graph, Point { x: 8, y: 0 }, Direction::PosY),
+            Point { x: 8, y: 20 }
+        );
+    }
+}
+
+#[cfg(test)]
+mod keep_out {
+    use crate::*;
+
+    const KEEP_OUT: BoundingBox = BoundingBox {
+        center: Point { x: 0, y: 0 },
+        half_width: 5,
+        half_height: 5,
+    };
+
+    fn build(keep_out: KeepOut) -> Graph {
+        let anchors = [
+            Anchor::new(-10, 0),
+            Anchor::new(10, 0),
+            Anchor::new(-10, 10),
+            Anchor::new(10, 10),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build_with_keep_outs(
+            &anchors,
+            &[],
+            &[],
+            &[keep_out],
+            false,
+            TrackSettings::default(),
+        );
+        graph
+    }
+
+    #[test]
+    fn hard() {
+        let graph = build(KeepOut::hard(KEEP_OUT));
+        let start = graph.find_node(Point { x: -10, y: 0 }).unwrap();
+        assert_eq!(graph.nodes()[start].get_neighbor(Direction::PosX), None);
+        assert!(graph.find_node(Point { x: -6, y: -6 }).is_none());
+
+        let graph = build(KeepOut::hard(KEEP_OUT).with_auto_anchors());
+        assert!(graph.find_node(Point { x: -6, y: -6 }).is_some());
+    }
+
+    #[test]
+    fn soft() {
+        let detours = |graph: &Graph| {
+            let PathFindResult::Found(path) = graph.find_path(
+                Point { x: -10, y: 0 },
+                Point { x: 10, y: 0 },
+                &CostModel::default(),
+            ) else {
+                panic!("no path found");
+            };
+
+            path.nodes()
+                .iter()
+                .any(|node| node.position == Point { x: -10, y: 10 })
+        };
+
+        // The edge through the zone still exists, but is only taken while it is cheap enough.
+        assert!(!detours(&build(KeepOut::soft(KEEP_OUT, 1))));
+        assert!(detours(&build(KeepOut::soft(KEEP_OUT, 10))));
+    }
+
+    #[test]
+    fn ffi_kind() {
+        let build = |kind: u32| {
+            let keep_outs = [ffi::KeepOut {
+                bounding_box: KEEP_OUT,
+                kind,
+                cost_multiplier: 1,
+                auto_anchors: false,
+            }];
+
+            let mut graph = Graph::default();
+            let result = unsafe {
+                ffi::RT_graph_build_with_keep_outs(
+                    &mut graph as *mut _,
+                    [Anchor::new(-10, 0), Anchor::new(10, 0)].as_slice().into(),
+                    [].as_slice().into(),
+                    [].as_slice().into(),
+                    keep_outs.as_slice().into(),
+                    false,
+                    TrackSettings::default(),
+                )
+            };
+
+            (
+                result,
+                graph.find_path(
+                    Point { x: -10, y: 0 },
+                    Point { x: 10, y: 0 },
+                    &CostModel::default(),
+                ),
+            )
+        };
+
+        let (result, path) = build(KeepOutKind::Hard as u32);
+        assert_eq!(result, ffi::Result::Success);
+        assert!(matches!(path, PathFindResult::NotFound));
+
+        let (result, path) = build(KeepOutKind::Soft as u32);
+        assert_eq!(result, ffi::Result::Success);
+        assert!(matches!(path, PathFindResult::Found(_)));
+
+        let (result, _) = build(2);
+        assert_eq!(result, ffi::Result::InvalidArgumentError);
+    }
+}
+
+#[cfg(test)]
+mod session {
+    use crate::*;
+
+    include!("../test_data/graph.rs");
+
+    fn create_nets() -> Vec<Vec<crate::Endpoint<'static>>> {
+        super::group_nets(
+            ENDPOINTS
+                .iter()
+                .map(|endpoint| (endpoint.net_id, endpoint.position)),
+        )
+    }
+
+    #[test]
+    fn reroute_moved_component() {
+        let mut anchors = ANCHORS.to_vec();
+        let mut bounding_boxes = BOUNDING_BOXES.to_vec();
+        let mut nets = create_nets();
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &bounding_boxes, true);
+
+        let mut session = RoutingSession::new();
+        session.set_nets(&nets);
+        assert_eq!(
+            session.route(
+                &graph,
+                true,
+                NetTopology::RootAndBranches,
+                &CostModel::default()
+            ),
+            Ok(nets.len())
+        );
+        assert_eq!(
+            session.route(
+                &graph,
+                true,
+                NetTopology::RootAndBranches,
+                &CostModel::default()
+            ),
+            Ok(0)
+        );
+
+        // Move the first component, including the endpoints connected to it.
+        let index = BoundingBoxIndex::from_u32(0).unwrap();
+        let offset = Point { x: 40, y: -20 };
+
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for anchor in anchors.iter_mut() {
+            if anchor.bounding_box == index {
+                removed.push(*anchor);
+                anchor.position.x += offset.x;
+                anchor.position.y += offset.y;
+                added.push(*anchor);
+            }
+        }
+
+        for endpoint in nets.iter_mut().flatten() {
+            if removed
+                .iter()
+                .any(|anchor| anchor.position == endpoint.position)
+            {
+                endpoint.position.x += offset.x;
+                endpoint.position.y += offset.y;
+            }
+        }
+
+        let old_bounding_box = bounding_boxes[0];
+        bounding_boxes[0].center.x += offset.x;
+        bounding_boxes[0].center.y += offset.y;
+
+        let change = BoundingBoxChange {
+            index,
+            bounding_box: bounding_boxes[0],
+        };
+        graph.update(&added, &removed, &[change]).unwrap();
+
+        let old_vertices: Vec<_> = (0..nets.len())
+            .map(|net| session.vertices(net).to_vec())
+            .collect();
+
+        session.set_nets(&nets);
+        session.invalidate_region(old_bounding_box);
+        session.invalidate_region(bounding_boxes[0]);
+        let routed_count = session
+            .route(
+                &graph,
+                true,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+        assert!(routed_count > 0);
+        assert!(routed_count < nets.len());
+
+        let mut expected = RoutingSession::new();
+        expected.set_nets(&nets);
+        expected
+            .route(
+                &graph,
+                true,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        // Every net was either reused or routed exactly like it would have been from scratch.
+        let mut reused_count = 0;
+        for (net, old_vertices) in old_vertices.iter().enumerate() {
+            if session.vertices(net) == old_vertices {
+                reused_count += 1;
+            } else {
+                assert_eq!(session.vertices(net), expected.vertices(net));
+            }
+        }
+        assert!(reused_count >= nets.len() - routed_count);
+
+        let mut vertices = vec![MaybeUninit::uninit(); session.vertex_count()];
+        let mut wire_views = vec![MaybeUninit::uninit(); session.wire_view_count()];
+        let mut net_views = vec![MaybeUninit::uninit(); session.net_count()];
+        session
+            .write_results(&mut vertices, &mut wire_views, &mut net_views)
+            .unwrap();
+    }
+
+    #[test]
+    fn query_result_sizes() {
+        use crate::ffi::*;
+
+        super::init();
+
+        let mut graph = Graph::default();
+        graph.build(super::ANCHORS, &[], false);
+
+        let endpoints = [(0, 0), (4, 4), (0, 2), (4, 2), (2, 0)].map(|(x, y)| ffi::Endpoint {
+            position: Point { x, y },
+            waypoint_offset: 0,
+            waypoint_count: 0,
+        });
+
+        let nets = [
+            Net {
+                endpoint_offset: 0,
+                endpoint_count: 2,
+                constraints: NetConstraints::default(),
+                priority: 0,
+            },
+            Net {
+                endpoint_offset: 2,
+                endpoint_count: 3,
+                constraints: NetConstraints::default(),
+                priority: 0,
+            },
+        ];
+
+        let mut session = std::ptr::null_mut();
+        assert_eq!(unsafe { RT_session_new(&mut session) }, Result::Success);
+
+        let result = unsafe {
+            RT_session_route_nets(
+                session,
+                &graph as *const _,
+                nets.as_slice().into(),
+                endpoints.as_slice().into(),
+                [].as_slice().into(),
+                true,
+                NetTopology::RootAndBranches,
+                CostModel::default(),
+            )
+        };
+        assert_eq!(result, Result::Success);
+
+        let mut vertex_count = 0;
+        let mut wire_view_count = 0;
+        let mut net_count = 0;
+        let result = unsafe {
+            RT_session_get_result_sizes(
+                session,
+                &mut vertex_count,
+                &mut wire_view_count,
+                &mut net_count,
+            )
+        };
+        assert_eq!(result, Result::Success);
+        assert_eq!(net_count, nets.len());
+
+        let mut vertices = vec![Vertex::default(); vertex_count];
+        let mut wire_views = vec![WireView::default(); wire_view_count];
+        let mut net_views = vec![NetView::default(); net_count];
+
+        let result = unsafe {
+            RT_session_write_results(
+                session,
+                (&mut vertices[1..]).into(),
+                wire_views.as_mut_slice().into(),
+                net_views.as_mut_slice().into(),
+            )
+        };
+        assert_eq!(result, Result::VertexBufferOverflowError);
+
+        // The results stay in the session, so they can be written again without routing.
+        let result = unsafe {
+            RT_session_write_results(
+                session,
+                vertices.as_mut_slice().into(),
+                wire_views.as_mut_slice().into(),
+                net_views.as_mut_slice().into(),
+            )
+        };
+        assert_eq!(result, Result::Success);
+
+        let last = net_views[1];
+        assert_eq!(
+            (last.wire_offset + last.wire_count) as usize,
+            wire_view_count
+        );
+
+        assert_eq!(unsafe { RT_session_free(session) }, Result::Success);
+    }
+}
+
+mod deterministic {
+    use crate::*;
+    use std::mem::MaybeUninit;
+
+    include!("../test_data/graph.rs");
+
+    fn create_nets() -> Vec<Vec<crate::Endpoint<'static>>> {
+        super::group_nets(
+            ENDPOINTS
+                .iter()
+                .map(|endpoint| (endpoint.net_id, endpoint.position)),
+        )
+    }
+
+    fn route(thread_count: usize) -> (Vec<Vertex>, Vec<WireView>, Vec<NetView>) {
+        let nets = create_nets();
+
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(thread_count)
+            .build()
+            .unwrap();
+
+        thread_pool.install(|| {
+            let mut graph = Graph::default();
+            graph.build(ANCHORS, BOUNDING_BOXES, true);
+
+            let mut vertices = vec![MaybeUninit::uninit(); 1 << 16];
+            let mut wire_views = vec![MaybeUninit::uninit(); 1 << 12];
+            let mut net_views = vec![MaybeUninit::uninit(); nets.len()];
+            graph
+                .connect_nets_deterministic(
+                    &nets,
+                    &mut vertices,
+                    &mut wire_views,
+                    &mut net_views,
+                    true,
+                    NetTopology::RootAndBranches,
+                    &CostModel::default(),
+                    &mut RoutingReport::new(),
+                )
+                .unwrap();
+
+            let net_views = super::assume_init(&net_views);
+            let wire_count = net_views
+                .iter()
+                .map(|net_view| net_view.wire_count as usize)
+                .sum();
+            let wire_views = super::assume_init(&wire_views[..wire_count]);
+            let vertex_count = wire_views
+                .iter()
+                .map(|wire_view| wire_view.vertex_count())
+                .sum();
+            let vertices = super::assume_init(&vertices[..vertex_count]);
+
+            (vertices, wire_views, net_views)
+        })
+    }
+
+    #[test]
+    fn independent_of_thread_count() {
+        let expected = route(1);
+        assert_eq!(route(4), expected);
+        assert_eq!(route(4), expected);
+    }
+
+    #[test]
+    fn owned_results() {
+        let (expected_vertices, expected_wire_views, expected_net_views) = route(1);
+
+        let nets: Vec<_> = create_nets().into_iter().map(NetSpec::from).collect();
+
+        let mut graph = Graph::default();
+        graph.build(ANCHORS, BOUNDING_BOXES, true);
+
+        let design = graph
+            .connect_nets(
+                &nets,
+                true,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        assert_eq!(design.nets.len(), expected_net_views.len());
+        assert_eq!(design.vertex_count(), expected_vertices.len());
+        assert_eq!(design.wire_view_count(), expected_wire_views.len());
+
+        for (net, net_view) in design.nets.iter().zip(&expected_net_views) {
+            let vertex_offset = net_view.vertex_offset as usize;
+            let wire_offset = net_view.wire_offset as usize;
+            let wire_count = net_view.wire_count as usize;
+
+            assert_eq!(
+                net.wire_views,
+                &expected_wire_views[wire_offset..(wire_offset + wire_count)]
+            );
+            assert_eq!(
+                net.vertices,
+                &expected_vertices[vertex_offset..(vertex_offset + net.vertices.len())]
+            );
+        }
+    }
+
+    #[test]
+    fn compact_output() {
+        use crate::ffi::*;
+
+        super::init();
+
+        let (expected_vertices, expected_wire_views, expected_net_views) = route(1);
+
+        let nets: Vec<_> = (0..expected_net_views.len())
+            .map(|net_id| {
+                let endpoint_offset = ENDPOINTS
+                    .iter()
+                    .position(|endpoint| endpoint.net_id == net_id)
+                    .unwrap();
+                let endpoint_count = ENDPOINTS
+                    .iter()
+                    .filter(|endpoint| endpoint.net_id == net_id)
+                    .count();
+
+                Net {
+                    endpoint_offset: endpoint_offset as u32,
+                    endpoint_count: endpoint_count as u32,
+                    constraints: NetConstraints::default(),
+                    priority: 0,
+                }
+            })
+            .collect();
+
+        let endpoints: Vec<_> = ENDPOINTS
+            .iter()
+            .map(|endpoint| ffi::Endpoint {
+                position: endpoint.position,
+                waypoint_offset: 0,
+                waypoint_count: 0,
+            })
+            .collect();
+
+        // Buffers of exactly the required size are enough, no matter how the nets are distributed over threads.
+        let mut vertices = vec![Vertex::default(); expected_vertices.len()];
+        let mut wire_views = vec![WireView::default(); expected_wire_views.len()];
+        let mut net_views = vec![NetView::default(); expected_net_views.len()];
+        let mut net_statuses = vec![NetStatus::default(); expected_net_views.len()];
+        let mut diagnostics = DiagnosticsBuffer {
+            net_statuses: net_statuses.as_mut_slice().into(),
+            diagnostics: [].as_mut_slice().into(),
+            diagnostic_count: 0,
+        };
+
+        let mut graph = Graph::default();
+        graph.build(ANCHORS, BOUNDING_BOXES, true);
+
+        let result = unsafe {
+            RT_graph_connect_nets(
+                &graph as *const _,
+                nets.as_slice().into(),
+                endpoints.as_slice().into(),
+                [].as_slice().into(),
+                vertices.as_mut_slice().into(),
+                wire_views.as_mut_slice().into(),
+                net_views.as_mut_slice().into(),
+                true,
+                NetTopology::RootAndBranches,
+                CostModel::default(),
+                &mut diagnostics,
+            )
+        };
+
+        assert_eq!(result, Result::Success);
+        assert_eq!(vertices, expected_vertices);
+        assert_eq!(wire_views, expected_wire_views);
+        assert_eq!(net_views, expected_net_views);
+    }
+}
+
+mod design {
+    use super::endpoint;
+    use crate::*;
+    use std::borrow::Cow;
+
+    fn vertex(x: f32, y: f32) -> Vertex {
+        Vertex { x, y }
+    }
+
+    fn route(endpoints: Vec<crate::Endpoint<'static>>) -> RoutedNet {
+        let mut graph = Graph::default();
+        graph.build(super::ANCHORS, &[], false);
+
+        let mut design = graph
+            .connect_nets(
+                &[endpoints.into()],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        design.nets.pop().unwrap()
+    }
+
+    #[test]
+    fn junction() {
+        let net = route(vec![
+            crate::Endpoint {
+                position: Point { x: 0, y: 2 },
+                waypoints: Default::default(),
+            },
+            crate::Endpoint {
+                position: Point { x: 4, y: 2 },
+                waypoints: Default::default(),
+            },
+            crate::Endpoint {
+                position: Point { x: 2, y: 3 },
+                waypoints: Default::default(),
+            },
+        ]);
+
+        assert_eq!(
+            net.wires,
+            [
+                RoutedWire {
+                    vertices: vec![vertex(0.0, 2.0), vertex(4.0, 2.0)],
+                    start: Some(WireAttachment::Endpoint(0)),
+                    end: Some(WireAttachment::Endpoint(1)),
+                    is_root: true,
+                },
+                RoutedWire {
+                    vertices: vec![vertex(2.0, 3.0), vertex(2.0, 2.0)],
+                    start: Some(WireAttachment::Endpoint(2)),
+                    end: Some(WireAttachment::Junction(0)),
+                    is_root: false,
+                },
+            ]
+        );
+
+        assert_eq!(
+            net.junctions,
+            [Junction {
+                position: vertex(2.0, 2.0),
+                degree: 3,
+            }]
+        );
+
+        assert_eq!(net.status, NetStatus::Routed);
+        assert!(net.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn waypoint() {
+        let net = route(vec![
+            crate::Endpoint {
+                position: Point { x: 0, y: 2 },
+                waypoints: Default::default(),
+            },
+            crate::Endpoint {
+                position: Point { x: 4, y: 2 },
+                waypoints: Default::default(),
+            },
+            crate::Endpoint {
+                position: Point { x: 2, y: 3 },
+                waypoints: Cow::Owned(vec![Point { x: 3, y: 3 }.into()]),
+            },
+        ]);
+
+        // The wire views leading to and away from the waypoint form a single wire.
+        assert_eq!(net.wire_views.len(), 3);
+        assert_eq!(
+            net.wires[1],
+            RoutedWire {
+                vertices: vec![vertex(2.0, 3.0), vertex(3.0, 3.0), vertex(3.0, 2.0)],
+                start: Some(WireAttachment::Endpoint(2)),
+                end: Some(WireAttachment::Junction(0)),
+                is_root: false,
+            }
+        );
+
+        assert_eq!(
+            net.junctions,
+            [Junction {
+                position: vertex(3.0, 2.0),
+                degree: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn diagnostics() {
+        // Anchors that don't connect in any direction are unreachable.
+        let anchors = [
+            Anchor::new(0, 0),
+            Anchor::new(20, 0),
+            Anchor::new(5, 3).with_connect_direction(Directions::NONE),
+            Anchor::new(15, 7).with_connect_direction(Directions::NONE),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+
+        let endpoint = |x, y, waypoints: &[Point]| crate::Endpoint {
+            position: Point { x, y },
+            waypoints: waypoints.iter().copied().map(Waypoint::from).collect(),
+        };
+
+        let design = graph
+            .connect_nets(
+                &[
+                    vec![
+                        endpoint(0, 0, &[]),
+                        endpoint(20, 0, &[Point { x: 15, y: 7 }]),
+                    ]
+                    .into(),
+                    vec![
+                        endpoint(0, 0, &[]),
+                        endpoint(20, 0, &[]),
+                        endpoint(5, 3, &[]),
+                    ]
+                    .into(),
+                ],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        assert_eq!(design.nets[0].status, NetStatus::Partial);
+        assert_eq!(
+            design.nets[0].diagnostics,
+            [Diagnostic {
+                kind: DiagnosticKind::UnreachableWaypoint,
+                start: Point { x: 0, y: 0 },
+                end: Point { x: 15, y: 7 },
+            }]
+        );
+
+        assert_eq!(design.nets[1].status, NetStatus::Fallback);
+        assert_eq!(design.nets[1].diagnostics.len(), 1);
+        assert_eq!(
+            design.nets[1].diagnostics[0].kind,
+            DiagnosticKind::FallbackWire
+        );
+        assert_eq!(design.nets[1].diagnostics[0].start, Point { x: 5, y: 3 });
+    }
+
+    /// A graph with two pins that can only be connected with two bends.
+    fn u_shape() -> Graph {
+        let anchors = [
+            Anchor::new(0, 0).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 20).with_connect_direction(Directions::POS_X),
+            Anchor::new(10, 10),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+        graph
+    }
+
+    #[test]
+    fn max_bends() {
+        let graph = u_shape();
+
+        let endpoints = vec![
+            crate::Endpoint {
+                position: Point { x: 0, y: 0 },
+                waypoints: Cow::Borrowed(&[]),
+            },
+            crate::Endpoint {
+                position: Point { x: 0, y: 20 },
+                waypoints: Cow::Borrowed(&[]),
+            },
+        ];
+
+        let design = graph
+            .connect_nets(
+                &[
+                    NetSpec::from(endpoints.clone())
+                        .with_constraints(NetConstraints::default().with_max_bends(2)),
+                    NetSpec::from(endpoints)
+                        .with_constraints(NetConstraints::default().with_max_bends(1)),
+                ],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        assert_eq!(design.nets[0].status, NetStatus::Routed);
+        assert_eq!(design.nets[1].status, NetStatus::Fallback);
+    }
+
+    #[test]
+    fn invalid_endpoint() {
+        let graph = u_shape();
+
+        let endpoint = |x, y, waypoints: &[Point]| crate::Endpoint {
+            position: Point { x, y },
+            waypoints: waypoints.iter().copied().map(Waypoint::from).collect(),
+        };
+
+        let nets = [
+            vec![endpoint(0, 0, &[]), endpoint(3, 7, &[])].into(),
+            vec![
+                endpoint(0, 0, &[Point { x: 1, y: 1 }]),
+                endpoint(0, 20, &[]),
+            ]
+            .into(),
+            vec![endpoint(0, 0, &[]), endpoint(0, 20, &[])].into(),
+        ];
+
+        // The nets with points off the graph fail on their own, the other net is still routed.
+        let design = graph
+            .connect_nets(
+                &nets,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        assert_eq!(design.nets[0].status, NetStatus::Failed);
+        assert!(design.nets[0].wires.is_empty());
+        assert_eq!(
+            design.nets[0].diagnostics,
+            [Diagnostic {
+                kind: DiagnosticKind::InvalidEndpoint,
+                start: Point { x: 3, y: 7 },
+                end: Point { x: 3, y: 7 },
+            }]
+        );
+
+        assert_eq!(design.nets[1].status, NetStatus::Failed);
+        assert!(design.nets[1].wires.is_empty());
+        assert_eq!(
+            design.nets[1].diagnostics,
+            [Diagnostic {
+                kind: DiagnosticKind::InvalidEndpoint,
+                start: Point { x: 0, y: 0 },
+                end: Point { x: 1, y: 1 },
+            }]
+        );
+
+        assert_eq!(design.nets[2].status, NetStatus::Routed);
+        assert!(!design.nets[2].wires.is_empty());
+
+        // Routing a single net has nowhere to report the diagnostic.
+        let mut vertices = [std::mem::MaybeUninit::uninit(); 16];
+        let mut wire_views = [std::mem::MaybeUninit::uninit(); 4];
+        let result = graph.connect_net(
+            nets[0].endpoints.as_ref(),
+            &mut vertices,
+            &mut wire_views,
+            false,
+            NetTopology::RootAndBranches,
+            &CostModel::default(),
+        );
+        assert_eq!(result, Err(RoutingError::InvalidPoint));
+    }
+
+    #[test]
+    fn max_bends_out_of_range() {
+        let graph = u_shape();
+
+        let endpoints = vec![
+            crate::Endpoint {
+                position: Point { x: 0, y: 0 },
+                waypoints: Cow::Borrowed(&[]),
+            },
+            crate::Endpoint {
+                position: Point { x: 0, y: 20 },
+                waypoints: Cow::Borrowed(&[]),
+            },
+        ];
+
+        let connect = |max_bends| {
+            graph.connect_nets(
+                &[NetSpec::from(endpoints.clone())
+                    .with_constraints(NetConstraints::default().with_max_bends(max_bends))],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+        };
+
+        let design = connect(NetConstraints::MAX_BEND_LIMIT).unwrap();
+        assert_eq!(design.nets[0].status, NetStatus::Routed);
+
+        for max_bends in [NetConstraints::MAX_BEND_LIMIT + 1, 100_000, u32::MAX] {
+            assert_eq!(connect(max_bends), Err(RoutingError::InvalidConstraints));
+        }
+
+        // Through the C API the limit is rejected as an invalid argument instead of panicking.
+        use crate::ffi::*;
+
+        let endpoints = [
+            ffi::Endpoint {
+                position: Point { x: 0, y: 0 },
+                waypoint_offset: 0,
+                waypoint_count: 0,
+            },
+            ffi::Endpoint {
+                position: Point { x: 0, y: 20 },
+                waypoint_offset: 0,
+                waypoint_count: 0,
+            },
+        ];
+        let nets = [Net {
+            endpoint_offset: 0,
+            endpoint_count: 2,
+            constraints: NetConstraints::default().with_max_bends(u32::MAX),
+            priority: 0,
+        }];
+
+        let mut vertices = [Vertex::default(); 16];
+        let mut wire_views = [WireView::default(); 4];
+        let mut net_views = [NetView::default(); 1];
+        let mut net_statuses = [NetStatus::default(); 1];
+        let mut buffer = DiagnosticsBuffer {
+            net_statuses: net_statuses.as_mut_slice().into(),
+            diagnostics: [].as_mut_slice().into(),
+            diagnostic_count: 0,
+        };
+
+        let result = unsafe {
+            RT_graph_connect_nets_by_priority(
+                &graph as *const _,
+                nets.as_slice().into(),
+                endpoints.as_slice().into(),
+                [].as_slice().into(),
+                vertices.as_mut_slice().into(),
+                wire_views.as_mut_slice().into(),
+                net_views.as_mut_slice().into(),
+                false,
+                NetTopology::RootAndBranches,
+                CostModel::default(),
+                CongestionCosts::default(),
+                &mut buffer,
+            )
+        };
+        assert_eq!(result, Result::InvalidArgumentError);
+    }
+
+    #[test]
+    fn max_bends_cheaper_path_with_more_bends() {
+        // The cheapest way to (10, 0) is a detour over (0, 5) and (10, 5) with two bends, because the direct edge
+        // runs through a soft keep-out. The end can only be reached from (10, 0) without another bend though,
+        // so the direct edge has to be taken.
+        let anchors = [
+            Anchor::new(0, 0),
+            Anchor::new(10, 0),
+            Anchor::new(20, 0).with_connect_direction(Directions::NEG_X),
+            Anchor::new(0, 5),
+            Anchor::new(10, 5),
+        ];
+        let keep_out = KeepOut::soft(
+            BoundingBox {
+                center: Point { x: 5, y: 0 },
+                half_width: 3,
+                half_height: 2,
+            },
+            10,
+        );
+
+        let mut graph = Graph::default();
+        graph.build_with_keep_outs(
+            &anchors,
+            &[],
+            &[],
+            &[keep_out],
+            false,
+            TrackSettings::default(),
+        );
+
+        let design = graph
+            .connect_nets(
+                &[NetSpec::from(vec![endpoint(0, 0), endpoint(20, 0)])
+                    .with_constraints(NetConstraints::default().with_max_bends(2))],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        let net = &design.nets[0];
+        assert_eq!(net.status, NetStatus::Routed);
+        assert!(net.wires[0].vertices.iter().all(|vertex| vertex.y == 0.0));
+    }
+
+    #[test]
+    fn routing_report() {
+        let graph = u_shape();
+        let endpoints = vec![
+            crate::Endpoint {
+                position: Point { x: 0, y: 0 },
+                waypoints: Cow::Borrowed(&[]),
+            },
+            crate::Endpoint {
+                position: Point { x: 0, y: 20 },
+                waypoints: Cow::Borrowed(&[]),
+            },
+        ];
+        let nets = [
+            NetSpec::from(endpoints.clone()),
+            NetSpec::from(endpoints).with_constraints(NetConstraints::default().with_max_bends(1)),
+        ];
+
+        let mut vertices = [std::mem::MaybeUninit::uninit(); 16];
+        let mut wire_views = [std::mem::MaybeUninit::uninit(); 4];
+        let mut net_views = [std::mem::MaybeUninit::uninit(); 2];
+
+        let check = |report: &RoutingReport| {
+            assert_eq!(report.net_count(), 2);
+            assert_eq!(report.status(0), NetStatus::Routed);
+            assert!(report.diagnostics(0).is_empty());
+            assert_eq!(report.status(1), NetStatus::Fallback);
+            assert_eq!(report.diagnostics(1)[0].kind, DiagnosticKind::FallbackWire);
+        };
+
+        let mut report = RoutingReport::new();
+        graph
+            .connect_nets_deterministic(
+                &nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+                &mut report,
+            )
+            .unwrap();
+        check(&report);
+
+        let mut report = RoutingReport::new();
+        graph
+            .connect_nets_congestion_aware(
+                &nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+                CongestionCosts::default(),
+                &mut report,
+            )
+            .unwrap();
+        check(&report);
+
+        let mut report = RoutingReport::new();
+        graph
+            .connect_nets_by_priority(
+                &nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+                CongestionCosts::default(),
+                &mut report,
+            )
+            .unwrap();
+        check(&report);
+
+        let mut report = RoutingReport::new();
+        graph
+            .connect_nets_negotiated(
+                &nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+                CongestionCosts::default(),
+                NegotiationSettings::default(),
+                &mut report,
+            )
+            .unwrap();
+        check(&report);
+    }
+
+    #[test]
+    fn diagnostics_buffer() {
+        use crate::ffi::*;
+
+        let graph = u_shape();
+        let endpoints = [
+            ffi::Endpoint {
+                position: Point { x: 0, y: 0 },
+                waypoint_offset: 0,
+                waypoint_count: 0,
+            },
+            ffi::Endpoint {
+                position: Point { x: 0, y: 20 },
+                waypoint_offset: 0,
+                waypoint_count: 0,
+            },
+        ];
+        let nets = [
+            Net {
+                endpoint_offset: 0,
+                endpoint_count: 2,
+                constraints: NetConstraints::default(),
+                priority: 0,
+            },
+            Net {
+                endpoint_offset: 0,
+                endpoint_count: 2,
+                constraints: NetConstraints::default().with_max_bends(1),
+                priority: 0,
+            },
+        ];
+
+        let mut vertices = [Vertex::default(); 16];
+        let mut wire_views = [WireView::default(); 4];
+        let mut net_views = [NetView::default(); 2];
+        let mut net_statuses = [NetStatus::default(); 2];
+
+        let mut connect = |net_statuses: &mut [NetStatus],
+                           diagnostics: &mut [NetDiagnostic],
+                           buffer: *mut DiagnosticsBuffer| {
+            if !buffer.is_null() {
+                unsafe {
+                    buffer.write(DiagnosticsBuffer {
+                        net_statuses: net_statuses.into(),
+                        diagnostics: diagnostics.into(),
+                        diagnostic_count: 0,
+                    });
+                }
+            }
+
+            unsafe {
+                RT_graph_connect_nets_by_priority(
+                    &graph as *const _,
+                    nets.as_slice().into(),
+                    endpoints.as_slice().into(),
+                    [].as_slice().into(),
+                    vertices.as_mut_slice().into(),
+                    wire_views.as_mut_slice().into(),
+                    net_views.as_mut_slice().into(),
+                    false,
+                    NetTopology::RootAndBranches,
+                    CostModel::default(),
+                    CongestionCosts::default(),
+                    buffer,
+                )
+            }
+        };
+
+        let mut buffer = std::mem::MaybeUninit::<DiagnosticsBuffer>::uninit();
+        let result = connect(&mut net_statuses, &mut [], std::ptr::null_mut());
+        assert_eq!(result, Result::NullPointerError);
+
+        let result = connect(&mut net_statuses[..1], &mut [], buffer.as_mut_ptr());
+        assert_eq!(result, Result::InvalidArgumentError);
+
+        // Diagnostics that do not fit are still counted.
+        let result = connect(&mut net_statuses, &mut [], buffer.as_mut_ptr());
+        assert_eq!(result, Result::Success);
+        assert_eq!(net_statuses, [NetStatus::Routed, NetStatus::Fallback]);
+        let diagnostic_count = unsafe { buffer.assume_init() }.diagnostic_count;
+        assert!(diagnostic_count > 0);
+
+        let mut diagnostics = vec![
+            NetDiagnostic {
+                net: u32::MAX,
+                diagnostic: Diagnostic {
+                    kind: DiagnosticKind::UnreachableWaypoint,
+                    start: Point::default(),
+                    end: Point::default(),
+                },
+            };
+            diagnostic_count
+        ];
+        let result = connect(&mut net_statuses, &mut diagnostics, buffer.as_mut_ptr());
+        assert_eq!(result, Result::Success);
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.net == 1));
+        assert_eq!(diagnostics[0].diagnostic.kind, DiagnosticKind::FallbackWire);
+    }
+
+    #[test]
+    fn waypoint_order() {
+        let anchors = [
+            Anchor::new(-10, 0),
+            Anchor::new(0, 0),
+            Anchor::new(6, 0),
+            Anchor::new(30, 0),
+            Anchor::new(-10, 10),
+            Anchor::new(30, 10),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+
+        let route = |waypoints: &'static [Point], waypoint_order| {
+            let endpoints = vec![
+                crate::Endpoint {
+                    position: Point { x: 0, y: 0 },
+                    waypoints: waypoints.iter().copied().map(Waypoint::from).collect(),
+                },
+                crate::Endpoint {
+                    position: Point { x: 30, y: 0 },
+                    waypoints: Cow::Borrowed(&[]),
+                },
+            ];
+
+            let mut design = graph
+                .connect_nets(
+                    &[NetSpec::from(endpoints).with_constraints(
+                        NetConstraints::default().with_waypoint_order(waypoint_order),
+                    )],
+                    false,
+                    NetTopology::RootAndBranches,
+                    &CostModel::default(),
+                )
+                .unwrap();
+
+            let net = design.nets.pop().unwrap();
+            assert_eq!(net.status, NetStatus::Routed);
+
+            // The X coordinates of the waypoints in the order the root wire visits them.
+            let visits: Vec<_> = net.wires[0]
+                .vertices
+                .iter()
+                .filter(|vertex| (vertex.y == 0.0) && [-10.0, 6.0].contains(&vertex.x))
+                .map(|vertex| vertex.x)
+                .collect();
+            visits
+        };
+
+        const WAYPOINTS: &[Point] = &[Point { x: -10, y: 0 }, Point { x: 6, y: 0 }];
+        const REVERSED_WAYPOINTS: &[Point] = &[Point { x: 6, y: 0 }, Point { x: -10, y: 0 }];
+
+        // Going to the closest waypoint first doubles back twice.
+        assert_eq!(route(WAYPOINTS, WaypointOrder::Nearest), [6.0, -10.0]);
+        assert_eq!(
+            route(REVERSED_WAYPOINTS, WaypointOrder::Given),
+            [6.0, -10.0]
+        );
+        assert_eq!(route(WAYPOINTS, WaypointOrder::Given), [-10.0, 6.0]);
+        assert_eq!(
+            route(REVERSED_WAYPOINTS, WaypointOrder::Optimized),
+            [-10.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn pin_side() {
+        // The sightline of the start passes straight through the pin, into its illegal side.
+        let anchors = [
+            Anchor::new(0, 0),
+            Anchor::new(10, 0).with_connect_direction(Directions::NEG_X),
+            Anchor::new(20, 0),
+            Anchor::new(0, 10),
+            Anchor::new(20, 10),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+
+        let pin = graph.find_node(Point { x: 10, y: 0 }).unwrap();
+        assert!(graph.nodes()[pin].get_neighbor(Direction::PosX).is_some());
+
+        let PathFindResult::Found(path) = graph.find_path(
+            Point { x: 20, y: 0 },
+            Point { x: 10, y: 0 },
+            &CostModel::default(),
+        ) else {
+            panic!("no path found");
+        };
+
+        let [.., before_pin, _] = path.nodes() else {
+            panic!("path too short");
+        };
+        assert!(before_pin.position.x < 10);
+    }
+
+    #[test]
+    fn waypoint_direction() {
+        let anchors = [
+            Anchor::new(0, 0),
+            Anchor::new(30, 0),
+            Anchor::new(10, 10),
+            Anchor::new(10, -10),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+
+        let route = |direction| {
+            let endpoints = vec![
+                crate::Endpoint {
+                    position: Point { x: 0, y: 0 },
+                    waypoints: Cow::Owned(vec![Waypoint {
+                        position: Point { x: 10, y: 0 },
+                        direction,
+                    }]),
+                },
+                crate::Endpoint {
+                    position: Point { x: 30, y: 0 },
+                    waypoints: Cow::Borrowed(&[]),
+                },
+            ];
+
+            let mut design = graph
+                .connect_nets(
+                    &[endpoints.into()],
+                    false,
+                    NetTopology::RootAndBranches,
+                    &CostModel::default(),
+                )
+                .unwrap();
+
+            let net = design.nets.pop().unwrap();
+            assert_eq!(net.status, NetStatus::Routed);
+            net.wires[0].vertices.clone()
+        };
+
+        assert!(route(None).iter().all(|vertex| vertex.y == 0.0));
+
+        // The wire has to cross the waypoint from top to bottom.
+        let vertices = route(Some(Direction::NegY));
+        let index = vertices
+            .iter()
+            .position(|&position| position == vertex(10.0, 0.0))
+            .unwrap();
+        assert_eq!(vertices[index - 1].x, 10.0);
+        assert!(vertices[index - 1].y > 0.0);
+        assert_eq!(vertices[index + 1].x, 10.0);
+        assert!(vertices[index + 1].y < 0.0);
+    }
+
+    #[test]
+    fn ffi_waypoint_direction() {
+        use crate::ffi::*;
+
+        let anchors = [
+            Anchor::new(0, 0),
+            Anchor::new(30, 0),
+            Anchor::new(10, 10),
+            Anchor::new(10, -10),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+
+        let endpoints = [
+            ffi::Endpoint {
+                position: Point { x: 0, y: 0 },
+                waypoint_offset: 0,
+                waypoint_count: 1,
+            },
+            ffi::Endpoint {
+                position: Point { x: 30, y: 0 },
+                waypoint_offset: 0,
+                waypoint_count: 0,
+            },
+        ];
+        let nets = [Net {
+            endpoint_offset: 0,
+            endpoint_count: 2,
+            constraints: NetConstraints::default(),
+            priority: 0,
+        }];
+
+        let mut vertices = [Vertex::default(); 16];
+        let mut wire_views = [WireView::default(); 4];
+        let mut net_views = [NetView::default(); 1];
+        let mut net_statuses = [NetStatus::default(); 1];
+
+        let mut connect = |direction| {
+            let waypoints = [ffi::Waypoint {
+                position: Point { x: 10, y: 0 },
+                direction,
+            }];
+            let mut buffer = DiagnosticsBuffer {
+                net_statuses: net_statuses.as_mut_slice().into(),
+                diagnostics: [].as_mut_slice().into(),
+                diagnostic_count: 0,
+            };
+
+            let result = unsafe {
+                RT_graph_connect_nets_by_priority(
+                    &graph as *const _,
+                    nets.as_slice().into(),
+                    endpoints.as_slice().into(),
+                    waypoints.as_slice().into(),
+                    vertices.as_mut_slice().into(),
+                    wire_views.as_mut_slice().into(),
+                    net_views.as_mut_slice().into(),
+                    false,
+                    NetTopology::RootAndBranches,
+                    CostModel::default(),
+                    CongestionCosts::default(),
+                    &mut buffer,
+                )
+            };
+
+            (result, vertices)
+        };
+
+        // A waypoint can only require a single direction.
+        let (result, _) = connect(Directions::POS_Y | Directions::NEG_Y);
+        assert_eq!(result, Result::InvalidArgumentError);
+
+        // The wire has to cross the waypoint from top to bottom.
+        let (result, vertices) = connect(Directions::NEG_Y);
+        assert_eq!(result, Result::Success);
+        let index = vertices
+            .iter()
+            .position(|&position| position == vertex(10.0, 0.0))
+            .unwrap();
+        assert_eq!(vertices[index - 1].x, 10.0);
+        assert!(vertices[index - 1].y > 0.0);
+    }
+
+    #[test]
+    fn waypoint_on_root_wire() {
+        // The waypoint lies on the root wire, so the branch ends in a junction right after reaching it.
+        let net = route(vec![
+            crate::Endpoint {
+                position: Point { x: 0, y: 2 },
+                waypoints: Default::default(),
+            },
+            crate::Endpoint {
+                position: Point { x: 4, y: 2 },
+                waypoints: Default::default(),
+            },
+            crate::Endpoint {
+                position: Point { x: 2, y: 3 },
+                waypoints: Cow::Owned(vec![Point { x: 2, y: 2 }.into()]),
+            },
+        ]);
+
+        assert_eq!(
+            net.wires[1],
+            RoutedWire {
+                vertices: vec![vertex(2.0, 3.0), vertex(2.0, 2.0)],
+                start: Some(WireAttachment::Endpoint(2)),
+                end: Some(WireAttachment::Junction(0)),
+                is_root: false,
+            }
+        );
+
+        assert_eq!(
+            net.junctions,
+            [Junction {
+                position: vertex(2.0, 2.0),
+                degree: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn root_fallback_after_waypoint() {
+        // The end of the root wire cannot connect in any direction, so the part after the waypoint is a fallback wire.
+        let anchors = [
+            Anchor::new(0, 0),
+            Anchor::new(10, 0),
+            Anchor::new(20, 5).with_connect_direction(Directions::NONE),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+
+        let endpoints = vec![
+            crate::Endpoint {
+                position: Point { x: 0, y: 0 },
+                waypoints: Cow::Owned(vec![Point { x: 10, y: 0 }.into()]),
+            },
+            crate::Endpoint {
+                position: Point { x: 20, y: 5 },
+                waypoints: Default::default(),
+            },
+        ];
+
+        let design = graph
+            .connect_nets(
+                &[endpoints.into()],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        let net = &design.nets[0];
+        assert_eq!(net.status, NetStatus::Fallback);
+        assert_eq!(net.wire_views.len(), 2);
+
+        // Both wire views form a single wire from one endpoint to the other without jumps.
+        let [wire] = net.wires.as_slice() else {
+            panic!("expected a single wire");
+        };
+        let ends = [wire.start, wire.end];
+        assert!(ends.contains(&Some(WireAttachment::Endpoint(0))));
+        assert!(ends.contains(&Some(WireAttachment::Endpoint(1))));
+        assert!(wire.segments().all(|[a, b]| (a.x == b.x) || (a.y == b.y)));
+        assert!(wire.vertices.contains(&vertex(10.0, 0.0)));
+    }
+
+    #[test]
+    fn disconnected_wire_views() {
+        let endpoints = [
+            crate::Endpoint {
+                position: Point { x: 0, y: 0 },
+                waypoints: Default::default(),
+            },
+            crate::Endpoint {
+                position: Point { x: 4, y: 4 },
+                waypoints: Default::default(),
+            },
+        ];
+
+        // The second wire view of the root wire does not start where the first one ends.
+        let vertices = vec![
+            vertex(0.0, 0.0),
+            vertex(2.0, 0.0),
+            vertex(2.0, 4.0),
+            vertex(4.0, 4.0),
+        ];
+        let wire_views = vec![
+            WireView::new(2, false, true).unwrap(),
+            WireView::new(2, false, true).unwrap(),
+        ];
+
+        let net = RoutedNet::new(
+            &endpoints,
+            vertices,
+            wire_views,
+            &Default::default(),
+            Default::default(),
+        );
+        assert_eq!(net, Err(RoutingError::DisconnectedWire));
+    }
+
+    #[test]
+    fn fallback_avoids_bounding_boxes() {
+        // The endpoint above the bounding box cannot connect in any direction, so it needs a fallback wire.
+        let bounding_boxes = [BoundingBox {
+            center: Point { x: 5, y: 0 },
+            half_width: 2,
+            half_height: 2,
+        }];
+        let anchors = [
+            Anchor::new(0, -5),
+            Anchor::new(20, -5),
+            Anchor::new(5, 6).with_connect_direction(Directions::NONE),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &bounding_boxes, false);
+
+        let design = graph
+            .connect_nets(
+                &[vec![endpoint(0, -5), endpoint(20, -5), endpoint(5, 6)].into()],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        let net = &design.nets[0];
+        assert_eq!(net.status, NetStatus::Fallback);
+
+        // The fallback wire takes a detour instead of going straight through the bounding box.
+        assert_eq!(
+            net.wires[0].vertices,
+            [vertex(20.0, -5.0), vertex(20.0, 6.0), vertex(5.0, 6.0)]
+        );
+    }
+
+    #[test]
+    fn fallback_search_is_bounded() {
+        // Going around the wall between the endpoints needs lines far outside the search area of the fallback wire,
+        // so it cuts through the wall instead.
+        let bounding_boxes = [BoundingBox {
+            center: Point { x: 5, y: 0 },
+            half_width: 10,
+            half_height: 100,
+        }];
+        let mut anchors = vec![
+            Anchor::new(-15, 0).with_connect_direction(Directions::NONE),
+            Anchor::new(25, 0),
+        ];
+        anchors.extend((1..=30).flat_map(|y| [Anchor::new(-40, y), Anchor::new(-40, -y)]));
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &bounding_boxes, false);
+
+        let design = graph
+            .connect_nets(
+                &[vec![endpoint(-15, 0), endpoint(25, 0)].into()],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        let net = &design.nets[0];
+        assert_eq!(net.status, NetStatus::Fallback);
+        assert!(net.wires[0].vertices.iter().all(|vertex| vertex.y == 0.0));
+    }
+
+    #[test]
+    fn branch_joins_fallback_segment() {
+        // The root wire has to be a fallback wire, the branch should join it in the middle of a segment.
+        let anchors = [
+            Anchor::new(0, 5).with_connect_direction(Directions::NONE),
+            Anchor::new(40, 0),
+            Anchor::new(10, 12),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+
+        let design = graph
+            .connect_nets(
+                &[vec![endpoint(0, 5), endpoint(40, 0), endpoint(10, 12)].into()],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        let net = &design.nets[0];
+        assert_eq!(net.status, NetStatus::Fallback);
+        assert_eq!(
+            net.wires[0].vertices,
+            [vertex(0.0, 5.0), vertex(0.0, 0.0), vertex(40.0, 0.0)]
+        );
+
+        // Instead of running along the fallback wire to its corner, the branch ends in a junction on it.
+        assert_eq!(
+            net.wires[1].vertices,
+            [vertex(10.0, 12.0), vertex(10.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn branch_joins_one_of_many_segments() {
+        // The root wire zigzags through its waypoints, so there are too many segments to check all of them.
+        let waypoints: Vec<Point> = (1..10)
+            .map(|i| Point {
+                x: i * 10,
+                y: (i % 2) * 10,
+            })
+            .collect();
+
+        let mut anchors = vec![Anchor::new(0, 0), Anchor::new(100, 10), Anchor::new(55, 40)];
+        anchors.extend(
+            waypoints
+                .iter()
+                .map(|waypoint| Anchor::new(waypoint.x, waypoint.y)),
+        );
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+
+        let endpoint = |x, y, waypoints: &[Point]| crate::Endpoint {
+            position: Point { x, y },
+            waypoints: waypoints.iter().copied().map(Waypoint::from).collect(),
+        };
+
+        let design = graph
+            .connect_nets(
+                &[NetSpec::from(vec![
+                    endpoint(0, 0, &waypoints),
+                    endpoint(100, 10, &[]),
+                    endpoint(55, 40, &[]),
+                ])
+                .with_constraints(
+                    NetConstraints::default().with_waypoint_order(WaypointOrder::Given),
+                )],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        let net = &design.nets[0];
+        assert_eq!(net.status, NetStatus::Routed);
+        assert_eq!(net.wires[0].vertices.len(), 20);
+        assert_eq!(
+            net.wires[1].vertices,
+            [vertex(55.0, 40.0), vertex(55.0, 10.0)]
+        );
+        assert_eq!(
+            net.junctions,
+            [Junction {
+                position: vertex(55.0, 10.0),
+                degree: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn branch_joins_graph_segment() {
+        // The root wire bends once, the branch should join its first segment between the two corners.
+        let anchors = [
+            Anchor::new(0, 0).with_connect_direction(Directions::POS_X),
+            Anchor::new(40, 30).with_connect_direction(Directions::NEG_Y),
+            Anchor::new(20, -10).with_connect_direction(Directions::POS_Y),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(&anchors, &[], false);
+
+        let design = graph
+            .connect_nets(
+                &[vec![endpoint(0, 0), endpoint(40, 30), endpoint(20, -10)].into()],
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        let net = &design.nets[0];
+        assert_eq!(net.status, NetStatus::Routed);
+        assert_eq!(
+            net.wires[0].vertices,
+            [vertex(0.0, 0.0), vertex(40.0, 0.0), vertex(40.0, 30.0)]
+        );
+        assert_eq!(
+            net.wires[1].vertices,
+            [vertex(20.0, -10.0), vertex(20.0, 0.0)]
+        );
+        assert_eq!(
+            net.junctions,
+            [Junction {
+                position: vertex(20.0, 0.0),
+                degree: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn deserialize_old_query() {
+        // The layout of queries serialized before nets had constraints and waypoints had directions.
+        #[derive(serde::Serialize)]
+        struct OldNet {
+            endpoint_offset: u32,
+            endpoint_count: u32,
+        }
+
+        #[derive(serde::Serialize)]
+        struct OldQuery<'a> {
+            graph: &'a graph::GraphData,
+            nets: Vec<OldNet>,
+            endpoints: Vec<ffi::Endpoint>,
+            waypoints: Vec<Point>,
+            perform_centering: bool,
+        }
+
+        let mut graph = Graph::default();
+        graph.build(super::ANCHORS, &[], false);
+
+        let endpoint = |x, y, waypoint_count| ffi::Endpoint {
+            position: Point { x, y },
+            waypoint_offset: 0,
+            waypoint_count,
+        };
+
+        let old_query = OldQuery {
+            graph: &graph.data,
+            nets: vec![OldNet {
+                endpoint_offset: 0,
+                endpoint_count: 2,
+            }],
+            endpoints: vec![endpoint(0, 0, 1), endpoint(4, 0, 0)],
+            waypoints: vec![Point { x: 2, y: 2 }],
+            perform_centering: true,
+        };
+
+        let bytes = rmp_serde::to_vec(&old_query).unwrap();
+        let query: ffi::GraphConnectNetsQuery = rmp_serde::from_slice(&bytes).unwrap();
+
+        assert_eq!(query.nets.len(), 1);
+        assert_eq!(query.nets[0].endpoint_count, 2);
+        assert_eq!(query.nets[0].constraints, NetConstraints::default());
+        assert_eq!(query.nets[0].priority, 0);
+        assert_eq!(query.endpoints.len(), 2);
+        assert_eq!(query.waypoints.len(), 1);
+        assert_eq!(query.waypoints[0].position, Point { x: 2, y: 2 });
+        assert_eq!(query.waypoints[0].direction, Directions::empty());
+        assert!(query.perform_centering);
+        assert_eq!(query.topology, NetTopology::default());
+        assert_eq!(query.cost_model, CostModel::default());
+    }
+
+    #[test]
+    fn round_trip_query() {
+        let mut graph = Graph::default();
+        graph.build(super::ANCHORS, &[], false);
+
+        let query = ffi::GraphConnectNetsQuery {
+            graph: graph.data.clone(),
+            nets: vec![ffi::Net {
+                endpoint_offset: 0,
+                endpoint_count: 2,
+                constraints: NetConstraints {
+                    max_bends: 3,
+                    ..Default::default()
+                },
+                priority: 7,
+            }],
+            endpoints: Vec::new(),
+            waypoints: vec![ffi::Waypoint {
+                position: Point { x: 2, y: 2 },
+                direction: Directions::POS_Y,
+            }],
+            perform_centering: false,
+            topology: NetTopology::Steiner,
+            cost_model: CostModel::default(),
+        };
+
+        let bytes = rmp_serde::to_vec(&query).unwrap();
+        let query: ffi::GraphConnectNetsQuery = rmp_serde::from_slice(&bytes).unwrap();
+
+        assert_eq!(query.nets[0].constraints.max_bends, 3);
+        assert_eq!(query.nets[0].priority, 7);
+        assert_eq!(query.waypoints[0].position, Point { x: 2, y: 2 });
+        assert_eq!(query.waypoints[0].direction, Directions::POS_Y);
+        assert_eq!(query.topology, NetTopology::Steiner);
+    }
+}
+
+mod congestion {
+    use super::endpoint;
+    use crate::*;
+    use std::mem::MaybeUninit;
+
+    /// The vertices of the root wire of a net.
+    fn root_wire(
+        vertices: &[MaybeUninit<Vertex>],
+        wire_views: &[MaybeUninit<WireView>],
+        net_view: &MaybeUninit<NetView>,
+    ) -> Vec<Vertex> {
+        let net_view = super::assume_init(std::slice::from_ref(net_view))[0];
+        super::net_wires(vertices, wire_views, &net_view).swap_remove(0)
+    }
+
+    #[test]
+    fn avoid_overlap() {
+        let mut graph = Graph::default();
+        graph.build(super::ANCHORS, &[], false);
+
+        // The second net runs on top of the first one if routed on its own.
+        let nets = [
+            [endpoint(1, 2), endpoint(3, 2)],
+            [endpoint(0, 2), endpoint(4, 2)],
+        ];
+
+        let mut vertices = [MaybeUninit::uninit(); 16];
+        let mut wire_views = [MaybeUninit::uninit(); 4];
+        let mut net_views = [MaybeUninit::uninit(); 2];
+        graph
+            .connect_nets_congestion_aware(
+                &nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+                CongestionCosts::default(),
+                &mut RoutingReport::new(),
+            )
+            .unwrap();
+
+        let detour = root_wire(&vertices, &wire_views, &net_views[1]);
+        assert_eq!(detour.first(), Some(&Vertex { x: 0.0, y: 2.0 }));
+        assert_eq!(detour.last(), Some(&Vertex { x: 4.0, y: 2.0 }));
+        assert!(detour.iter().any(|vertex| vertex.y != 2.0));
+    }
+
+    #[test]
+    fn priority_order() {
+        let mut graph = Graph::default();
+        graph.build(super::ANCHORS, &[], false);
+
+        // The second net takes the straight route because it is routed first, so the first net has to detour.
+        let nets = [
+            NetSpec::from(vec![endpoint(0, 2), endpoint(4, 2)]),
+            NetSpec::from(vec![endpoint(1, 2), endpoint(3, 2)]).with_priority(1),
+        ];
+
+        let mut vertices = [MaybeUninit::uninit(); 16];
+        let mut wire_views = [MaybeUninit::uninit(); 4];
+        let mut net_views = [MaybeUninit::uninit(); 2];
+        graph
+            .connect_nets_by_priority(
+                &nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+                CongestionCosts::default(),
+                &mut RoutingReport::new(),
+            )
+            .unwrap();
+
+        let straight = root_wire(&vertices, &wire_views, &net_views[1]);
+        assert!(straight.iter().all(|vertex| vertex.y == 2.0));
+
+        let detour = root_wire(&vertices, &wire_views, &net_views[0]);
+        assert_eq!(detour.first(), Some(&Vertex { x: 0.0, y: 2.0 }));
+        assert_eq!(detour.last(), Some(&Vertex { x: 4.0, y: 2.0 }));
+        assert!(detour.iter().any(|vertex| vertex.y != 2.0));
+    }
+
+    #[test]
+    fn negotiate_overlap() {
+        let mut graph = Graph::default();
+        graph.build(super::ANCHORS, &[], false);
+
+        // Running on top of the first net is cheaper for the second net than any detour,
+        // so only the first net can give way.
+        let nets = [
+            [endpoint(0, 1), endpoint(0, 4)],
+            [endpoint(0, 2), endpoint(0, 3)],
+        ];
+
+        let congestion_costs = CongestionCosts {
+            overlap_penalty: 1,
+            forbid_overlap: false,
+            crossing_penalty: 0,
+        };
+
+        let mut vertices = [MaybeUninit::uninit(); 16];
+        let mut wire_views = [MaybeUninit::uninit(); 4];
+        let mut net_views = [MaybeUninit::uninit(); 2];
+        let outcome = graph
+            .connect_nets_negotiated(
+                &nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+                congestion_costs,
+                NegotiationSettings::default(),
+                &mut RoutingReport::new(),
+            )
+            .unwrap();
+
+        assert_eq!(outcome.overused_channel_count, 0);
+        assert!(outcome.iterations > 0);
+
+        let detour = root_wire(&vertices, &wire_views, &net_views[0]);
+        assert_eq!(detour.first(), Some(&Vertex { x: 0.0, y: 1.0 }));
+        assert_eq!(detour.last(), Some(&Vertex { x: 0.0, y: 4.0 }));
+        assert!(detour.iter().any(|vertex| vertex.x != 0.0));
+    }
+
+    #[test]
+    fn distinct_tracks() {
+        const ANCHORS: &[Anchor] = &[
+            Anchor::new(-5, 2),
+            Anchor::new(5, 2),
+            Anchor::new(-6, 1),
+            Anchor::new(6, 1),
+        ];
+
+        // Going around the bottom of the bounding box is a long detour.
+        const BOUNDING_BOXES: &[BoundingBox] = &[BoundingBox {
+            center: Point { x: 0, y: -20 },
+            half_width: 2,
+            half_height: 22,
+        }];
+
+        let mut graph = Graph::default();
+        graph.build_with_tracks(
+            ANCHORS,
+            BOUNDING_BOXES,
+            false,
+            TrackSettings {
+                track_count: 2,
+                ..Default::default()
+            },
+        );
+
+        let nets = [
+            [endpoint(-5, 2), endpoint(5, 2)],
+            [endpoint(-6, 1), endpoint(6, 1)],
+        ];
+
+        let mut vertices = [MaybeUninit::uninit(); 16];
+        let mut wire_views = [MaybeUninit::uninit(); 4];
+        let mut net_views = [MaybeUninit::uninit(); 2];
+        graph
+            .connect_nets_congestion_aware(
+                &nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+                CongestionCosts::default(),
+                &mut RoutingReport::new(),
+            )
+            .unwrap();
+
+        // Both nets run above the bounding box, each on its own track.
+        let inner = root_wire(&vertices, &wire_views, &net_views[0]);
+        assert!(inner.contains(&Vertex { x: 5.0, y: 3.0 }));
+        assert!(inner.iter().all(|vertex| vertex.y <= 3.0));
+
+        let outer = root_wire(&vertices, &wire_views, &net_views[1]);
+        assert!(outer.contains(&Vertex { x: -6.0, y: 4.0 }));
+        assert!(outer.contains(&Vertex { x: 6.0, y: 4.0 }));
+    }
+
+    #[test]
+    fn distinct_tracks_parallel() {
+        const ANCHORS: &[Anchor] = &[
+            Anchor::new(-5, 2),
+            Anchor::new(5, 2),
+            Anchor::new(-6, 1),
+            Anchor::new(6, 1),
+        ];
+
+        const BOUNDING_BOXES: &[BoundingBox] = &[BoundingBox {
+            center: Point { x: 0, y: -20 },
+            half_width: 2,
+            half_height: 22,
+        }];
+
+        super::init();
+
+        let mut graph = Graph::default();
+        graph.build_with_tracks(
+            ANCHORS,
+            BOUNDING_BOXES,
+            false,
+            TrackSettings {
+                track_count: 2,
+                ..Default::default()
+            },
+        );
+
+        let nets = [
+            vec![endpoint(-5, 2), endpoint(5, 2)],
+            vec![endpoint(-6, 1), endpoint(6, 1)],
+        ];
+
+        // The highest point of every net, where it runs above the bounding box.
+        let top = |wire: &[Vertex]| wire.iter().map(|vertex| vertex.y).fold(f32::MIN, f32::max);
+
+        let design = graph
+            .connect_nets(
+                &nets.clone().map(NetSpec::from),
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        let mut tops: Vec<_> = design.nets.iter().map(|net| top(&net.vertices)).collect();
+        tops.sort_by(f32::total_cmp);
+        assert_eq!(tops, [3.0, 4.0]);
+
+        let mut vertices = [MaybeUninit::uninit(); 16];
+        let mut wire_views = [MaybeUninit::uninit(); 4];
+        let mut net_views = [MaybeUninit::uninit(); 2];
+        graph
+            .connect_nets_deterministic(
+                &nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                false,
+                NetTopology::RootAndBranches,
+                &CostModel::default(),
+                &mut RoutingReport::new(),
+            )
+            .unwrap();
+
+        let mut tops: Vec<_> = net_views
+            .iter()
+            .map(|net_view| top(&root_wire(&vertices, &wire_views, net_view)))
+            .collect();
+        tops.sort_by(f32::total_cmp);
+        assert_eq!(tops, [3.0, 4.0]);
+    }
+
+    #[test]
+    fn channel_tracks() {
+        // A channel from X 2 to X 8 between two bounding boxes facing each other from Y -2 to 2.
+        const BOUNDING_BOXES: &[BoundingBox] = &[
+            BoundingBox {
+                center: Point { x: 0, y: 0 },
+                half_width: 2,
+                half_height: 2,
+            },
+            BoundingBox {
+                center: Point { x: 10, y: 1 },
+                half_width: 2,
+                half_height: 3,
+            },
+        ];
+
+        let build = |channel_track_count| {
+            let mut graph = Graph::default();
+            graph.build_with_tracks(
+                &[],
+                BOUNDING_BOXES,
+                false,
+                TrackSettings {
+                    channel_track_count,
+                    ..Default::default()
+                },
+            );
+            graph
+        };
+
+        // Only the tracks around the bounding boxes run through the channel.
+        let graph = build(0);
+        assert!(graph.data.find_node(Point { x: 3, y: -3 }).is_some());
+        assert!(graph.data.find_node(Point { x: 7, y: -3 }).is_some());
+        assert!(graph.data.find_node(Point { x: 4, y: -3 }).is_none());
+
+        // The channel tracks continue the ones of the left bounding box up to the ones of the right one.
+        let graph = build(5);
+        for x in 4..=6 {
+            assert!(graph.data.find_node(Point { x, y: -3 }).is_some());
+            assert!(graph.data.find_node(Point { x, y: 3 }).is_some());
+        }
+
+        let graph = build(1);
+        assert!(graph.data.find_node(Point { x: 4, y: -3 }).is_some());
+        assert!(graph.data.find_node(Point { x: 5, y: -3 }).is_none());
+    }
+
+    #[test]
+    fn tracks_at_coordinate_limits() {
+        const BOUNDING_BOXES: &[BoundingBox] = &[
+            BoundingBox {
+                center: Point {
+                    x: i32::MIN + 4,
+                    y: i32::MIN + 4,
+                },
+                half_width: 2,
+                half_height: 2,
+            },
+            BoundingBox {
+                center: Point {
+                    x: i32::MAX - 4,
+                    y: i32::MAX - 4,
+                },
+                half_width: 2,
+                half_height: 2,
+            },
+        ];
+
+        let mut graph = Graph::default();
+        graph.build_with_tracks(
+            &[],
+            BOUNDING_BOXES,
+            false,
+            TrackSettings {
+                track_count: 3,
+                wire_spacing: 3,
+                grid_pitch: 7,
+                channel_track_count: 2,
+            },
+        );
+
+        // The outer tracks are clamped to the range of coordinates.
+        assert!(graph
+            .data
+            .find_node(Point {
+                x: i32::MIN,
+                y: i32::MIN
+            })
+            .is_some());
+        assert!(graph
+            .data
+            .find_node(Point {
+                x: i32::MAX,
+                y: i32::MAX
+            })
+            .is_some());
+    }
+
+    /// The cost of net 1 leaving the center of a plus shaped graph in direction `dir`,
+    /// after arriving there in direction `straight_dir`, while net 0 occupies `occupied`.
+    fn center_cost(occupied: &[Point], dir: Direction, straight_dir: Direction) -> Option<u32> {
+        use crate::congestion::{Congestion, Occupancy};
+
+        const ANCHORS: &[Anchor] = &[
+            Anchor::new(0, 1),
+            Anchor::new(2, 1),
+            Anchor::new(1, 0),
+            Anchor::new(1, 2),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(ANCHORS, &[], false);
+
+        let mut occupancy = Occupancy::default();
+        occupancy.insert_segments(&graph.data, occupied, 0);
+
+        let congestion = Congestion {
+            occupancy: &mut occupancy,
+            net: 1,
+            costs: CongestionCosts::default(),
+        };
+
+        let center = graph.data.find_node(Point { x: 1, y: 1 }).unwrap();
+        congestion.edge_cost(&graph.data, center, dir, Some(straight_dir))
+    }
+
+    #[test]
+    fn crossing_and_touching() {
+        let costs = CongestionCosts::default();
+        let vertical = [Point { x: 1, y: 0 }, Point { x: 1, y: 2 }];
+        let ends_at_center = [Point { x: 1, y: 0 }, Point { x: 1, y: 1 }];
+
+        // Passing straight through a wire that passes straight through as well is a crossing.
+        assert_eq!(
+            center_cost(&vertical, Direction::PosX, Direction::PosX),
+            Some(costs.crossing_penalty)
+        );
+
+        // Bending on top of a wire, or passing a wire that ends there, only touches it.
+        assert_eq!(
+            center_cost(&vertical, Direction::PosX, Direction::NegY),
+            Some(costs.overlap_penalty)
+        );
+        assert_eq!(
+            center_cost(&ends_at_center, Direction::PosX, Direction::PosX),
+            Some(costs.overlap_penalty)
+        );
+
+        // Wires leaving on the other side of the path do not touch it.
+        assert_eq!(
+            center_cost(&ends_at_center, Direction::PosY, Direction::PosY),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn fallback_wire_occupancy() {
+        use crate::congestion::{Congestion, Occupancy};
+
+        const ANCHORS: &[Anchor] = &[Anchor::new(0, 0), Anchor::new(2, 0), Anchor::new(4, 0)];
+
+        let mut graph = Graph::default();
+        graph.build(ANCHORS, &[], false);
+
+        // Fallback wires do not have to start or end at nodes, but occupy all edges they cover.
+        let mut occupancy = Occupancy::default();
+        occupancy.insert_segments(
+            &graph.data,
+            &[Point { x: -1, y: 0 }, Point { x: 3, y: 0 }],
+            0,
+        );
+
+        let congestion = Congestion {
+            occupancy: &mut occupancy,
+            net: 1,
+            costs: CongestionCosts {
+                forbid_overlap: true,
+                ..Default::default()
+            },
+        };
+
+        let node = |x| graph.data.find_node(Point { x, y: 0 }).unwrap();
+        assert_eq!(
+            congestion.edge_cost(&graph.data, node(0), Direction::PosX, None),
+            None
+        );
+        assert_eq!(
+            congestion.edge_cost(&graph.data, node(2), Direction::PosX, None),
+            Some(0)
+        );
+    }
+}
+
+#[cfg(test)]
+mod bidirectional {
+    use crate::*;
+
+    include!("../test_data/graph.rs");
+
+    /// The cost of a path under a cost model with only length and bend weights.
+    fn path_cost(path: &Path, cost_model: &CostModel) -> u32 {
+        let mut cost = 0;
+        let mut prev_dir = None;
+        for segment in path.nodes().windows(2) {
+            let length = segment[0]
+                .position
+                .manhatten_distance_to(segment[1].position);
+            let dir = segment[0].bend_direction;
+
+            cost += length * cost_model.length_weight;
+            if dir != prev_dir {
+                cost += length * cost_model.bend_length_weight + cost_model.bend_penalty;
+            }
+
+            prev_dir = dir;
+        }
+
+        cost
+    }
+
+    /// Asserts that both searches find paths of the same cost between pairs of endpoints.
+    fn assert_same_cost(cost_model: &CostModel) {
+        let mut graph = Graph::default();
+        graph.build(ANCHORS, BOUNDING_BOXES, false);
+
+        let bidirectional = SearchOptions {
+            bidirectional: true,
+        };
+
+        for pair in ENDPOINTS.windows(2).step_by(5) {
+            let (start, end) = (pair[0].position, pair[1].position);
+
+            match (
+                graph.find_path(start, end, cost_model),
+                graph.find_path_with_options(start, end, cost_model, &bidirectional),
+            ) {
+                (PathFindResult::Found(forward), PathFindResult::Found(both)) => {
+                    assert_eq!(
+                        path_cost(&forward, cost_model),
+                        path_cost(&both, cost_model),
+                        "paths from {start:?} to {end:?} differ in cost",
+                    );
+                }
+                (PathFindResult::NotFound, PathFindResult::NotFound) => (),
+                _ => panic!("only one search found a path from {start:?} to {end:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn shortest_path() {
+        // Without bend costs, both searches have to find a path of the same length.
+        assert_same_cost(&CostModel {
+            bend_length_weight: 0,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn cheapest_path_with_bends() {
+        assert_same_cost(&CostModel::default());
+        assert_same_cost(&CostModel {
+            bend_penalty: 8,
+            ..Default::default()
+        });
+    }
+
+    /// Records the nodes the path finder reached.
+    #[derive(Default)]
+    struct ReachedNodes {
+        start: Option<u32>,
+        reached: Vec<u32>,
+        popped: Vec<u32>,
+    }
+
+    impl ReplayCapture for ReachedNodes {
+        fn begin_path_finding(&mut self, start: u32, _: impl Iterator<Item = u32>, _: bool) {
+            self.start = Some(start);
+        }
+        fn path_finding_set_g_score(&mut self, node: u32, g_score: u32) {
+            assert!((g_score > 0) || (Some(node) == self.start));
+            self.reached.push(node);
+        }
+        fn path_finding_push_open_queue(&mut self, _: u32, _: u32) {}
+        fn path_finding_set_predecessor(&mut self, _: u32, _: u32) {}
+        fn path_finding_pop_open_queue(&mut self, node: u32) {
+            self.popped.push(node);
+        }
+        fn path_finding_clear_state(&mut self) {}
+        fn path_finding_insert_path_node(&mut self, _: usize, _: u32) {}
+        fn path_finding_remove_path_node(&mut self, _: usize) {}
+        fn end_path_finding(&mut self, _: bool) {}
+
+        fn routing_begin_root_wire(&mut self, _: Point, _: Point) {}
+        fn routing_begin_branch_wire(&mut self, _: Point) {}
+        fn routing_push_vertex(&mut self, _: Vertex) {}
+        fn routing_end_wire_segment(&mut self, _: bool) {}
+        fn routing_end_wire(&mut self) {}
+    }
+
+    #[test]
+    fn replay_only_shows_forward_search() {
+        let mut graph = Graph::default();
+        graph.build(ANCHORS, BOUNDING_BOXES, false);
+
+        let (start, end) = (ENDPOINTS[0].position, ENDPOINTS[1].position);
+        let mut replay = ReachedNodes::default();
+        let result = graph.find_path_replay(
+            start,
+            end,
+            &CostModel::default(),
+            &SearchOptions {
+                bidirectional: true,
+            },
+            &mut replay,
+        );
+        assert!(matches!(result, PathFindResult::Found(_)));
+
+        // The end is only reached by the forward search, after paths from the start to it have been explored.
+        let end_index = graph.find_node(end).unwrap() as u32;
+        assert_ne!(replay.reached.first(), Some(&end_index));
+        assert!(replay
+            .popped
+            .iter()
+            .all(|node| replay.reached.contains(node)));
+    }
+}
+
+mod waypoints {
+    use crate::waypoints::optimize_order;
+    use crate::Point;
+
+    fn points(xs: &[i32]) -> Vec<Point> {
+        xs.iter().map(|&x| Point { x, y: 0 }).collect()
+    }
+
+    #[test]
+    fn exact() {
+        let mut waypoints = points(&[6, -10, 20]);
+        optimize_order(
+            Point { x: 0, y: 0 },
+            &mut waypoints,
+            Some(Point { x: 30, y: 0 }),
+        );
+        assert_eq!(waypoints, points(&[-10, 6, 20]));
+
+        // Without a fixed end, the tour finishes at whichever waypoint is cheapest.
+        let mut waypoints = points(&[-3, 5, -10]);
+        optimize_order(Point { x: 0, y: 0 }, &mut waypoints, None);
+        assert_eq!(waypoints, points(&[5, -3, -10]));
+    }
+
+    #[test]
+    fn two_opt() {
+        let mut waypoints = points(&[5, -1, 3, 9, -4, 1, 7, -2, 8, 4, -3]);
+        optimize_order(
+            Point { x: 0, y: 0 },
+            &mut waypoints,
+            Some(Point { x: 10, y: 0 }),
+        );
+        assert_eq!(waypoints, points(&[-1, -2, -3, -4, 1, 3, 4, 5, 7, 8, 9]));
+    }
+}
+
+mod radix_heap {
+    use crate::radix_heap::RadixHeap;
+
+    #[test]
+    fn pop_order() {
+        let mut heap = RadixHeap::default();
+        for key in [7, 3, 1024, 3, 0, u32::MAX, 12] {
+            heap.push(key, key);
+        }
+
+        assert_eq!(heap.peek(), Some((0, &0)));
+        assert_eq!(heap.pop(), Some((0, 0)));
+        assert_eq!(heap.pop(), Some((3, 3)));
+
+        // Keys pushed later only have to be at least the last popped one.
+        heap.push(3, 3);
+        heap.push(5, 5);
+
+        let mut keys = Vec::new();
+        while let Some((key, _)) = heap.pop() {
+            keys.push(key);
+        }
+        assert_eq!(keys, [3, 3, 5, 7, 12, 1024, u32::MAX]);
+    }
+}
+
+mod bus {
+    use super::endpoint;
+    use crate::*;
+    use std::mem::MaybeUninit;
+
+    fn connect_bus(anchors: &[Anchor], nets: &[[crate::Endpoint; 2]]) -> (usize, Vec<Vertex>) {
+        connect_bus_around(anchors, &[], nets)
+    }
+
+    fn connect_bus_around<'a, N: RoutableNet<'a>>(
+        anchors: &[Anchor],
+        bounding_boxes: &[BoundingBox],
+        nets: &[N],
+    ) -> (usize, Vec<Vertex>) {
+        let mut graph = Graph::default();
+        graph.build(anchors, bounding_boxes, false);
+
+        let mut vertices = [MaybeUninit::uninit(); 32];
+        let mut wire_views = [MaybeUninit::uninit(); 4];
+        let mut net_views = vec![MaybeUninit::uninit(); nets.len()];
+        let bundled_count = graph
+            .connect_bus(
+                nets,
+                &mut vertices,
+                &mut wire_views,
+                &mut net_views,
+                &CostModel::default(),
+                &mut RoutingReport::new(),
+            )
+            .unwrap();
+
+        let vertices = super::assume_init(&net_views)
+            .iter()
+            .flat_map(|net_view| super::net_wires(&vertices, &wire_views, net_view))
+            .flatten()
+            .collect();
+
+        (bundled_count, vertices)
+    }
+
+    #[test]
+    fn nested_bends() {
+        // A column of pins on the left connects to a row of pins at the bottom.
+        let anchors = [
+            Anchor::new(0, 0).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 2).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 4).with_connect_direction(Directions::POS_X),
+            Anchor::new(10, 20).with_connect_direction(Directions::NEG_Y),
+            Anchor::new(8, 20).with_connect_direction(Directions::NEG_Y),
+            Anchor::new(6, 20).with_connect_direction(Directions::NEG_Y),
+        ];
+
+        let nets = [
+            [endpoint(0, 0), endpoint(10, 20)],
+            [endpoint(0, 2), endpoint(8, 20)],
+            [endpoint(0, 4), endpoint(6, 20)],
+        ];
+
+        let (bundled_count, vertices) = connect_bus(&anchors, &nets);
+        assert_eq!(bundled_count, 3);
+        assert_eq!(
+            vertices,
+            [
+                Vertex { x: 0.0, y: 0.0 },
+                Vertex { x: 10.0, y: 0.0 },
+                Vertex { x: 10.0, y: 20.0 },
+                Vertex { x: 0.0, y: 2.0 },
+                Vertex { x: 8.0, y: 2.0 },
+                Vertex { x: 8.0, y: 20.0 },
+                Vertex { x: 0.0, y: 4.0 },
+                Vertex { x: 6.0, y: 4.0 },
+                Vertex { x: 6.0, y: 20.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn misaligned_end() {
+        let anchors = [
+            Anchor::new(0, 0).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 2).with_connect_direction(Directions::POS_X),
+            Anchor::new(10, 20).with_connect_direction(Directions::NEG_Y),
+            Anchor::new(12, 20).with_connect_direction(Directions::NEG_Y),
+        ];
+
+        // The second end lies on the wrong side of the bundle, so that net is routed on its own.
+        let nets = [
+            [endpoint(0, 0), endpoint(10, 20)],
+            [endpoint(0, 2), endpoint(12, 20)],
+        ];
+
+        let (bundled_count, _) = connect_bus(&anchors, &nets);
+        assert_eq!(bundled_count, 1);
+    }
+
+    #[test]
+    fn leader_keeps_room_for_bundle() {
+        let anchors = [
+            Anchor::new(0, 0).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 2).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 4).with_connect_direction(Directions::POS_X),
+            Anchor::new(30, 20).with_connect_direction(Directions::NEG_X),
+            Anchor::new(30, 22).with_connect_direction(Directions::NEG_X),
+            Anchor::new(30, 24).with_connect_direction(Directions::NEG_X),
+        ];
+
+        let bounding_boxes = [
+            BoundingBox {
+                center: Point { x: 23, y: 10 },
+                half_width: 2,
+                half_height: 2,
+            },
+            BoundingBox {
+                center: Point { x: 14, y: 41 },
+                half_width: 1,
+                half_height: 1,
+            },
+        ];
+
+        let nets = [
+            [endpoint(0, 0), endpoint(30, 20)],
+            [endpoint(0, 2), endpoint(30, 22)],
+            [endpoint(0, 4), endpoint(30, 24)],
+        ];
+
+        // The shortest path runs right next to the first bounding box, which leaves no room for the other wires,
+        // so the leading wire turns early enough for all of them to pass the bounding box on its left.
+        let (bundled_count, vertices) = connect_bus_around(&anchors, &bounding_boxes, &nets);
+        assert_eq!(bundled_count, 3);
+        assert_eq!(vertices[1], Vertex { x: 16.0, y: 0.0 });
+    }
+
+    #[test]
+    fn uneven_pitch() {
+        let anchors = [
+            Anchor::new(0, 0).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 2).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 6).with_connect_direction(Directions::POS_X),
+            Anchor::new(10, 20).with_connect_direction(Directions::NEG_Y),
+            Anchor::new(8, 20).with_connect_direction(Directions::NEG_Y),
+            Anchor::new(4, 20).with_connect_direction(Directions::NEG_Y),
+        ];
+
+        // The third net lines up with the bundle, but is not spaced evenly with the others.
+        let nets = [
+            [endpoint(0, 0), endpoint(10, 20)],
+            [endpoint(0, 2), endpoint(8, 20)],
+            [endpoint(0, 6), endpoint(4, 20)],
+        ];
+
+        let (bundled_count, _) = connect_bus(&anchors, &nets);
+        assert_eq!(bundled_count, 2);
+    }
+
+    #[test]
+    fn follower_with_waypoints_or_constraints() {
+        let anchors = [
+            Anchor::new(0, 0).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 2).with_connect_direction(Directions::POS_X),
+            Anchor::new(10, 20).with_connect_direction(Directions::NEG_Y),
+            Anchor::new(8, 20).with_connect_direction(Directions::NEG_Y),
+            Anchor::new(2, 12),
+        ];
+
+        let net = |constraints| NetSpec {
+            endpoints: vec![endpoint(0, 2), endpoint(8, 20)].into(),
+            constraints,
+            priority: 0,
+        };
+
+        let leader = NetSpec {
+            endpoints: vec![endpoint(0, 0), endpoint(10, 20)].into(),
+            constraints: NetConstraints::default(),
+            priority: 0,
+        };
+
+        let (bundled_count, _) =
+            connect_bus_around(&anchors, &[], &[leader.clone(), net(Default::default())]);
+        assert_eq!(bundled_count, 2);
+
+        let (bundled_count, _) = connect_bus_around(
+            &anchors,
+            &[],
+            &[
+                leader.clone(),
+                net(NetConstraints::default()
+                    .with_direction_preference(DirectionPreference::Vertical)),
+            ],
+        );
+        assert_eq!(bundled_count, 1);
+
+        // The bundle does not pass through the waypoint of the follower.
+        let mut follower = net(Default::default());
+        follower.endpoints.to_mut()[0].waypoints = vec![Point { x: 2, y: 12 }.into()].into();
+        let (bundled_count, vertices) = connect_bus_around(&anchors, &[], &[leader, follower]);
+        assert_eq!(bundled_count, 1);
+        assert!(vertices.contains(&Vertex { x: 2.0, y: 12.0 }));
+    }
+
+    #[test]
+    fn follower_with_fewer_bends() {
+        let anchors = [
+            Anchor::new(0, 0).with_connect_direction(Directions::POS_X),
+            Anchor::new(0, 2).with_connect_direction(Directions::POS_X),
+            Anchor::new(30, 20).with_connect_direction(Directions::NEG_X),
+            Anchor::new(30, 22).with_connect_direction(Directions::NEG_X),
+            Anchor::new(20, 40),
+        ];
+
+        let net = |endpoints, max_bends| NetSpec {
+            endpoints,
+            constraints: NetConstraints::default().with_max_bends(max_bends),
+            priority: 0,
+        };
+
+        let leader = net(vec![endpoint(0, 0), endpoint(30, 20)].into(), 0);
+        let follower = |max_bends| net(vec![endpoint(0, 2), endpoint(30, 22)].into(), max_bends);
+
+        let (bundled_count, _) = connect_bus_around(&anchors, &[], &[leader.clone(), follower(2)]);
+        assert_eq!(bundled_count, 2);
+
+        // The bundle bends twice, which is more than the follower allows.
+        let (bundled_count, _) = connect_bus_around(&anchors, &[], &[leader, follower(1)]);
+        assert_eq!(bundled_count, 1);
+    }
+}
+
+mod topology {
+    use super::endpoint;
+    use crate::*;
+    use std::mem::MaybeUninit;
+
+    /// The total length of all wires of a net.
+    fn wire_length(graph: &Graph, endpoints: &[crate::Endpoint], topology: NetTopology) -> f32 {
+        let mut vertices = [MaybeUninit::uninit(); 64];
+        let mut wire_views = [MaybeUninit::uninit(); 16];
+        let net_view = graph
+            .connect_net(
+                endpoints,
+                &mut vertices,
+                &mut wire_views,
+                false,
+                topology,
+                &CostModel::default(),
+            )
+            .unwrap();
+
+        let mut length = 0.0;
+        for wire_vertices in super::net_wires(&vertices, &wire_views, &net_view) {
+            for pair in wire_vertices.windows(2) {
+                let [a, b] = pair else { unreachable!() };
+                length += (a.x - b.x).abs() + (a.y - b.y).abs();
+            }
+        }
+
+        length
+    }
+
+    #[test]
+    fn steiner() {
+        const ANCHORS: &[Anchor] = &[
+            Anchor::new(0, 0),
+            Anchor::new(10, 0),
+            Anchor::new(20, 0).with_connect_direction(Directions::POS_Y),
+            Anchor::new(0, 10),
+            Anchor::new(0, 20),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(ANCHORS, &[], false);
+
+        // The root wire between the two most distant endpoints runs around the empty corner of the L,
+        // because it can only arrive at its end from above.
+        let endpoints = [
+            endpoint(0, 20),
+            endpoint(20, 0),
+            endpoint(0, 0),
+            endpoint(10, 0),
+            endpoint(0, 10),
+        ];
+
+        assert_eq!(
+            wire_length(&graph, &endpoints, NetTopology::RootAndBranches),
+            70.0
+        );
+        assert_eq!(wire_length(&graph, &endpoints, NetTopology::Steiner), 40.0);
+    }
+
+    #[test]
+    fn steiner_points() {
+        const ANCHORS: &[Anchor] = &[
+            Anchor::new(0, 10),
+            Anchor::new(10, 30),
+            Anchor::new(20, 0),
+            Anchor::new(30, 20),
+            Anchor::new(40, 40),
+        ];
+
+        let mut graph = Graph::default();
+        graph.build(ANCHORS, &[], false);
+
+        // The shortest tree joins the wires at (10, 10) and (10, 20), where there are no endpoints.
+        let endpoints: Vec<_> = ANCHORS
+            .iter()
+            .map(|anchor| endpoint(anchor.position.x, anchor.position.y))
+            .collect();
+
+        assert_eq!(
+            wire_length(&graph, &endpoints, NetTopology::RootAndBranches),
+            130.0
+        );
+        assert_eq!(wire_length(&graph, &endpoints, NetTopology::Steiner), 100.0);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::Point;
+
+/// Up to this many waypoints the shortest tour is found exactly, above it the tour is only improved locally.
+const EXACT_LIMIT: usize = 8;
+
+#[inline]
+fn distance(a: impl Into<Point>, b: impl Into<Point>) -> u64 {
+    a.into().manhatten_distance_to(b.into()) as u64
+}
+
+/// Reorders `waypoints` so the tour starting at `start`, visiting all waypoints and finally arriving at `end`
+/// (if there is one) becomes as short as possible.
+///
+/// Distances are Manhattan distances, so obstacles between waypoints are not taken into account.
+pub(crate) fn optimize_order<T: Copy + Into<Point>>(
+    start: Point,
+    waypoints: &mut [T],
+    end: Option<Point>,
+) {
+    if waypoints.len() < 2 {
+        return;
+    }
+
+    if waypoints.len() <= EXACT_LIMIT {
+        order_exact(start, waypoints, end);
+    } else {
+        order_nearest(start, waypoints);
+        improve_two_opt(start, waypoints, end);
+    }
+}
+
+/// Finds the shortest tour using the Held-Karp algorithm.
+fn order_exact<T: Copy + Into<Point>>(start: Point, waypoints: &mut [T], end: Option<Point>) {
+    let n = waypoints.len();
+    let set_count = 1usize << n;
+
+    // The length of the shortest tour from `start` through the waypoints in `set` ending at `last`
+    // is stored at `set * n + last`, together with the waypoint visited before `last`.
+    let mut cost = vec![u64::MAX; set_count * n];
+    let mut pred = vec![usize::MAX; set_count * n];
+
+    for (last, &waypoint) in waypoints.iter().enumerate() {
+        cost[(1 << last) * n + last] = distance(start, waypoint);
+    }
+
+    // Every set is numerically greater than all of its subsets, so they are always complete when it is reached.
+    for set in 1..set_count {
+        for last in 0..n {
+            let current_cost = cost[set * n + last];
+            if current_cost == u64::MAX {
+                continue;
+            }
+
+            for next in 0..n {
+                if (set & (1 << next)) != 0 {
+                    continue;
+                }
+
+                let next_index = (set | (1 << next)) * n + next;
+                let next_cost = current_cost + distance(waypoints[last], waypoints[next]);
+                if next_cost < cost[next_index] {
+                    cost[next_index] = next_cost;
+                    pred[next_index] = last;
+                }
+            }
+        }
+    }
+
+    let mut set = set_count - 1;
+    let mut last = (0..n)
+        .min_by_key(|&last| {
+            let end_cost = end.map_or(0, |end| distance(waypoints[last], end));
+            cost[set * n + last] + end_cost
+        })
+        .expect("empty waypoint list");
+
+    let mut order = Vec::with_capacity(n);
+    loop {
+        order.push(waypoints[last]);
+
+        let pred_last = pred[set * n + last];
+        set &= !(1 << last);
+        if set == 0 {
+            break;
+        }
+
+        last = pred_last;
+    }
+
+    for (waypoint, ordered) in waypoints.iter_mut().zip(order.into_iter().rev()) {
+        *waypoint = ordered;
+    }
+}
+
+/// Builds a tour by always continuing to the closest waypoint that was not visited yet.
+fn order_nearest<T: Copy + Into<Point>>(start: Point, waypoints: &mut [T]) {
+    let mut current = start;
+    for i in 0..waypoints.len() {
+        let nearest = (i..waypoints.len())
+            .min_by_key(|&j| distance(current, waypoints[j]))
+            .unwrap();
+
+        waypoints.swap(i, nearest);
+        current = waypoints[i].into();
+    }
+}
+
+/// Shortens a tour by reversing parts of it until no reversal makes it any shorter.
+fn improve_two_opt<T: Copy + Into<Point>>(start: Point, waypoints: &mut [T], end: Option<Point>) {
+    let n = waypoints.len();
+
+    loop {
+        let mut improved = false;
+
+        for i in 0..n {
+            let before = if i == 0 {
+                start
+            } else {
+                waypoints[i - 1].into()
+            };
+
+            for j in (i + 1)..n {
+                let after = if (j + 1) < n {
+                    Some(waypoints[j + 1].into())
+                } else {
+                    end
+                };
+
+                let old_cost = distance(before, waypoints[i])
+                    + after.map_or(0, |after| distance(waypoints[j], after));
+                let new_cost = distance(before, waypoints[j])
+                    + after.map_or(0, |after| distance(waypoints[i], after));
+
+                if new_cost < old_cost {
+                    waypoints[i..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+}